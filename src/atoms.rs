@@ -0,0 +1,100 @@
+use xcb::x;
+
+xcb::atoms_struct! {
+    #[derive(Copy, Clone, Debug)]
+    pub struct Atoms {
+        pub net_supported => b"_NET_SUPPORTED" only_if_exists = false,
+        pub net_supporting_wm_check => b"_NET_SUPPORTING_WM_CHECK" only_if_exists = false,
+        pub net_client_list => b"_NET_CLIENT_LIST" only_if_exists = false,
+        pub net_active_window => b"_NET_ACTIVE_WINDOW" only_if_exists = false,
+        pub net_close_window => b"_NET_CLOSE_WINDOW" only_if_exists = false,
+        pub net_wm_name => b"_NET_WM_NAME" only_if_exists = false,
+        pub net_wm_pid => b"_NET_WM_PID" only_if_exists = false,
+        pub net_wm_desktop => b"_NET_WM_DESKTOP" only_if_exists = false,
+        pub net_wm_moveresize => b"_NET_WM_MOVERESIZE" only_if_exists = false,
+        pub net_moveresize_window => b"_NET_MOVERESIZE_WINDOW" only_if_exists = false,
+        pub net_wm_user_time => b"_NET_WM_USER_TIME" only_if_exists = false,
+        pub net_wm_user_time_window => b"_NET_WM_USER_TIME_WINDOW" only_if_exists = false,
+        pub wm_protocols => b"WM_PROTOCOLS" only_if_exists = false,
+        pub wm_delete_window => b"WM_DELETE_WINDOW" only_if_exists = false,
+        pub wm_take_focus => b"WM_TAKE_FOCUS" only_if_exists = false,
+        pub wm_state => b"WM_STATE" only_if_exists = false,
+        pub wm_change_state => b"WM_CHANGE_STATE" only_if_exists = false,
+        pub utf8_string => b"UTF8_STRING" only_if_exists = false,
+        pub net_wm_state => b"_NET_WM_STATE" only_if_exists = false,
+        pub net_wm_state_maximized_horz => b"_NET_WM_STATE_MAXIMIZED_HORZ" only_if_exists = false,
+        pub net_wm_state_maximized_vert => b"_NET_WM_STATE_MAXIMIZED_VERT" only_if_exists = false,
+        pub net_wm_state_fullscreen => b"_NET_WM_STATE_FULLSCREEN" only_if_exists = false,
+        pub net_wm_state_demands_attention => b"_NET_WM_STATE_DEMANDS_ATTENTION" only_if_exists = false,
+        pub net_wm_state_sticky => b"_NET_WM_STATE_STICKY" only_if_exists = false,
+        pub net_wm_state_above => b"_NET_WM_STATE_ABOVE" only_if_exists = false,
+        pub net_wm_state_below => b"_NET_WM_STATE_BELOW" only_if_exists = false,
+        pub net_wm_allowed_actions => b"_NET_WM_ALLOWED_ACTIONS" only_if_exists = false,
+        pub net_wm_action_move => b"_NET_WM_ACTION_MOVE" only_if_exists = false,
+        pub net_wm_action_resize => b"_NET_WM_ACTION_RESIZE" only_if_exists = false,
+        pub net_wm_action_minimize => b"_NET_WM_ACTION_MINIMIZE" only_if_exists = false,
+        pub net_wm_action_stick => b"_NET_WM_ACTION_STICK" only_if_exists = false,
+        pub net_wm_action_maximize_horz => b"_NET_WM_ACTION_MAXIMIZE_HORZ" only_if_exists = false,
+        pub net_wm_action_maximize_vert => b"_NET_WM_ACTION_MAXIMIZE_VERT" only_if_exists = false,
+        pub net_wm_action_fullscreen => b"_NET_WM_ACTION_FULLSCREEN" only_if_exists = false,
+        pub net_wm_action_change_desktop => b"_NET_WM_ACTION_CHANGE_DESKTOP" only_if_exists = false,
+        pub net_wm_action_close => b"_NET_WM_ACTION_CLOSE" only_if_exists = false,
+        pub net_wm_action_above => b"_NET_WM_ACTION_ABOVE" only_if_exists = false,
+        pub net_wm_action_below => b"_NET_WM_ACTION_BELOW" only_if_exists = false,
+        pub net_wm_window_type => b"_NET_WM_WINDOW_TYPE" only_if_exists = false,
+        pub net_wm_window_type_splash => b"_NET_WM_WINDOW_TYPE_SPLASH" only_if_exists = false,
+        pub net_wm_window_type_dialog => b"_NET_WM_WINDOW_TYPE_DIALOG" only_if_exists = false,
+        pub net_wm_window_type_utility => b"_NET_WM_WINDOW_TYPE_UTILITY" only_if_exists = false,
+        pub net_wm_window_type_toolbar => b"_NET_WM_WINDOW_TYPE_TOOLBAR" only_if_exists = false,
+        pub net_wm_window_type_menu => b"_NET_WM_WINDOW_TYPE_MENU" only_if_exists = false,
+        pub net_wm_window_opacity => b"_NET_WM_WINDOW_OPACITY" only_if_exists = false,
+        pub net_frame_extents => b"_NET_FRAME_EXTENTS" only_if_exists = false,
+        pub net_request_frame_extents => b"_NET_REQUEST_FRAME_EXTENTS" only_if_exists = false,
+        pub panko_workspace_switch => b"_PANKO_WORKSPACE_SWITCH" only_if_exists = false,
+    }
+}
+
+impl Atoms {
+    // EWMH hints we implement, for _NET_SUPPORTED
+    pub fn supported(&self) -> Vec<x::Atom> {
+        vec![
+            self.net_supporting_wm_check,
+            self.net_close_window,
+            self.net_wm_name,
+            self.net_wm_pid,
+            self.net_wm_desktop,
+            self.net_wm_moveresize,
+            self.net_moveresize_window,
+            self.net_wm_user_time,
+            self.net_wm_user_time_window,
+            self.net_wm_state,
+            self.net_wm_state_maximized_horz,
+            self.net_wm_state_maximized_vert,
+            self.net_wm_state_fullscreen,
+            self.net_wm_state_demands_attention,
+            self.net_wm_state_sticky,
+            self.net_wm_state_above,
+            self.net_wm_state_below,
+            self.net_wm_allowed_actions,
+            self.net_wm_action_move,
+            self.net_wm_action_resize,
+            self.net_wm_action_minimize,
+            self.net_wm_action_stick,
+            self.net_wm_action_maximize_horz,
+            self.net_wm_action_maximize_vert,
+            self.net_wm_action_fullscreen,
+            self.net_wm_action_change_desktop,
+            self.net_wm_action_close,
+            self.net_wm_action_above,
+            self.net_wm_action_below,
+            self.net_wm_window_type,
+            self.net_wm_window_type_splash,
+            self.net_wm_window_type_dialog,
+            self.net_wm_window_type_utility,
+            self.net_wm_window_type_toolbar,
+            self.net_wm_window_type_menu,
+            self.net_frame_extents,
+            self.net_request_frame_extents,
+        ]
+    }
+}
@@ -0,0 +1,66 @@
+use xcb::{x, Connection, Xid};
+
+// x::ButtonIndex only names buttons 1-5, but the protocol allows up to 255 and mice with side
+// buttons send 8 and 9 for back and forward. grabs for those go straight to libxcb.
+
+#[repr(C)]
+struct VoidCookie {
+    sequence: u32,
+}
+
+#[link(name = "xcb")]
+extern "C" {
+    #[allow(clippy::too_many_arguments)]
+    fn xcb_grab_button_checked(
+        c: *mut xcb::ffi::xcb_connection_t,
+        owner_events: u8,
+        grab_window: u32,
+        event_mask: u16,
+        pointer_mode: u8,
+        keyboard_mode: u8,
+        confine_to: u32,
+        cursor: u32,
+        button: u8,
+        modifiers: u16,
+    ) -> VoidCookie;
+
+    fn xcb_ungrab_button(
+        c: *mut xcb::ffi::xcb_connection_t,
+        button: u8,
+        grab_window: u32,
+        modifiers: u16,
+    ) -> VoidCookie;
+
+    fn xcb_request_check(c: *mut xcb::ffi::xcb_connection_t, cookie: VoidCookie) -> *mut libc::c_void;
+}
+
+// like x::GrabButton for the press only, returning false if someone else already has it
+pub fn grab(conn: &Connection, window: x::Window, button: u8, modifiers: x::ModMask) -> bool {
+    unsafe {
+        let cookie = xcb_grab_button_checked(
+            conn.get_raw_conn(),
+            0,
+            window.resource_id(),
+            x::EventMask::BUTTON_PRESS.bits() as u16,
+            x::GrabMode::Async as u8,
+            x::GrabMode::Async as u8,
+            window.resource_id(),
+            x::CURSOR_NONE.resource_id(),
+            button,
+            modifiers.bits() as u16,
+        );
+
+        let error = xcb_request_check(conn.get_raw_conn(), cookie);
+        if error.is_null() {
+            return true;
+        }
+        libc::free(error);
+        false
+    }
+}
+
+pub fn ungrab(conn: &Connection, window: x::Window, button: u8, modifiers: x::ModMask) {
+    unsafe {
+        xcb_ungrab_button(conn.get_raw_conn(), button, window.resource_id(), modifiers.bits() as u16);
+    }
+}
@@ -0,0 +1,976 @@
+use xcb::x;
+use log::{debug, warn};
+use std::fs;
+use std::path::PathBuf;
+
+use crate::keysym;
+use crate::rules::Rule;
+
+// config lives in $XDG_CONFIG_HOME/panko/config (or ~/.config/panko/config). one setting per
+// line as "name = value"; blank lines and lines starting with # are ignored. anything not set
+// keeps its default, and a missing file is just all defaults.
+//
+//   border_width = 2                  pixels
+//   scale = auto                      multiply border_width, snap_grid, edge_resistance and
+//                                     cursor_size by this, for HiDPI, unless they're set here:
+//                                     those are real pixels and stay as they are. auto works it
+//                                     out from the primary monitor's size in millimetres, a
+//                                     pixel per 96dpi; a number fixes it, so 1 scales nothing
+//   border_focused = #0055ff          colour of the focused window's border
+//   border_unfocused = #000000        colour of every other border
+//   border_focused_stripes = none     a second colour to stripe the focused border with
+//   border_unfocused_stripes = none   and the same for the others; windows with their own
+//                                     depth, like translucent ones, stay plain
+//   border_attention = #ff5500        colour of windows asking for attention
+//   smart_borders = false             no border on a window that's the only one showing on its
+//                                     monitor, back when another one shows up
+//   background = none                 fill the desktop with a plain colour, eg #202020, across
+//                                     every monitor; none leaves whatever was there
+//   cursor_theme = default            Xcursor theme for the pointer over the desktop and
+//                                     during drags; empty for the plain X cursor font
+//   cursor_size = 24                  nominal size of cursor theme images, in pixels
+//   workspaces = 9                    how many workspaces
+//   per_monitor_workspaces = true     each monitor switches on its own; false to share one set
+//   wheel_switches_workspaces = false scroll over the desktop background to change workspace
+//   root_menu_command =               run on a plain right click on the desktop background, for
+//                                     a menu like jgmenu or a launcher; nothing if it's empty
+//   tags = false                      mod4+N views tag N and mod4+shift+N toggles it on the
+//                                     focused window, rather than workspaces; put it before any
+//                                     bind lines for those keys
+//   reserved_top = 0                  pixels at each edge of every monitor that windows keep out
+//   reserved_bottom = 0               of when maximized, tiled or dragged, for a bar that doesn't
+//   reserved_left = 0                 set struts
+//   reserved_right = 0
+//   tiling = false                    lay windows out side by side rather than where they ask
+//   tiling_max_width = none           widest the tiled area gets, in pixels or percent of the
+//                                     monitor; wider monitors have it in the middle with empty
+//                                     margins each side, for ultrawides
+//   snap_grid = 16                    round drags to this many pixels; 0 for no grid
+//   snap_bypass_modifier = shift      hold during a drag to ignore the grid
+//   edge_resistance = 0               pixels to push a moving window past a screen or monitor
+//                                     edge before it goes through; 0 to stop at the screen edge
+//   min_window_size = 32              smallest a drag resizes a window to, if WM_NORMAL_HINTS
+//                                     doesn't give a minimum size of its own
+//   drag_timeout = 5000               milliseconds without the pointer moving before checking
+//                                     that a drag's button is still down, and ending it if the
+//                                     release was missed; 0 to never check
+//   click_raises = true               clicking a window raises it as well as focusing it
+//   raise_transients = true           raising a window brings its dialogs up with it; false
+//                                     leaves them where they are in the stack
+//   fullscreen_release_click = false  drop the click-to-raise grab while a fullscreen window
+//                                     has focus, for games that want every click
+//   focus_monitor_warps = true        focus_next_monitor and focus_prev_monitor move the pointer
+//                                     to the middle of the monitor too
+//   focus_freeze_modifier = none      hold to move the pointer across windows without focusing
+//                                     them, eg control
+//   auto_raise = false                raise windows when the pointer gives them focus
+//   auto_raise_delay = 0              milliseconds to wait before raising, so passing over a
+//                                     window on the way somewhere else doesn't raise it
+//   default_size = 640x480            size of new windows that don't ask for one, in pixels or
+//                                     percent of the monitor like 60%x60%
+//   oversized_windows = shrink        new windows bigger than the monitor are cut down to fit
+//                                     and centred; keep_aspect shrinks them to the same shape,
+//                                     and leave maps them as big as they asked. never smaller
+//                                     than their minimum size
+//   new_windows_in_front = true       raise and focus new windows; false opens them behind
+//                                     everything else
+//   focus_stealing_prevention = false leave focus alone when a window appears, unless it's a
+//                                     dialog for the focused window or _NET_WM_USER_TIME says
+//                                     it came from the last input; it's marked for attention
+//   status_output = stdout            write workspace and focus changes for a bar, to stdout or
+//                                     a file or named pipe; see status.rs for the format
+//   min_opacity = 20                  percent; opacity_down goes no lower, so windows can't
+//                                     disappear entirely
+//   unfocused_opacity = 100           percent to dim windows to while they don't have focus,
+//                                     on top of any opacity they've been given
+//   confirm_quit = false              quit needs pressing twice within a few seconds
+//   scratchpad_class = Scratchpad     WM_CLASS of the window toggle_scratchpad shows and hides
+//   scratchpad_command = xterm -class Scratchpad
+//                                     run by toggle_scratchpad if there's no such window yet
+//
+// key bindings are "bind = <modifiers+key> <action> [arg]", replacing any existing binding for
+// the same keys; the action "none" removes it. modifiers are shift, control, mod1 (alt),
+// mod2-mod5 and mod4 (super). keys are single characters or names like period, Tab, F1, or
+// mouse buttons as button1-button255; 8 and 9 are usually back and forward.
+//
+//   bind = mod4+period send_to_next_monitor
+//   bind = mod4+comma send_to_prev_monitor
+//   bind = mod4+shift+period tile_to_next_monitor  a tiled window into the same place in the
+//   bind = mod4+shift+comma tile_to_prev_monitor  next monitor's tiling; others are sent along
+//   bind = mod4+w focus_next_monitor     focus the top window on the next monitor, or the one
+//   bind = mod4+e focus_prev_monitor     before; does nothing with only one monitor
+//   bind = mod4+1 switch_workspace 1
+//   bind = mod4+shift+1 send_to_workspace 1
+//   bind = mod4+1 view_tag 1              show only windows with tag 1, on top of workspaces;
+//   bind = mod4+control+1 toggle_view_tag 1  add tag 1 to what's showing, or take it away
+//   bind = mod4+shift+1 toggle_tag 1      give the focused window tag 1, or take it away
+//   bind = mod4+shift+g bring_all         every window onto the workspaces showing, minimized
+//                                         ones too, for when things get lost
+//   bind = mod4+tab cycle_next            hold mod4 and press tab to pick a window, let go to
+//   bind = mod4+shift+tab cycle_prev      switch to it
+//   bind = mod4+shift+grave cycle_class_next  the same, but only windows with the focused one's
+//                                         class; there's also cycle_class_prev
+//   bind = mod4+control+tab cycle_monitor_next  the same, but only windows on the focused
+//   bind = mod4+control+shift+tab cycle_monitor_prev  window's monitor, or the pointer's
+//   bind = mod4+m toggle_maximize
+//   bind = mod4+n minimize
+//   bind = mod4+shift+n restore_minimized  the most recently minimized window
+//   bind = mod4+f toggle_zoom             fill the monitor with no border, for clients that can't
+//                                         go fullscreen themselves
+//   bind = mod4+shift+c reload_config
+//   bind = mod4+grave toggle_scratchpad
+//   bind = mod4+shift+k close_window      again to SIGTERM, and again to SIGKILL
+//   bind = mod4+shift+space toggle_floating  take a window out of the tiling, or put it back
+//   bind = mod4+c centre_window           in the middle of its monitor, the same size; not for
+//                                         tiled windows
+//   bind = mod4+t toggle_tabbed           tiles on the workspace all take the whole area, with
+//                                         only the front one showing, like tabs; with tiling on
+//   bind = mod4+j tab_next                bring the next tile to the front and focus it, for
+//   bind = mod4+k tab_prev                switching tabs, or just moving around the tiles
+//   bind = mod4+l grow_master             widen the first tile on the workspace, or narrow it,
+//   bind = mod4+h shrink_master           a step at a time
+//   bind = mod4+shift+0 reset_layout      undo grow_master and shrink_master on the workspace
+//   bind = mod4+x swap_windows            mark the focused window, then again on another to
+//                                         swap them over; tiles swap places in the tiling
+//   bind = mod4+shift+e quit
+//   bind = mod4+plus opacity_up           also mod4+equal, for keyboards where plus needs shift;
+//   bind = mod4+minus opacity_down        only shows with a compositor running
+//   bind = button8 prev_workspace
+//   bind = button9 next_workspace
+//
+// rules place, float or ignore windows by class or title, see rules.rs for the details
+//
+//   rule = class=Gimp float
+//   rule = class=Firefox title="Profile 2" workspace=3
+//   rule = class=Xwinwrap ignore
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    SendToNextMonitor,
+    SendToPrevMonitor,
+    TileToNextMonitor,
+    TileToPrevMonitor,
+    FocusNextMonitor,
+    FocusPrevMonitor,
+    SwitchWorkspace(usize),
+    SendToWorkspace(usize),
+    NextWorkspace,
+    PrevWorkspace,
+    ViewTag(usize),
+    ToggleViewTag(usize),
+    ToggleTag(usize),
+    BringAll,
+    CycleNext,
+    CyclePrev,
+    CycleClassNext,
+    CycleClassPrev,
+    CycleMonitorNext,
+    CycleMonitorPrev,
+    ToggleMaximize,
+    ToggleZoom,
+    Minimize,
+    RestoreMinimized,
+    ReloadConfig,
+    ToggleScratchpad,
+    CloseWindow,
+    ToggleFloating,
+    CentreWindow,
+    ToggleTabbed,
+    TabNext,
+    TabPrev,
+    GrowMaster,
+    ShrinkMaster,
+    ResetLayout,
+    SwapWindows,
+    OpacityUp,
+    OpacityDown,
+    Quit,
+}
+
+impl Action {
+    fn from_name(name: &str, arg: Option<&str>) -> Result<Action, String> {
+        // workspaces are numbered from 1 in the config
+        let workspace = || -> Result<usize, String> {
+            match arg.map(|a| a.parse::<usize>()) {
+                Some(Ok(n)) if n > 0 => Ok(n - 1),
+                _ => Err(format!("{} needs a workspace number", name)),
+            }
+        };
+
+        // tags too, and there's one bit each for them
+        let tag = || -> Result<usize, String> {
+            match arg.map(|a| a.parse::<usize>()) {
+                Some(Ok(n)) if n > 0 && n <= 32 => Ok(n - 1),
+                _ => Err(format!("{} needs a tag number from 1 to 32", name)),
+            }
+        };
+
+        match name {
+            "send_to_next_monitor" => Ok(Action::SendToNextMonitor),
+            "send_to_prev_monitor" => Ok(Action::SendToPrevMonitor),
+            "tile_to_next_monitor" => Ok(Action::TileToNextMonitor),
+            "tile_to_prev_monitor" => Ok(Action::TileToPrevMonitor),
+            "focus_next_monitor" => Ok(Action::FocusNextMonitor),
+            "focus_prev_monitor" => Ok(Action::FocusPrevMonitor),
+            "switch_workspace" => Ok(Action::SwitchWorkspace(workspace()?)),
+            "send_to_workspace" => Ok(Action::SendToWorkspace(workspace()?)),
+            "next_workspace" => Ok(Action::NextWorkspace),
+            "prev_workspace" => Ok(Action::PrevWorkspace),
+            "view_tag" => Ok(Action::ViewTag(tag()?)),
+            "toggle_view_tag" => Ok(Action::ToggleViewTag(tag()?)),
+            "toggle_tag" => Ok(Action::ToggleTag(tag()?)),
+            "bring_all" => Ok(Action::BringAll),
+            "cycle_next" => Ok(Action::CycleNext),
+            "cycle_prev" => Ok(Action::CyclePrev),
+            "cycle_class_next" => Ok(Action::CycleClassNext),
+            "cycle_class_prev" => Ok(Action::CycleClassPrev),
+            "cycle_monitor_next" => Ok(Action::CycleMonitorNext),
+            "cycle_monitor_prev" => Ok(Action::CycleMonitorPrev),
+            "toggle_maximize" => Ok(Action::ToggleMaximize),
+            "toggle_zoom" => Ok(Action::ToggleZoom),
+            "minimize" => Ok(Action::Minimize),
+            "restore_minimized" => Ok(Action::RestoreMinimized),
+            "reload_config" => Ok(Action::ReloadConfig),
+            "toggle_scratchpad" => Ok(Action::ToggleScratchpad),
+            "close_window" => Ok(Action::CloseWindow),
+            "toggle_floating" => Ok(Action::ToggleFloating),
+            "centre_window" => Ok(Action::CentreWindow),
+            "toggle_tabbed" => Ok(Action::ToggleTabbed),
+            "tab_next" => Ok(Action::TabNext),
+            "tab_prev" => Ok(Action::TabPrev),
+            "grow_master" => Ok(Action::GrowMaster),
+            "shrink_master" => Ok(Action::ShrinkMaster),
+            "reset_layout" => Ok(Action::ResetLayout),
+            "swap_windows" => Ok(Action::SwapWindows),
+            "opacity_up" => Ok(Action::OpacityUp),
+            "opacity_down" => Ok(Action::OpacityDown),
+            "quit" => Ok(Action::Quit),
+            _ => Err(format!("unknown action {:?}", name)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KeyBinding {
+    pub modifiers: x::ModMask,
+    pub keysym: u32,
+    pub action: Action,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ButtonBinding {
+    pub modifiers: x::ModMask,
+    pub button: u8,
+    pub action: Action,
+}
+
+// one side of a window, fixed or as a share of the monitor
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Length {
+    Pixels(u32),
+    Percent(u32),
+}
+
+impl Length {
+    // outer size, for a monitor side this long
+    pub fn resolve(&self, available: i32) -> u32 {
+        match *self {
+            Length::Pixels(n) => n,
+            Length::Percent(p) => (available.max(0) as u64 * p as u64 / 100) as u32,
+        }
+    }
+}
+
+// which of the sizes scale applies to were set in the file. only the defaults get scaled
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SetSizes {
+    pub border_width: bool,
+    pub snap_grid: bool,
+    pub edge_resistance: bool,
+    pub cursor_size: bool,
+}
+
+// what to do with a new window that's bigger than its monitor
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Oversized {
+    Leave,
+    Shrink,
+    KeepAspect,
+}
+
+pub struct Config {
+    pub key_bindings: Vec<KeyBinding>,
+    pub button_bindings: Vec<ButtonBinding>,
+    pub rules: Vec<Rule>,
+
+    pub border_width: u32,
+    // what sizes in pixels are multiplied by; None to go by the monitor
+    pub scale: Option<u32>,
+    pub set_sizes: SetSizes,
+    pub border_focused: u32,
+    pub border_unfocused: u32,
+    // striped diagonally with the plain colour, rather than solid
+    pub border_focused_stripes: Option<u32>,
+    pub border_unfocused_stripes: Option<u32>,
+    pub border_attention: u32,
+    // a window by itself on its monitor goes without
+    pub smart_borders: bool,
+    // the root window's colour, if we're to set it
+    pub background: Option<u32>,
+    // the pointer's look comes from this Xcursor theme, at about this size
+    pub cursor_theme: Option<String>,
+    pub cursor_size: u32,
+
+    pub workspace_count: usize,
+    // each monitor switches workspaces on its own; false for one set shared by all monitors
+    pub per_monitor_workspaces: bool,
+    // scrolling over the root window goes to the next or previous workspace
+    pub wheel_switches_workspaces: bool,
+    // run when the desktop background is right clicked
+    pub root_menu_command: Option<String>,
+    // the default number keys are for tags rather than workspaces
+    pub tags: bool,
+
+    // kept clear at the edges of each monitor, so the work area is what's left
+    pub reserved_top: u32,
+    pub reserved_bottom: u32,
+    pub reserved_left: u32,
+    pub reserved_right: u32,
+
+    // windows tile across the monitor, except floating ones
+    pub tiling: bool,
+    // tiling keeps to this width in the middle of the monitor, if it's wider
+    pub tiling_max_width: Option<Length>,
+
+    // round dragged positions and sizes to multiples of this many pixels
+    pub snap_grid: Option<u32>,
+    // hold this during a drag to ignore the grid
+    pub snap_bypass_modifier: x::ModMask,
+    // moved windows stick at screen and monitor edges until pushed this far past them, rather
+    // than being kept on screen
+    pub edge_resistance: i32,
+    // resizing stops here, for windows that don't say how small they can go
+    pub min_window_size: u32,
+    // a drag that's gone quiet this long is checked for a missed button release, in ms
+    pub drag_timeout: u32,
+
+    // give up the plain left click grab while a fullscreen window has focus
+    pub fullscreen_release_click: bool,
+    // a plain click on a window brings it to the front; false leaves the stacking alone
+    pub click_raises: bool,
+    // a window's dialogs go to the front with it
+    pub raise_transients: bool,
+
+    // focusing another monitor from the keyboard takes the pointer there too, so focus follows
+    // mouse doesn't take it straight back and an empty monitor stays the active one
+    pub focus_monitor_warps: bool,
+    // held while the pointer crosses into a window, focus stays where it is
+    pub focus_freeze_modifier: Option<x::ModMask>,
+
+    // raise windows focused by the pointer, after this many milliseconds
+    pub auto_raise: bool,
+    pub auto_raise_delay: u32,
+
+    // for new windows with no size of their own from a rule, size hints or last time
+    pub default_size: (Length, Length),
+    // new windows too big for the monitor, which would have parts out of reach
+    pub oversized_windows: Oversized,
+
+    // raise and focus windows when they're first mapped, rather than putting them at the back
+    pub new_windows_in_front: bool,
+
+    // new windows only get focus if they're a dialog for the focused window or the user just
+    // asked for them
+    pub focus_stealing_prevention: bool,
+
+    // where to write status lines for an external bar, off if unset
+    pub status_output: Option<String>,
+
+    // lowest percentage opacity_down will go to
+    pub min_opacity: u32,
+
+    // percentage of their own opacity unfocused windows get, so 100 leaves them alone
+    pub unfocused_opacity: u32,
+
+    // quit only on a second press soon after the first, since it ends the whole session
+    pub confirm_quit: bool,
+
+    // the dropdown window, and how to start it if it isn't running
+    pub scratchpad_class: Option<String>,
+    pub scratchpad_command: Option<String>,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        let mut key_bindings = vec![
+            KeyBinding {
+                modifiers: x::ModMask::N4,
+                keysym: keysym::PERIOD,
+                action: Action::SendToNextMonitor,
+            },
+            KeyBinding {
+                modifiers: x::ModMask::N4,
+                keysym: keysym::COMMA,
+                action: Action::SendToPrevMonitor,
+            },
+            KeyBinding {
+                modifiers: x::ModMask::N4 | x::ModMask::SHIFT,
+                keysym: keysym::PERIOD,
+                action: Action::TileToNextMonitor,
+            },
+            KeyBinding {
+                modifiers: x::ModMask::N4 | x::ModMask::SHIFT,
+                keysym: keysym::COMMA,
+                action: Action::TileToPrevMonitor,
+            },
+            KeyBinding {
+                modifiers: x::ModMask::N4,
+                keysym: keysym::W,
+                action: Action::FocusNextMonitor,
+            },
+            KeyBinding {
+                modifiers: x::ModMask::N4,
+                keysym: keysym::E,
+                action: Action::FocusPrevMonitor,
+            },
+            KeyBinding {
+                modifiers: x::ModMask::N4 | x::ModMask::SHIFT,
+                keysym: keysym::G,
+                action: Action::BringAll,
+            },
+            KeyBinding {
+                modifiers: x::ModMask::N4,
+                keysym: keysym::TAB,
+                action: Action::CycleNext,
+            },
+            KeyBinding {
+                modifiers: x::ModMask::N4 | x::ModMask::SHIFT,
+                keysym: keysym::TAB,
+                action: Action::CyclePrev,
+            },
+            KeyBinding {
+                modifiers: x::ModMask::N4 | x::ModMask::SHIFT,
+                keysym: keysym::GRAVE,
+                action: Action::CycleClassNext,
+            },
+            KeyBinding {
+                modifiers: x::ModMask::N4 | x::ModMask::CONTROL,
+                keysym: keysym::TAB,
+                action: Action::CycleMonitorNext,
+            },
+            KeyBinding {
+                modifiers: x::ModMask::N4 | x::ModMask::CONTROL | x::ModMask::SHIFT,
+                keysym: keysym::TAB,
+                action: Action::CycleMonitorPrev,
+            },
+            KeyBinding {
+                modifiers: x::ModMask::N4,
+                keysym: keysym::M,
+                action: Action::ToggleMaximize,
+            },
+            KeyBinding {
+                modifiers: x::ModMask::N4,
+                keysym: keysym::N,
+                action: Action::Minimize,
+            },
+            KeyBinding {
+                modifiers: x::ModMask::N4 | x::ModMask::SHIFT,
+                keysym: keysym::N,
+                action: Action::RestoreMinimized,
+            },
+            KeyBinding {
+                modifiers: x::ModMask::N4,
+                keysym: keysym::F,
+                action: Action::ToggleZoom,
+            },
+            KeyBinding {
+                modifiers: x::ModMask::N4 | x::ModMask::SHIFT,
+                keysym: keysym::C,
+                action: Action::ReloadConfig,
+            },
+            KeyBinding {
+                modifiers: x::ModMask::N4,
+                keysym: keysym::GRAVE,
+                action: Action::ToggleScratchpad,
+            },
+            KeyBinding {
+                modifiers: x::ModMask::N4 | x::ModMask::SHIFT,
+                keysym: keysym::K,
+                action: Action::CloseWindow,
+            },
+            KeyBinding {
+                modifiers: x::ModMask::N4 | x::ModMask::SHIFT,
+                keysym: keysym::SPACE,
+                action: Action::ToggleFloating,
+            },
+            KeyBinding {
+                modifiers: x::ModMask::N4,
+                keysym: keysym::C,
+                action: Action::CentreWindow,
+            },
+            KeyBinding {
+                modifiers: x::ModMask::N4,
+                keysym: keysym::T,
+                action: Action::ToggleTabbed,
+            },
+            KeyBinding {
+                modifiers: x::ModMask::N4,
+                keysym: keysym::J,
+                action: Action::TabNext,
+            },
+            KeyBinding {
+                modifiers: x::ModMask::N4,
+                keysym: keysym::K,
+                action: Action::TabPrev,
+            },
+            KeyBinding {
+                modifiers: x::ModMask::N4,
+                keysym: keysym::L,
+                action: Action::GrowMaster,
+            },
+            KeyBinding {
+                modifiers: x::ModMask::N4,
+                keysym: keysym::H,
+                action: Action::ShrinkMaster,
+            },
+            KeyBinding {
+                modifiers: x::ModMask::N4 | x::ModMask::SHIFT,
+                keysym: keysym::KEY_0,
+                action: Action::ResetLayout,
+            },
+            KeyBinding {
+                modifiers: x::ModMask::N4,
+                keysym: keysym::X,
+                action: Action::SwapWindows,
+            },
+            KeyBinding {
+                modifiers: x::ModMask::N4,
+                keysym: keysym::PLUS,
+                action: Action::OpacityUp,
+            },
+            KeyBinding {
+                modifiers: x::ModMask::N4,
+                keysym: keysym::EQUAL,
+                action: Action::OpacityUp,
+            },
+            KeyBinding {
+                modifiers: x::ModMask::N4,
+                keysym: keysym::MINUS,
+                action: Action::OpacityDown,
+            },
+            KeyBinding {
+                modifiers: x::ModMask::N4 | x::ModMask::SHIFT,
+                keysym: keysym::E,
+                action: Action::Quit,
+            },
+        ];
+
+        // Mod4+N to view workspace N, Mod4+Shift+N to send the focused window there
+        let workspace_count = 9;
+        for n in 0..workspace_count {
+            key_bindings.push(KeyBinding {
+                modifiers: x::ModMask::N4,
+                keysym: keysym::KEY_1 + n as u32,
+                action: Action::SwitchWorkspace(n),
+            });
+            key_bindings.push(KeyBinding {
+                modifiers: x::ModMask::N4 | x::ModMask::SHIFT,
+                keysym: keysym::KEY_1 + n as u32,
+                action: Action::SendToWorkspace(n),
+            });
+        }
+
+        Config {
+            key_bindings,
+            button_bindings: vec![],
+            rules: vec![],
+            border_width: 2,
+            scale: None,
+            set_sizes: SetSizes::default(),
+            border_focused: 0x0055ff,
+            border_unfocused: 0x000000,
+            border_focused_stripes: None,
+            border_unfocused_stripes: None,
+            border_attention: 0xff5500,
+            smart_borders: false,
+            background: None,
+            cursor_theme: Some("default".to_string()),
+            cursor_size: 24,
+            workspace_count,
+            per_monitor_workspaces: true,
+            wheel_switches_workspaces: false,
+            root_menu_command: None,
+            tags: false,
+            reserved_top: 0,
+            reserved_bottom: 0,
+            reserved_left: 0,
+            reserved_right: 0,
+            tiling: false,
+            tiling_max_width: None,
+            snap_grid: None,
+            snap_bypass_modifier: x::ModMask::SHIFT,
+            edge_resistance: 0,
+            min_window_size: 32,
+            drag_timeout: 5000,
+            fullscreen_release_click: false,
+            click_raises: true,
+            raise_transients: true,
+            focus_monitor_warps: true,
+            focus_freeze_modifier: None,
+            auto_raise: false,
+            auto_raise_delay: 0,
+            default_size: (Length::Pixels(640), Length::Pixels(480)),
+            oversized_windows: Oversized::Shrink,
+            new_windows_in_front: true,
+            focus_stealing_prevention: false,
+            status_output: None,
+            min_opacity: 20,
+            unfocused_opacity: 100,
+            confirm_quit: false,
+            scratchpad_class: None,
+            scratchpad_command: None,
+        }
+    }
+}
+
+impl Config {
+    fn path() -> Option<PathBuf> {
+        let config_dir = match std::env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+        };
+        Some(config_dir.join("panko/config"))
+    }
+
+    // defaults, overridden by whatever's in the config file
+    pub fn load() -> Config {
+        let mut config = Config::default();
+
+        let path = match Config::path() {
+            Some(p) => p,
+            None => return config,
+        };
+
+        let data = match fs::read_to_string(&path) {
+            Ok(d) => d,
+            Err(e) => {
+                debug!("couldn't read {:?}, using defaults: {}", path, e);
+                return config;
+            },
+        };
+
+        for (n, line) in data.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Err(e) = config.apply_line(line) {
+                warn!("{}:{}: {}", path.display(), n+1, e);
+            }
+        }
+
+        debug!("loaded config from {:?}", path);
+
+        config
+    }
+
+    // what's different in the new config, for logging on reload
+    pub fn changes(&self, new: &Config) -> Vec<String> {
+        let mut changes = vec![];
+
+        macro_rules! compare {
+            ($($field:ident),*) => {
+                $(
+                    if self.$field != new.$field {
+                        changes.push(format!("{} {:?} -> {:?}", stringify!($field), self.$field, new.$field));
+                    }
+                )*
+            }
+        }
+        compare!(border_width, scale, border_focused, border_unfocused, border_focused_stripes,
+            border_unfocused_stripes, border_attention, smart_borders, background, cursor_theme,
+            cursor_size, workspace_count, per_monitor_workspaces, wheel_switches_workspaces,
+            root_menu_command, tags, reserved_top, reserved_bottom, reserved_left, reserved_right,
+            tiling, tiling_max_width, snap_grid, snap_bypass_modifier, edge_resistance,
+            min_window_size, drag_timeout, fullscreen_release_click, click_raises, raise_transients,
+            focus_monitor_warps, focus_freeze_modifier, auto_raise, auto_raise_delay, default_size,
+            oversized_windows, new_windows_in_front, focus_stealing_prevention, status_output,
+            min_opacity, unfocused_opacity, confirm_quit, scratchpad_class, scratchpad_command);
+
+        for b in self.key_bindings.iter().filter(|b| !new.key_bindings.contains(b)) {
+            changes.push(format!("unbound {:?}", b));
+        }
+        for b in new.key_bindings.iter().filter(|b| !self.key_bindings.contains(b)) {
+            changes.push(format!("bound {:?}", b));
+        }
+        for b in self.button_bindings.iter().filter(|b| !new.button_bindings.contains(b)) {
+            changes.push(format!("unbound {:?}", b));
+        }
+        for b in new.button_bindings.iter().filter(|b| !self.button_bindings.contains(b)) {
+            changes.push(format!("bound {:?}", b));
+        }
+        for r in self.rules.iter().filter(|r| !new.rules.contains(r)) {
+            changes.push(format!("removed rule {:?}", r));
+        }
+        for r in new.rules.iter().filter(|r| !self.rules.contains(r)) {
+            changes.push(format!("added rule {:?}", r));
+        }
+
+        changes
+    }
+
+    fn apply_line(&mut self, line: &str) -> Result<(), String> {
+        let (name, value) = line.split_once('=').ok_or("expected 'name = value'")?;
+        let value = value.trim();
+
+        match name.trim() {
+            "border_width" => {
+                self.border_width = parse_number(value)?;
+                self.set_sizes.border_width = true;
+            },
+            "scale" => {
+                self.scale = match value {
+                    "auto" => None,
+                    _ => match parse_number(value)? {
+                        0 => return Err("scale needs to be at least 1".to_string()),
+                        n => Some(n),
+                    },
+                };
+            },
+            "border_focused" => self.border_focused = parse_colour(value)?,
+            "border_unfocused" => self.border_unfocused = parse_colour(value)?,
+            "border_focused_stripes" => self.border_focused_stripes = parse_optional_colour(value)?,
+            "border_unfocused_stripes" => self.border_unfocused_stripes = parse_optional_colour(value)?,
+            "border_attention" => self.border_attention = parse_colour(value)?,
+            "smart_borders" => self.smart_borders = parse_bool(value)?,
+            "background" => self.background = parse_optional_colour(value)?,
+            "cursor_theme" => self.cursor_theme = parse_string(value),
+            "cursor_size" => {
+                self.cursor_size = match parse_number(value)? {
+                    0 => return Err("cursor_size needs to be at least 1".to_string()),
+                    n => n,
+                };
+                self.set_sizes.cursor_size = true;
+            },
+            "workspaces" => {
+                self.workspace_count = match parse_number(value)? {
+                    0 => return Err("need at least one workspace".to_string()),
+                    n => n as usize,
+                };
+            },
+            "per_monitor_workspaces" => self.per_monitor_workspaces = parse_bool(value)?,
+            "wheel_switches_workspaces" => self.wheel_switches_workspaces = parse_bool(value)?,
+            "root_menu_command" => self.root_menu_command = parse_string(value),
+            "tags" => {
+                self.tags = parse_bool(value)?;
+                self.number_keys_for_tags(self.tags);
+            },
+            "reserved_top" => self.reserved_top = parse_number(value)?,
+            "reserved_bottom" => self.reserved_bottom = parse_number(value)?,
+            "reserved_left" => self.reserved_left = parse_number(value)?,
+            "reserved_right" => self.reserved_right = parse_number(value)?,
+            "tiling" => self.tiling = parse_bool(value)?,
+            "tiling_max_width" => {
+                self.tiling_max_width = match value {
+                    "none" => None,
+                    _ => Some(parse_length(value).ok_or(format!("expected pixels or a percentage, got {:?}", value))?),
+                };
+            },
+            "snap_grid" => {
+                self.snap_grid = match parse_number(value)? {
+                    0 => None,
+                    n => Some(n),
+                };
+                self.set_sizes.snap_grid = true;
+            },
+            "snap_bypass_modifier" => self.snap_bypass_modifier = parse_modifiers(value.split('+'))?,
+            "edge_resistance" => {
+                self.edge_resistance = parse_number(value)? as i32;
+                self.set_sizes.edge_resistance = true;
+            },
+            "min_window_size" => {
+                self.min_window_size = match parse_number(value)? {
+                    0 => return Err("min_window_size needs to be at least 1".to_string()),
+                    n => n,
+                };
+            },
+            "drag_timeout" => self.drag_timeout = parse_number(value)?,
+            "fullscreen_release_click" => self.fullscreen_release_click = parse_bool(value)?,
+            "click_raises" => self.click_raises = parse_bool(value)?,
+            "raise_transients" => self.raise_transients = parse_bool(value)?,
+            "focus_monitor_warps" => self.focus_monitor_warps = parse_bool(value)?,
+            "focus_freeze_modifier" => self.focus_freeze_modifier = match value {
+                "none" => None,
+                _ => Some(parse_modifiers(value.split('+'))?),
+            },
+            "auto_raise" => self.auto_raise = parse_bool(value)?,
+            "auto_raise_delay" => self.auto_raise_delay = parse_number(value)?,
+            "default_size" => self.default_size = parse_size(value)?,
+            "oversized_windows" => {
+                self.oversized_windows = match value {
+                    "leave" => Oversized::Leave,
+                    "shrink" => Oversized::Shrink,
+                    "keep_aspect" => Oversized::KeepAspect,
+                    _ => return Err(format!("expected leave, shrink or keep_aspect, got {:?}", value)),
+                };
+            },
+            "new_windows_in_front" => self.new_windows_in_front = parse_bool(value)?,
+            "focus_stealing_prevention" => self.focus_stealing_prevention = parse_bool(value)?,
+            "status_output" => self.status_output = parse_string(value),
+            "min_opacity" => {
+                self.min_opacity = match parse_number(value)? {
+                    n if n > 100 => return Err("min_opacity is a percentage".to_string()),
+                    n => n,
+                };
+            },
+            "unfocused_opacity" => {
+                self.unfocused_opacity = match parse_number(value)? {
+                    n if n > 100 => return Err("unfocused_opacity is a percentage".to_string()),
+                    n => n,
+                };
+            },
+            "confirm_quit" => self.confirm_quit = parse_bool(value)?,
+            "scratchpad_class" => self.scratchpad_class = parse_string(value),
+            "scratchpad_command" => self.scratchpad_command = parse_string(value),
+            "bind" => self.bind(value)?,
+            "rule" => self.rules.push(Rule::parse(value)?),
+            name => return Err(format!("unknown setting {:?}", name)),
+        }
+
+        Ok(())
+    }
+
+    // swap the default Mod4+N and Mod4+Shift+N between workspaces and tags, leaving anything
+    // that's been bound to something else alone
+    fn number_keys_for_tags(&mut self, tags: bool) {
+        for b in self.key_bindings.iter_mut().filter(|b| (keysym::KEY_1..keysym::KEY_1 + 9).contains(&b.keysym)) {
+            let n = (b.keysym - keysym::KEY_1) as usize;
+            let shift = b.modifiers.contains(x::ModMask::SHIFT);
+            b.action = match (b.action, tags) {
+                (Action::SwitchWorkspace(w), true) if w == n && !shift => Action::ViewTag(n),
+                (Action::SendToWorkspace(w), true) if w == n && shift => Action::ToggleTag(n),
+                (Action::ViewTag(t), false) if t == n && !shift => Action::SwitchWorkspace(n),
+                (Action::ToggleTag(t), false) if t == n && shift => Action::SendToWorkspace(n),
+                (action, _) => action,
+            };
+        }
+    }
+
+    fn bind(&mut self, value: &str) -> Result<(), String> {
+        let mut words = value.split_whitespace();
+        let keys = words.next().ok_or("bind needs keys and an action")?;
+        let action = words.next().ok_or("bind needs an action")?;
+        let arg = words.next();
+
+        // modifiers first, key last
+        let mut parts: Vec<_> = keys.split('+').collect();
+        let key = parts.pop().unwrap_or_default();
+        let modifiers = parse_modifiers(parts.into_iter())?;
+
+        if let Some(button) = key.strip_prefix("button") {
+            let button = match button.parse::<u8>() {
+                Ok(b) if b > 0 => b,
+                _ => return Err(format!("unknown button {:?}", key)),
+            };
+
+            self.button_bindings.retain(|b| b.modifiers != modifiers || b.button != button);
+
+            if action != "none" {
+                self.button_bindings.push(ButtonBinding {
+                    modifiers,
+                    button,
+                    action: Action::from_name(action, arg)?,
+                });
+            }
+
+            return Ok(());
+        }
+
+        let keysym = keysym::from_name(key).ok_or_else(|| format!("unknown key {:?}", key))?;
+
+        self.key_bindings.retain(|b| b.modifiers != modifiers || b.keysym != keysym);
+
+        if action != "none" {
+            self.key_bindings.push(KeyBinding {
+                modifiers,
+                keysym,
+                action: Action::from_name(action, arg)?,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+fn parse_number(value: &str) -> Result<u32, String> {
+    value.parse().map_err(|_| format!("expected a number, got {:?}", value))
+}
+
+// WxH, each side in pixels or a percentage like 60%
+pub fn parse_size(value: &str) -> Result<(Length, Length), String> {
+    match value.split_once('x').map(|(w, h)| (parse_length(w), parse_length(h))) {
+        Some((Some(width), Some(height))) => Ok((width, height)),
+        _ => Err(format!("expected a size like 640x480 or 60%x60%, got {:?}", value)),
+    }
+}
+
+// pixels, or a percentage with %
+pub fn parse_length(value: &str) -> Option<Length> {
+    match value.strip_suffix('%') {
+        Some(p) => p.parse().ok().filter(|&p| p > 0 && p <= 100).map(Length::Percent),
+        None => value.parse().ok().filter(|&n| n > 0).map(Length::Pixels),
+    }
+}
+
+// empty to unset
+fn parse_string(value: &str) -> Option<String> {
+    match value {
+        "" => None,
+        _ => Some(value.to_string()),
+    }
+}
+
+fn parse_bool(value: &str) -> Result<bool, String> {
+    match value {
+        "true" | "yes" | "on" => Ok(true),
+        "false" | "no" | "off" => Ok(false),
+        _ => Err(format!("expected true or false, got {:?}", value)),
+    }
+}
+
+// #rrggbb or 0xrrggbb
+fn parse_colour(value: &str) -> Result<u32, String> {
+    let hex = value.strip_prefix('#').or_else(|| value.strip_prefix("0x"));
+    match hex.map(|h| u32::from_str_radix(h, 16)) {
+        Some(Ok(colour)) if colour <= 0xffffff => Ok(colour),
+        _ => Err(format!("expected a colour like #0055ff, got {:?}", value)),
+    }
+}
+
+// a colour, or none
+fn parse_optional_colour(value: &str) -> Result<Option<u32>, String> {
+    match value {
+        "none" => Ok(None),
+        _ => parse_colour(value).map(Some),
+    }
+}
+
+fn parse_modifiers<'a>(names: impl Iterator<Item = &'a str>) -> Result<x::ModMask, String> {
+    let mut modifiers = x::ModMask::empty();
+
+    for name in names {
+        modifiers |= match name.to_ascii_lowercase().as_str() {
+            "shift" => x::ModMask::SHIFT,
+            "control" | "ctrl" => x::ModMask::CONTROL,
+            "mod1" | "alt" => x::ModMask::N1,
+            "mod2" => x::ModMask::N2,
+            "mod3" => x::ModMask::N3,
+            "mod4" | "super" => x::ModMask::N4,
+            "mod5" => x::ModMask::N5,
+            _ => return Err(format!("unknown modifier {:?}", name)),
+        };
+    }
+
+    Ok(modifiers)
+}
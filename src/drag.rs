@@ -0,0 +1,260 @@
+// geometry for mouse moves and resizes, kept free of X so it can be tested
+use crate::monitor::Monitor;
+
+// top-left of a window being moved so that the pointer stays at the same offset into it, snapped
+// to the grid and kept inside the area, usually the screen less anything reserved. sizes include
+// the border. if the window is bigger than the area its top-left corner stays inside
+pub fn clamp_move(ptr: (i32, i32), off: (i32, i32), win: (i32, i32), area: &Monitor, grid: Option<u32>) -> (i32, i32) {
+    let x = snap_to_grid(ptr.0 - off.0, grid);
+    let y = snap_to_grid(ptr.1 - off.1, grid);
+
+    (
+        x.min(area.x + area.width - win.0).max(area.x),
+        y.min(area.y + area.height - win.1).max(area.y),
+    )
+}
+
+// one axis of a move with edge resistance. the leading side of a window moving from current
+// towards pos stops at the first edge it would cross, until it's been pushed more than
+// resistance past it. returns the position and how far past the edge it's being pushed, 0 if it
+// isn't being held. size includes the border
+pub fn resist(pos: i32, current: i32, size: i32, edges: &[i32], resistance: i32) -> (i32, i32) {
+    if resistance <= 0 {
+        return (pos, 0);
+    }
+
+    // where the window would stop for each edge it crosses, nearest first
+    let stop = if pos > current {
+        edges.iter()
+            .filter(|&&e| current + size <= e && e < pos + size)
+            .map(|&e| e - size)
+            .min()
+    }
+    else {
+        edges.iter()
+            .filter(|&&e| pos < e && e <= current)
+            .copied()
+            .max()
+    };
+
+    match stop {
+        Some(stop) if (pos - stop).abs() <= resistance => (stop, pos - stop),
+        _ => (pos, 0),
+    }
+}
+
+// which sides of a window follow the pointer during a resize
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Edges {
+    pub left: bool,
+    pub right: bool,
+    pub top: bool,
+    pub bottom: bool,
+}
+
+impl Edges {
+    pub const BOTTOM_RIGHT: Edges = Edges { left: false, right: true, top: false, bottom: true };
+
+    // the eight resize directions of _NET_WM_MOVERESIZE, clockwise from top-left
+    pub fn from_direction(direction: u32) -> Option<Edges> {
+        let (left, right, top, bottom) = match direction {
+            0 => (true, false, true, false),
+            1 => (false, false, true, false),
+            2 => (false, true, true, false),
+            3 => (false, true, false, false),
+            4 => (false, true, false, true),
+            5 => (false, false, false, true),
+            6 => (true, false, false, true),
+            7 => (true, false, false, false),
+            _ => return None,
+        };
+        Some(Edges { left, right, top, bottom })
+    }
+
+    // the cursor for resizing from these edges, by its name in cursor themes and the X cursor
+    // font
+    pub fn cursor_name(&self) -> &'static str {
+        match (self.left, self.right, self.top, self.bottom) {
+            (true, false, true, false) => "top_left_corner",
+            (false, false, true, false) => "top_side",
+            (false, true, true, false) => "top_right_corner",
+            (false, true, false, false) => "right_side",
+            (false, false, false, true) => "bottom_side",
+            (true, false, false, true) => "bottom_left_corner",
+            (true, false, false, false) => "left_side",
+            _ => "bottom_right_corner",
+        }
+    }
+}
+
+// position and inner size of a window being resized so its moving edges follow the pointer, snapped
+// to the grid and no smaller than min, width and height. the other edges stay where they are
+pub fn resize_edges(ptr: (i32, i32), win: (i32, i32, u32, u32), edges: Edges, border: i32, min: (u32, u32), grid: Option<u32>) -> (i32, i32, u32, u32) {
+    let (x, width) = resize_axis(ptr.0, win.0, win.2, edges.left, edges.right, border, min.0, grid);
+    let (y, height) = resize_axis(ptr.1, win.1, win.3, edges.top, edges.bottom, border, min.1, grid);
+    (x, y, width, height)
+}
+
+// one dimension of resize_edges. start is the outer position, size the inner size
+#[allow(clippy::too_many_arguments)]
+fn resize_axis(ptr: i32, start: i32, size: u32, low: bool, high: bool, border: i32, min: u32, grid: Option<u32>) -> (i32, u32) {
+    if high {
+        let size = snap_to_grid(ptr - start + 1 - 2*border, grid).max(min as i32);
+        (start, size as u32)
+    }
+    else if low {
+        // far edge stays put
+        let end = start + size as i32 + 2*border;
+        let size = (end - snap_to_grid(ptr, grid) - 2*border).max(min as i32);
+        (end - 2*border - size, size as u32)
+    }
+    else {
+        (start, size)
+    }
+}
+
+// round to the nearest multiple of the grid, if there is one
+pub fn snap_to_grid(value: i32, grid: Option<u32>) -> i32 {
+    match grid {
+        Some(grid) if grid > 1 => {
+            let grid = grid as i32;
+            (value + grid/2).div_euclid(grid) * grid
+        },
+        _ => value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCREEN: &Monitor = &Monitor { x: 0, y: 0, width: 1920, height: 1080 };
+
+    #[test]
+    fn move_keeps_pointer_offset() {
+        assert_eq!(clamp_move((500, 400), (10, 20), (300, 200), SCREEN, None), (490, 380));
+    }
+
+    #[test]
+    fn move_stops_at_screen_edges() {
+        assert_eq!(clamp_move((5, 5), (10, 20), (300, 200), SCREEN, None), (0, 0));
+        assert_eq!(clamp_move((-100, -50), (0, 0), (300, 200), SCREEN, None), (0, 0));
+        assert_eq!(clamp_move((1900, 1070), (10, 20), (300, 200), SCREEN, None), (1620, 880));
+    }
+
+    #[test]
+    fn move_window_larger_than_screen_keeps_top_left_visible() {
+        assert_eq!(clamp_move((800, 600), (10, 10), (2500, 1500), SCREEN, None), (0, 0));
+        assert_eq!(clamp_move((-800, -600), (10, 10), (2500, 1500), SCREEN, None), (0, 0));
+    }
+
+    #[test]
+    fn move_snaps_before_clamping() {
+        assert_eq!(clamp_move((107, 95), (0, 0), (300, 200), SCREEN, Some(16)), (112, 96));
+        assert_eq!(clamp_move((1915, 1075), (0, 0), (300, 200), SCREEN, Some(16)), (1620, 880));
+    }
+
+    #[test]
+    fn move_stays_out_of_reserved_edges() {
+        let area = SCREEN.inset(24, 0, 0, 100);
+        assert_eq!(clamp_move((500, 5), (10, 10), (300, 200), &area, None), (490, 24));
+        assert_eq!(clamp_move((1900, 400), (10, 10), (300, 200), &area, None), (1520, 390));
+    }
+
+    const EDGES: &[i32] = &[0, 1920, 3840];
+
+    #[test]
+    fn resistance_holds_at_edge() {
+        // right side at 1900, pushed 15 past the monitor boundary
+        assert_eq!(resist(1635, 1600, 300, EDGES, 20), (1620, 15));
+        assert_eq!(resist(-15, 10, 300, EDGES, 20), (0, -15));
+    }
+
+    #[test]
+    fn resistance_breaks_through() {
+        assert_eq!(resist(1645, 1620, 300, EDGES, 20), (1645, 0));
+        assert_eq!(resist(-25, 0, 300, EDGES, 20), (-25, 0));
+    }
+
+    #[test]
+    fn resistance_only_for_leading_side() {
+        // left side crossing the boundary on the way right doesn't stop it
+        assert_eq!(resist(1930, 1910, 300, EDGES, 20), (1930, 0));
+        // nor moving away from an edge it's past
+        assert_eq!(resist(1650, 1645, 300, EDGES, 20), (1650, 0));
+    }
+
+    #[test]
+    fn no_resistance() {
+        assert_eq!(resist(1635, 1600, 300, EDGES, 0), (1635, 0));
+    }
+
+    const WIN: (i32, i32, u32, u32) = (100, 100, 300, 200);
+    const MIN: (u32, u32) = (32, 32);
+
+    #[test]
+    fn resize_follows_pointer() {
+        // 2px border each side
+        assert_eq!(resize_edges((503, 403), WIN, Edges::BOTTOM_RIGHT, 2, MIN, None), (100, 100, 400, 300));
+    }
+
+    #[test]
+    fn resize_stops_at_minimum() {
+        assert_eq!(resize_edges((110, 500), WIN, Edges::BOTTOM_RIGHT, 2, MIN, None), (100, 100, 32, 397));
+        assert_eq!(resize_edges((0, 0), WIN, Edges::BOTTOM_RIGHT, 2, MIN, None), (100, 100, 32, 32));
+    }
+
+    #[test]
+    fn resize_minimum_per_axis() {
+        assert_eq!(resize_edges((0, 0), WIN, Edges::BOTTOM_RIGHT, 2, (200, 10), None), (100, 100, 200, 10));
+    }
+
+    #[test]
+    fn resize_from_negative_origin() {
+        assert_eq!(resize_edges((99, 49), (-100, -50, 10, 10), Edges::BOTTOM_RIGHT, 0, MIN, None), (-100, -50, 200, 100));
+    }
+
+    #[test]
+    fn resize_top_left_keeps_bottom_right_fixed() {
+        // outer right and bottom edges are at 404 and 304
+        let (x, y, width, height) = resize_edges((50, 80), WIN, Edges::from_direction(0).unwrap(), 2, MIN, None);
+        assert_eq!((x, y, width, height), (50, 80, 350, 220));
+        assert_eq!((x + width as i32 + 4, y + height as i32 + 4), (404, 304));
+    }
+
+    #[test]
+    fn resize_single_edge_leaves_other_axis() {
+        assert_eq!(resize_edges((50, 999), WIN, Edges::from_direction(7).unwrap(), 2, MIN, None), (50, 100, 350, 200));
+        assert_eq!(resize_edges((999, 503), WIN, Edges::from_direction(5).unwrap(), 2, MIN, None), (100, 100, 300, 400));
+    }
+
+    #[test]
+    fn resize_left_past_right_edge_stops_at_minimum() {
+        let (x, _, width, _) = resize_edges((1000, 100), WIN, Edges::from_direction(7).unwrap(), 2, MIN, None);
+        assert_eq!((x, width), (404 - 4 - 32, 32));
+    }
+
+    #[test]
+    fn directions_outside_the_eight_edges() {
+        assert_eq!(Edges::from_direction(8), None);
+        assert_eq!(Edges::from_direction(11), None);
+    }
+
+    #[test]
+    fn cursor_for_each_direction() {
+        let names: Vec<_> = (0..8).map(|d| Edges::from_direction(d).unwrap().cursor_name()).collect();
+        assert_eq!(names, vec![
+            "top_left_corner", "top_side", "top_right_corner", "right_side",
+            "bottom_right_corner", "bottom_side", "bottom_left_corner", "left_side",
+        ]);
+    }
+
+    #[test]
+    fn snap_rounds_to_nearest() {
+        assert_eq!(snap_to_grid(7, Some(16)), 0);
+        assert_eq!(snap_to_grid(8, Some(16)), 16);
+        assert_eq!(snap_to_grid(-9, Some(16)), -16);
+        assert_eq!(snap_to_grid(13, None), 13);
+        assert_eq!(snap_to_grid(13, Some(1)), 13);
+    }
+}
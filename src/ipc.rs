@@ -0,0 +1,163 @@
+use log::{debug, warn};
+use std::fs;
+use std::io::{self, Read, Write};
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+
+// commands from scripts over a unix socket, one per line. each response is one or more lines
+// ending with an empty one. the first line is "ok" or "error <reason>", and anything after it is
+// key=value pairs, eg
+//
+//   $ echo query 0x1a00003 | nc -U $PANKO_SOCKET
+//   ok
+//   id=0x1a00003
+//   class=XTerm
+//   ...
+//
+// query takes a window id, and stats gives counts of events, round trips and flushes since we
+// started, for comparing performance. trace on and trace off log every event we get, and
+// everything else of ours at trace level, whatever RUST_LOG says. dump gives the layout as a
+// line of JSON: monitors, the workspace showing on each, and every managed window with its id,
+// geometry, workspace and states. restore takes that JSON back and puts windows that are still
+// around on the monitor and workspace, and at the geometry, it has for them, for reproducing
+// bug reports.
+//
+// the socket is $XDG_RUNTIME_DIR/panko-<display>.sock, or under /tmp if that isn't set, and its
+// path is put in $PANKO_SOCKET for anything we start.
+pub struct Ipc {
+    listener: UnixListener,
+    path: PathBuf,
+    clients: Vec<Client>,
+}
+
+struct Client {
+    stream: UnixStream,
+    // bytes read that aren't a whole line yet
+    buf: Vec<u8>,
+    // hung up, or broke; gone once it's had its replies
+    closed: bool,
+}
+
+// most a client can send without a newline before we give up on it, with room for restoring a
+// big layout
+const MAX_LINE: usize = 256*1024;
+
+fn socket_path() -> PathBuf {
+    // ":0.0" and "host:1" both make a reasonable file name once the odd characters are gone
+    let display: String = std::env::var("DISPLAY").unwrap_or_default().chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '.' { c } else { '_' })
+        .collect();
+
+    match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir).join(format!("panko-{}.sock", display)),
+        _ => {
+            let uid = unsafe { libc::getuid() };
+            PathBuf::from(format!("/tmp/panko-{}-{}.sock", uid, display))
+        },
+    }
+}
+
+impl Ipc {
+    pub fn listen() -> io::Result<Ipc> {
+        let path = socket_path();
+
+        // we're the window manager for this display, so anything already there is left over
+        let _ = fs::remove_file(&path);
+
+        let listener = UnixListener::bind(&path)?;
+        listener.set_nonblocking(true)?;
+
+        debug!("listening for commands on {:?}", path);
+        std::env::set_var("PANKO_SOCKET", &path);
+
+        Ok(Ipc {
+            listener,
+            path,
+            clients: vec![],
+        })
+    }
+
+    // everything the event loop should wake up for
+    pub fn fds(&self) -> Vec<RawFd> {
+        let mut fds = vec![self.listener.as_raw_fd()];
+        fds.extend(self.clients.iter().map(|c| c.stream.as_raw_fd()));
+        fds
+    }
+
+    // accept new clients and collect any whole lines they've sent, as (client, line)
+    pub fn read_commands(&mut self) -> Vec<(usize, String)> {
+        loop {
+            match self.listener.accept() {
+                Ok((stream, _)) => {
+                    if let Err(e) = stream.set_nonblocking(true) {
+                        warn!("couldn't set up command connection: {}", e);
+                        continue;
+                    }
+                    self.clients.push(Client {
+                        stream,
+                        buf: vec![],
+                        closed: false,
+                    });
+                },
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) => {
+                    warn!("couldn't accept command connection: {}", e);
+                    break;
+                },
+            }
+        }
+
+        let mut commands = vec![];
+
+        for (n, client) in self.clients.iter_mut().enumerate() {
+            let mut data = [0u8; 1024];
+            loop {
+                match client.stream.read(&mut data) {
+                    Ok(0) => {
+                        client.closed = true;
+                        break;
+                    },
+                    Ok(len) => client.buf.extend_from_slice(&data[..len]),
+                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                    Err(e) => {
+                        debug!("command connection failed: {}", e);
+                        client.closed = true;
+                        break;
+                    },
+                }
+            }
+
+            while let Some(end) = client.buf.iter().position(|&b| b == b'\n') {
+                let line: Vec<_> = client.buf.drain(..=end).collect();
+                let line = String::from_utf8_lossy(&line).trim().to_string();
+                if !line.is_empty() {
+                    commands.push((n, line));
+                }
+            }
+        }
+
+        commands
+    }
+
+    pub fn reply(&mut self, client: usize, response: &str) {
+        if let Some(c) = self.clients.get_mut(client) {
+            if let Err(e) = c.stream.write_all(format!("{}\n\n", response).as_bytes()) {
+                debug!("couldn't reply to command: {}", e);
+                c.closed = true;
+            }
+        }
+    }
+
+    // drop clients that have hung up or are sending nonsense. only after replying, since it
+    // changes client numbers
+    pub fn drop_closed(&mut self) {
+        self.clients.retain(|c| !c.closed && c.buf.len() <= MAX_LINE);
+    }
+}
+
+impl Drop for Ipc {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
@@ -0,0 +1,366 @@
+use std::fmt;
+
+// just enough JSON for the dump and restore commands: whole numbers only, and objects keep their
+// keys in order so a dump reads the same way every time
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(i64),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+// deeper than anything we write, and short of running out of stack on hostile input
+const MAX_DEPTH: usize = 32;
+
+impl Value {
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            Value::Number(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            Value::Bool(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Value::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Value {
+        Value::Bool(b)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(n: i64) -> Value {
+        Value::Number(n)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Value {
+        Value::String(s.to_string())
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(v: Option<T>) -> Value {
+        v.map_or(Value::Null, Into::into)
+    }
+}
+
+// compact, all on one line, so it fits in a command and a reply
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Null => write!(f, "null"),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Number(n) => write!(f, "{}", n),
+            Value::String(s) => write_string(f, s),
+            Value::Array(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, "]")
+            },
+            Value::Object(fields) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+                write!(f, "}}")
+            },
+        }
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter, s: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+pub fn parse(text: &str) -> Result<Value, String> {
+    let mut parser = Parser {
+        chars: text.chars().collect(),
+        pos: 0,
+    };
+
+    let value = parser.value(0)?;
+    parser.skip_space();
+    match parser.peek() {
+        None => Ok(value),
+        Some(c) => Err(format!("unexpected {:?} after the end at {}", c, parser.pos)),
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek();
+        self.pos += 1;
+        c
+    }
+
+    fn skip_space(&mut self) {
+        while self.peek().is_some_and(|c| matches!(c, ' ' | '\t' | '\n' | '\r')) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        self.skip_space();
+        match self.next() {
+            Some(c) if c == expected => Ok(()),
+            Some(c) => Err(format!("expected {:?}, got {:?} at {}", expected, c, self.pos - 1)),
+            None => Err(format!("expected {:?}, got the end", expected)),
+        }
+    }
+
+    fn word(&mut self, word: &str, value: Value) -> Result<Value, String> {
+        for expected in word.chars() {
+            if self.next() != Some(expected) {
+                return Err(format!("bad word at {}, expected {:?}", self.pos - 1, word));
+            }
+        }
+        Ok(value)
+    }
+
+    fn value(&mut self, depth: usize) -> Result<Value, String> {
+        if depth > MAX_DEPTH {
+            return Err("nested too deeply".to_string());
+        }
+
+        self.skip_space();
+        match self.peek() {
+            Some('{') => self.object(depth),
+            Some('[') => self.array(depth),
+            Some('"') => self.string().map(Value::String),
+            Some('-' | '0'..='9') => self.number(),
+            Some('t') => self.word("true", Value::Bool(true)),
+            Some('f') => self.word("false", Value::Bool(false)),
+            Some('n') => self.word("null", Value::Null),
+            Some(c) => Err(format!("unexpected {:?} at {}", c, self.pos)),
+            None => Err("unexpected end".to_string()),
+        }
+    }
+
+    fn object(&mut self, depth: usize) -> Result<Value, String> {
+        self.expect('{')?;
+        let mut fields = vec![];
+
+        self.skip_space();
+        if self.peek() == Some('}') {
+            self.pos += 1;
+            return Ok(Value::Object(fields));
+        }
+
+        loop {
+            self.skip_space();
+            if self.peek() != Some('"') {
+                return Err(format!("expected a key at {}", self.pos));
+            }
+            let key = self.string()?;
+            self.expect(':')?;
+            fields.push((key, self.value(depth + 1)?));
+
+            self.skip_space();
+            match self.next() {
+                Some(',') => continue,
+                Some('}') => return Ok(Value::Object(fields)),
+                _ => return Err(format!("expected ',' or '}}' at {}", self.pos - 1)),
+            }
+        }
+    }
+
+    fn array(&mut self, depth: usize) -> Result<Value, String> {
+        self.expect('[')?;
+        let mut items = vec![];
+
+        self.skip_space();
+        if self.peek() == Some(']') {
+            self.pos += 1;
+            return Ok(Value::Array(items));
+        }
+
+        loop {
+            items.push(self.value(depth + 1)?);
+
+            self.skip_space();
+            match self.next() {
+                Some(',') => continue,
+                Some(']') => return Ok(Value::Array(items)),
+                _ => return Err(format!("expected ',' or ']' at {}", self.pos - 1)),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<Value, String> {
+        let start = self.pos;
+        if self.peek() == Some('-') {
+            self.pos += 1;
+        }
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        if self.peek().is_some_and(|c| matches!(c, '.' | 'e' | 'E')) {
+            return Err(format!("only whole numbers are expected, at {}", start));
+        }
+
+        let digits: String = self.chars[start..self.pos].iter().collect();
+        digits.parse().map(Value::Number).map_err(|_| format!("bad number {:?} at {}", digits, start))
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut s = String::new();
+
+        loop {
+            match self.next() {
+                Some('"') => return Ok(s),
+                Some('\\') => {
+                    let c = match self.next() {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('/') => '/',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('u') => self.escaped_char()?,
+                        _ => return Err(format!("bad escape at {}", self.pos - 1)),
+                    };
+                    s.push(c);
+                },
+                Some(c) if (c as u32) < 0x20 => return Err(format!("control character in string at {}", self.pos - 1)),
+                Some(c) => s.push(c),
+                None => return Err("unterminated string".to_string()),
+            }
+        }
+    }
+
+    // after \u: four hex digits, or two sets of them for a surrogate pair
+    fn escaped_char(&mut self) -> Result<char, String> {
+        let high = self.hex4()?;
+        let code = match high {
+            0xd800..=0xdbff => {
+                if self.next() != Some('\\') || self.next() != Some('u') {
+                    return Err(format!("unpaired surrogate at {}", self.pos));
+                }
+                match self.hex4()? {
+                    low @ 0xdc00..=0xdfff => 0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00),
+                    _ => return Err(format!("unpaired surrogate at {}", self.pos)),
+                }
+            },
+            code => code,
+        };
+        char::from_u32(code).ok_or(format!("bad character escape at {}", self.pos))
+    }
+
+    fn hex4(&mut self) -> Result<u32, String> {
+        let digits: String = (0..4).filter_map(|_| self.next()).collect();
+        match digits.len() {
+            4 => u32::from_str_radix(&digits, 16).map_err(|_| format!("bad hex {:?} at {}", digits, self.pos)),
+            _ => Err("unexpected end in escape".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips() {
+        let value = Value::Object(vec![
+            ("id".to_string(), "0x1a00003".into()),
+            ("x".to_string(), (-40).into()),
+            ("floating".to_string(), true.into()),
+            ("title".to_string(), Value::from(None::<&str>)),
+            ("list".to_string(), Value::Array(vec![1.into(), Value::Array(vec![]), Value::Object(vec![])])),
+        ]);
+        let text = value.to_string();
+        assert_eq!(text, r#"{"id":"0x1a00003","x":-40,"floating":true,"title":null,"list":[1,[],{}]}"#);
+        assert_eq!(parse(&text).unwrap(), value);
+    }
+
+    #[test]
+    fn strings_escape() {
+        let title = "say \"hi\"\n\\ \u{1} é 🦀";
+        let text = Value::from(title).to_string();
+        assert_eq!(text, "\"say \\\"hi\\\"\\n\\\\ \\u0001 é 🦀\"");
+        assert_eq!(parse(&text).unwrap().as_str(), Some(title));
+        assert_eq!(parse(r#""\u00e9\ud83e\udd80\/""#).unwrap().as_str(), Some("é🦀/"));
+    }
+
+    #[test]
+    fn lookups() {
+        let value = parse(r#" { "a" : [ 1 , 2 ] , "b" : false , "a" : null } "#).unwrap();
+        assert_eq!(value.get("a").and_then(Value::as_array).map(|a| a.len()), Some(2));
+        assert_eq!(value.get("b").and_then(Value::as_bool), Some(false));
+        assert_eq!(value.get("c"), None);
+        assert_eq!(Value::Number(3).get("a"), None);
+    }
+
+    #[test]
+    fn rejects_bad_json() {
+        for bad in ["", "{", "[1,]", "{\"a\" 1}", "{a:1}", "1.5", "1e3", "tru", "\"open", "\"\\ud800\"", "[1] 2", "99999999999999999999"] {
+            assert!(parse(bad).is_err(), "{:?} parsed", bad);
+        }
+        assert!(parse(&"[".repeat(100)).is_err());
+    }
+}
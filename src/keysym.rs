@@ -0,0 +1,79 @@
+// the handful of X keysyms we bind to by default; see X11/keysymdef.h for the rest
+pub const SPACE: u32 = 0x0020;
+pub const PLUS: u32 = 0x002b;
+pub const COMMA: u32 = 0x002c;
+pub const MINUS: u32 = 0x002d;
+pub const PERIOD: u32 = 0x002e;
+pub const KEY_0: u32 = 0x0030;
+pub const KEY_1: u32 = 0x0031;
+pub const EQUAL: u32 = 0x003d;
+pub const GRAVE: u32 = 0x0060;
+pub const C: u32 = 0x0063;
+pub const E: u32 = 0x0065;
+pub const F: u32 = 0x0066;
+pub const G: u32 = 0x0067;
+pub const H: u32 = 0x0068;
+pub const J: u32 = 0x006a;
+pub const K: u32 = 0x006b;
+pub const L: u32 = 0x006c;
+pub const M: u32 = 0x006d;
+pub const N: u32 = 0x006e;
+pub const T: u32 = 0x0074;
+pub const W: u32 = 0x0077;
+pub const X: u32 = 0x0078;
+pub const TAB: u32 = 0xff09;
+
+// names for keys that aren't a single printable character
+const NAMES: &[(&str, u32)] = &[
+    ("space", 0x0020),
+    ("apostrophe", 0x0027),
+    ("plus", 0x002b),
+    ("comma", 0x002c),
+    ("minus", 0x002d),
+    ("period", 0x002e),
+    ("slash", 0x002f),
+    ("semicolon", 0x003b),
+    ("equal", 0x003d),
+    ("bracketleft", 0x005b),
+    ("backslash", 0x005c),
+    ("bracketright", 0x005d),
+    ("grave", 0x0060),
+    ("BackSpace", 0xff08),
+    ("Tab", 0xff09),
+    ("Return", 0xff0d),
+    ("Escape", 0xff1b),
+    ("Home", 0xff50),
+    ("Left", 0xff51),
+    ("Up", 0xff52),
+    ("Right", 0xff53),
+    ("Down", 0xff54),
+    ("Prior", 0xff55),
+    ("Next", 0xff56),
+    ("End", 0xff57),
+    ("Delete", 0xffff),
+];
+
+// keysym for a name as written in the config, eg "a", "5", "period", "Tab", "F1"
+pub fn from_name(name: &str) -> Option<u32> {
+    // printable latin-1 keysyms are just the character; bindings are on the unshifted key
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        let c = c.to_ascii_lowercase();
+        return if c.is_ascii_graphic() {
+            Some(c as u32)
+        }
+        else {
+            None
+        };
+    }
+
+    if let Some(&(_, keysym)) = NAMES.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)) {
+        return Some(keysym);
+    }
+
+    // F1 is 0xffbe, and they run on from there
+    match name.strip_prefix('F').and_then(|n| n.parse::<u32>().ok()) {
+        Some(n) if (1..=35).contains(&n) => Some(0xffbe + n - 1),
+        _ => None,
+    }
+}
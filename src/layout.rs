@@ -0,0 +1,289 @@
+// where tiled windows go, kept free of X so it can be tested
+use crate::monitor::Monitor;
+use crate::window::Geometry;
+
+// how much of the width the first tile gets, in percent, unless it's been changed
+pub const MASTER_DEFAULT: u32 = 50;
+pub const MASTER_MIN: u32 = 10;
+pub const MASTER_MAX: u32 = 90;
+
+// the first window takes master percent of the monitor on the left and the rest share what's
+// left on the right, one above the other. geometries are position and inner size, with room
+// left for the border
+pub fn tile(monitor: &Monitor, count: usize, master: u32, border: i32) -> Vec<Geometry> {
+    match count {
+        0 => vec![],
+        1 => vec![cell(monitor.x, monitor.y, monitor.width, monitor.height, border)],
+        _ => {
+            let master_width = (monitor.width as i64*master.clamp(MASTER_MIN, MASTER_MAX) as i64/100) as i32;
+            let stack_width = monitor.width - master_width;
+            let stack_x = monitor.x + master_width;
+
+            let mut cells = vec![cell(monitor.x, monitor.y, master_width, monitor.height, border)];
+
+            // last one takes whatever's left after dividing
+            let stack_count = (count - 1) as i32;
+            let stack_height = monitor.height/stack_count;
+            for n in 0..stack_count {
+                let y = monitor.y + n*stack_height;
+                let height = if n == stack_count - 1 { monitor.y + monitor.height - y } else { stack_height };
+                cells.push(cell(stack_x, y, stack_width, height, border));
+            }
+
+            cells
+        },
+    }
+}
+
+// columns down the left of the area, for windows pinned to these outer widths, and the area left
+// for the rest. a column can't take more than what's left, so with too many the last ones are
+// narrower and the others get squeezed to nothing
+pub fn pin_columns(area: &Monitor, widths: &[u32], border: i32) -> (Vec<Geometry>, Monitor) {
+    let end = area.x + area.width;
+    let mut x = area.x;
+
+    let cells = widths.iter()
+        .map(|&width| {
+            let width = (width.min(i32::MAX as u32) as i32).min(end - x).max(0);
+            let cell = cell(x, area.y, width, area.height, border);
+            x += width;
+            cell
+        })
+        .collect();
+
+    (cells, Monitor { x, width: end - x, ..*area })
+}
+
+// every window gets the whole monitor and they stack, with the one in front showing, like tabs
+pub fn tabbed(monitor: &Monitor, count: usize, border: i32) -> Vec<Geometry> {
+    vec![cell(monitor.x, monitor.y, monitor.width, monitor.height, border); count]
+}
+
+// the biggest window with the aspect ratio (width, height) that fits on the monitor, in the
+// middle. the ratio is for the inner size, since that's what the client draws into
+pub fn fit_aspect(monitor: &Monitor, (aspect_width, aspect_height): (u32, u32), border: i32) -> Geometry {
+    let available_width = (monitor.width - 2*border).max(1) as u64;
+    let available_height = (monitor.height - 2*border).max(1) as u64;
+    let (aspect_width, aspect_height) = (aspect_width.max(1) as u64, aspect_height.max(1) as u64);
+
+    // as wide as it can be, unless that makes it too tall
+    let (width, height) = match available_width*aspect_height/aspect_width {
+        height if height <= available_height => (available_width, height),
+        _ => (available_height*aspect_width/aspect_height, available_height),
+    };
+    let (width, height) = (width.max(1) as u32, height.max(1) as u32);
+    let (x, y) = centre(monitor, width, height, border);
+
+    Geometry {
+        x,
+        y,
+        width,
+        height,
+    }
+}
+
+// a window too big for the monitor made small enough to fit, in the middle. keep_aspect scales
+// both sides by the same amount rather than just cutting off what's over. it doesn't go below
+// the minimum size, so it can still end up too big, with its top-left corner on the monitor
+pub fn shrink_to_fit(monitor: &Monitor, (width, height): (u32, u32), (min_width, min_height): (u32, u32), keep_aspect: bool, border: i32) -> Geometry {
+    let available_width = (monitor.width - 2*border).max(1) as u32;
+    let available_height = (monitor.height - 2*border).max(1) as u32;
+    let (width, height) = (width.max(1), height.max(1));
+
+    let (width, height) = if keep_aspect && (width > available_width || height > available_height) {
+        // whichever side is over by more decides how much both shrink
+        if available_width as u64*height as u64 <= available_height as u64*width as u64 {
+            (available_width, (height as u64*available_width as u64/width as u64) as u32)
+        }
+        else {
+            ((width as u64*available_height as u64/height as u64) as u32, available_height)
+        }
+    }
+    else {
+        (width.min(available_width), height.min(available_height))
+    };
+    let (width, height) = (width.max(min_width).max(1), height.max(min_height).max(1));
+    let (x, y) = centre(monitor, width, height, border);
+
+    Geometry {
+        x,
+        y,
+        width,
+        height,
+    }
+}
+
+// top-left of a window this size in the middle of the monitor. one that's too big to fit
+// keeps its top-left corner on the monitor, so it can still be grabbed
+pub fn centre(monitor: &Monitor, width: u32, height: u32, border: i32) -> (i32, i32) {
+    let outer_width = width.min(i32::MAX as u32) as i32 + 2*border;
+    let outer_height = height.min(i32::MAX as u32) as i32 + 2*border;
+
+    (
+        monitor.x + ((monitor.width - outer_width)/2).max(0),
+        monitor.y + ((monitor.height - outer_height)/2).max(0),
+    )
+}
+
+// the middle of the area, no wider than max_width. the rest is left empty either side so very
+// wide monitors don't get very wide windows
+pub fn pillarbox(area: &Monitor, max_width: u32) -> Monitor {
+    let max_width = max_width.clamp(1, i32::MAX as u32) as i32;
+    if area.width <= max_width {
+        return *area;
+    }
+
+    Monitor {
+        x: area.x + (area.width - max_width)/2,
+        width: max_width,
+        ..*area
+    }
+}
+
+// a window filling the outer rect, border and all
+fn cell(x: i32, y: i32, width: i32, height: i32, border: i32) -> Geometry {
+    Geometry {
+        x,
+        y,
+        width: (width - 2*border).max(1) as u32,
+        height: (height - 2*border).max(1) as u32,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MONITOR: Monitor = Monitor { x: 1920, y: 0, width: 1920, height: 1080 };
+
+    fn rects(cells: &[Geometry]) -> Vec<(i32, i32, u32, u32)> {
+        cells.iter().map(|g| (g.x, g.y, g.width, g.height)).collect()
+    }
+
+    #[test]
+    fn nothing_to_tile() {
+        assert!(tile(&MONITOR, 0, 50, 2).is_empty());
+    }
+
+    #[test]
+    fn one_window_fills_monitor() {
+        assert_eq!(rects(&tile(&MONITOR, 1, 50, 2)), vec![(1920, 0, 1916, 1076)]);
+    }
+
+    #[test]
+    fn two_windows_side_by_side() {
+        assert_eq!(rects(&tile(&MONITOR, 2, 50, 0)), vec![(1920, 0, 960, 1080), (2880, 0, 960, 1080)]);
+    }
+
+    #[test]
+    fn stack_shares_right_half() {
+        assert_eq!(rects(&tile(&MONITOR, 4, 50, 1)), vec![
+            (1920, 0, 958, 1078),
+            (2880, 0, 958, 358),
+            (2880, 360, 958, 358),
+            (2880, 720, 958, 358),
+        ]);
+    }
+
+    #[test]
+    fn master_takes_its_share() {
+        assert_eq!(rects(&tile(&MONITOR, 3, 60, 0)), vec![
+            (1920, 0, 1152, 1080),
+            (3072, 0, 768, 540),
+            (3072, 540, 768, 540),
+        ]);
+        assert_eq!(rects(&tile(&MONITOR, 2, 100, 0))[0], (1920, 0, 1728, 1080));
+    }
+
+    #[test]
+    fn pinned_columns_come_first() {
+        let (cells, rest) = pin_columns(&MONITOR, &[300, 200], 2);
+        assert_eq!(rects(&cells), vec![(1920, 0, 296, 1076), (2220, 0, 196, 1076)]);
+        assert_eq!(rest, Monitor { x: 2420, y: 0, width: 1420, height: 1080 });
+        assert_eq!(rects(&tile(&rest, 2, 50, 0)), vec![(2420, 0, 710, 1080), (3130, 0, 710, 1080)]);
+    }
+
+    #[test]
+    fn pinned_columns_stop_at_edge() {
+        let (cells, rest) = pin_columns(&MONITOR, &[1500, 1000, 10], 0);
+        assert_eq!(rects(&cells), vec![(1920, 0, 1500, 1080), (3420, 0, 420, 1080), (3840, 0, 1, 1080)]);
+        assert_eq!(rest.width, 0);
+        let (cells, rest) = pin_columns(&MONITOR, &[], 0);
+        assert!(cells.is_empty());
+        assert_eq!(rest, MONITOR);
+    }
+
+    #[test]
+    fn tabs_share_monitor() {
+        assert_eq!(rects(&tabbed(&MONITOR, 3, 2)), vec![(1920, 0, 1916, 1076); 3]);
+        assert!(tabbed(&MONITOR, 0, 2).is_empty());
+    }
+
+    #[test]
+    fn aspect_fits_width() {
+        let g = fit_aspect(&Monitor { x: 0, y: 0, width: 1920, height: 1200 }, (16, 9), 0);
+        assert_eq!(rects(&[g]), vec![(0, 60, 1920, 1080)]);
+    }
+
+    #[test]
+    fn aspect_fits_height() {
+        let g = fit_aspect(&MONITOR, (4, 3), 2);
+        assert_eq!(rects(&[g]), vec![(2161, 0, 1434, 1076)]);
+    }
+
+    #[test]
+    fn centre_counts_border() {
+        assert_eq!(centre(&MONITOR, 800, 600, 2), (2478, 238));
+    }
+
+    #[test]
+    fn centre_keeps_big_window_on_monitor() {
+        assert_eq!(centre(&MONITOR, 2000, 600, 0), (1920, 240));
+    }
+
+    #[test]
+    fn shrink_cuts_to_monitor() {
+        let g = shrink_to_fit(&MONITOR, (2400, 900), (0, 0), false, 2);
+        assert_eq!(rects(&[g]), vec![(1920, 88, 1916, 900)]);
+    }
+
+    #[test]
+    fn shrink_keeps_aspect() {
+        let g = shrink_to_fit(&MONITOR, (3840, 1080), (0, 0), true, 0);
+        assert_eq!(rects(&[g]), vec![(1920, 270, 1920, 540)]);
+        let g = shrink_to_fit(&MONITOR, (1000, 2160), (0, 0), true, 0);
+        assert_eq!(rects(&[g]), vec![(2630, 0, 500, 1080)]);
+    }
+
+    #[test]
+    fn shrink_stops_at_min_size() {
+        let g = shrink_to_fit(&MONITOR, (2400, 1200), (2000, 0), false, 0);
+        assert_eq!(rects(&[g]), vec![(1920, 0, 2000, 1080)]);
+    }
+
+    #[test]
+    fn pillarbox_centres_on_wide_monitor() {
+        let ultrawide = Monitor { x: 0, y: 0, width: 5120, height: 1440 };
+        assert_eq!(pillarbox(&ultrawide, 3440), Monitor { x: 840, y: 0, width: 3440, height: 1440 });
+        assert_eq!(rects(&tile(&pillarbox(&ultrawide, 3440), 2, 50, 0)), vec![
+            (840, 0, 1720, 1440),
+            (2560, 0, 1720, 1440),
+        ]);
+    }
+
+    #[test]
+    fn pillarbox_leaves_narrow_monitor() {
+        assert_eq!(pillarbox(&MONITOR, 2560), MONITOR);
+    }
+
+    #[test]
+    fn stack_remainder_goes_to_last() {
+        let cells = tile(&Monitor { x: 0, y: 0, width: 101, height: 100 }, 4, 50, 0);
+        assert_eq!(rects(&cells), vec![
+            (0, 0, 50, 100),
+            (50, 0, 51, 33),
+            (50, 33, 51, 33),
+            (50, 66, 51, 34),
+        ]);
+    }
+}
@@ -0,0 +1,102 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+// log output for --log-file, for sessions started by a display manager where stderr goes
+// nowhere useful. once the file passes MAX_SIZE it's moved to <path>.1, the old .1 to .2 and so
+// on, keeping `keep` of them, and a new one is started. that only happens between lines, so a
+// record is never split across files
+pub struct LogFile {
+    path: PathBuf,
+    keep: usize,
+    file: File,
+    size: u64,
+    max_size: u64,
+    at_line_start: bool,
+}
+
+const MAX_SIZE: u64 = 10*1024*1024;
+
+impl LogFile {
+    pub fn open(path: &Path, keep: usize) -> io::Result<LogFile> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let size = file.metadata()?.len();
+
+        Ok(LogFile {
+            path: path.to_path_buf(),
+            keep,
+            file,
+            size,
+            max_size: MAX_SIZE,
+            at_line_start: true,
+        })
+    }
+
+    fn rotated(&self, n: usize) -> PathBuf {
+        let mut name = self.path.clone().into_os_string();
+        name.push(format!(".{}", n));
+        PathBuf::from(name)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        // with nothing to keep, the one file just starts again
+        if self.keep > 0 {
+            for n in (1..self.keep).rev() {
+                let _ = fs::rename(self.rotated(n), self.rotated(n+1));
+            }
+            fs::rename(&self.path, self.rotated(1))?;
+        }
+
+        self.file = OpenOptions::new().create(true).write(true).truncate(true).open(&self.path)?;
+        self.size = 0;
+
+        Ok(())
+    }
+}
+
+impl Write for LogFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.at_line_start && self.size >= self.max_size {
+            self.rotate()?;
+        }
+
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        if written > 0 {
+            self.at_line_start = buf[written-1] == b'\n';
+        }
+
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rotates_between_lines() {
+        let dir = std::env::temp_dir().join(format!("panko-logfile-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("panko.log");
+
+        let mut log = LogFile::open(&path, 2).unwrap();
+        log.max_size = 10;
+        for line in ["first line\n", "second ", "line\n", "third line\n", "fourth line\n"] {
+            log.write_all(line.as_bytes()).unwrap();
+        }
+
+        let read = |p: PathBuf| fs::read_to_string(p).unwrap();
+        assert_eq!(read(path.clone()), "fourth line\n");
+        assert_eq!(read(log.rotated(1)), "third line\n");
+        assert_eq!(read(log.rotated(2)), "second line\n");
+        assert!(!log.rotated(3).exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,76 @@
+use log::{LevelFilter, Log, Metadata, Record};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::logfile::LogFile;
+
+// env_logger, filtered by RUST_LOG as usual, except that tracing can be turned on while we're
+// running (SIGUSR2, or "trace on" over the socket) to get everything of ours at trace level,
+// every event included, without a restart. the inner logger lets everything through and the
+// RUST_LOG filter is applied here, so it can be bypassed
+struct Logger {
+    inner: env_logger::Logger,
+    filter: env_logger::filter::Filter,
+}
+
+static TRACING: AtomicBool = AtomicBool::new(false);
+
+impl Logger {
+    fn wanted(&self, metadata: &Metadata) -> bool {
+        self.filter.enabled(metadata) || (TRACING.load(Ordering::Relaxed) && metadata.target().starts_with("panko"))
+    }
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.wanted(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if self.wanted(record.metadata()) {
+            self.inner.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+// to stderr, or a rotating file if there's a path that can be opened
+pub fn init(log_file: Option<&Path>, log_keep: usize) {
+    let mut builder = env_logger::Builder::new();
+    builder.filter_level(LevelFilter::Trace);
+
+    if let Some(path) = log_file {
+        match LogFile::open(path, log_keep) {
+            Ok(file) => {
+                builder.target(env_logger::Target::Pipe(Box::new(file)));
+            },
+            Err(e) => eprintln!("panko: couldn't open {:?} for logging, using stderr: {}", path, e),
+        }
+    }
+
+    let filter = env_logger::filter::Builder::from_env("RUST_LOG").build();
+    log::set_max_level(filter.filter());
+
+    let logger = Logger {
+        inner: builder.build(),
+        filter,
+    };
+    if log::set_boxed_logger(Box::new(logger)).is_err() {
+        eprintln!("panko: logger already set up");
+    }
+}
+
+pub fn tracing() -> bool {
+    TRACING.load(Ordering::Relaxed)
+}
+
+pub fn set_tracing(on: bool) {
+    TRACING.store(on, Ordering::Relaxed);
+
+    // the log macros check this before anything else, so it has to come up to let trace through
+    let normal = env_logger::filter::Builder::from_env("RUST_LOG").build().filter();
+    log::set_max_level(if on { LevelFilter::Trace } else { normal });
+}
@@ -1,12 +1,59 @@
+mod atoms;
+mod buttons;
+mod config;
+mod drag;
+mod ipc;
+mod json;
+mod keysym;
+mod layout;
+mod logfile;
+mod logging;
 mod manager;
+mod monitor;
+mod rules;
+mod session;
+mod signals;
+mod status;
 mod window;
+mod xcursor;
 
+use std::path::PathBuf;
+
+use crate::config::Config;
 use crate::manager::Manager;
 
+const USAGE: &str = "usage: panko [--log-file <path>] [--log-keep <count>]";
+
+// logging goes to stderr unless there's --log-file, with --log-keep old files kept as it rotates
+fn parse_args() -> Result<(Option<PathBuf>, usize), String> {
+    let mut log_file = None;
+    let mut log_keep = 3;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--log-file" => log_file = Some(PathBuf::from(args.next().ok_or("--log-file needs a path")?)),
+            "--log-keep" => {
+                log_keep = args.next()
+                    .and_then(|n| n.parse().ok())
+                    .ok_or("--log-keep needs a number")?;
+            },
+            _ => return Err(format!("unknown option {:?}", arg)),
+        }
+    }
+
+    Ok((log_file, log_keep))
+}
+
 fn main() -> xcb::Result<()> {
-    env_logger::Builder::new().parse_default_env().init();
+    let (log_file, log_keep) = parse_args().unwrap_or_else(|e| {
+        eprintln!("panko: {}\n{}", e, USAGE);
+        std::process::exit(2);
+    });
+
+    logging::init(log_file.as_deref(), log_keep);
 
-    let mut wm = Manager::connect()?;
+    let mut wm = Manager::connect(Config::load())?;
     wm.attach_existing_windows()?;
     wm.run()
 }
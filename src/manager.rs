@@ -1,51 +1,337 @@
-use xcb::{x, Connection, Xid};
-use log::debug;
-use std::collections::HashMap;
+use xcb::{randr, render, x, Connection, Xid, XidNew};
+use log::{debug, error, info, trace, warn};
+use std::cell::Cell;
+use std::collections::{hash_map, HashMap, HashSet};
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::process::Command;
+use std::thread;
+use std::time::{Duration, Instant};
 
-use crate::window::Window;
+use crate::atoms::Atoms;
+use crate::buttons;
+use crate::config::{Action, ButtonBinding, Config, Length, Oversized};
+use crate::drag::{self, Edges};
+use crate::ipc::Ipc;
+use crate::json::{self, Value};
+use crate::layout;
+use crate::logging;
+use crate::monitor::{self, Monitor};
+use crate::session;
+use crate::signals;
+use crate::status::Status;
+use crate::window::{Geometry, Window, WindowType};
+use crate::xcursor;
 
-const BORDER_WIDTH: i32 = 2;
+// WM_STATE values, from ICCCM
+const WITHDRAWN_STATE: u32 = 0;
+const NORMAL_STATE: u32 = 1;
+const ICONIC_STATE: u32 = 3;
+
+// percent opacity_up and opacity_down change it by
+const OPACITY_STEP: i32 = 10;
+
+// percent of the width grow_master and shrink_master change the first tile by
+const MASTER_STEP: i32 = 5;
+
+// milliseconds of server time a quit press stays armed for, with confirm_quit
+const QUIT_CONFIRM_TIME: i32 = 3000;
+
+// the pointer shapes we use, by name, with their glyphs in the X cursor font
+const CURSORS: [(&str, u16); 10] = [
+    ("left_ptr", 68),
+    ("fleur", 52),
+    ("top_left_corner", 134),
+    ("top_side", 138),
+    ("top_right_corner", 136),
+    ("right_side", 96),
+    ("bottom_right_corner", 14),
+    ("bottom_side", 16),
+    ("bottom_left_corner", 12),
+    ("left_side", 70),
+];
 
 pub struct Manager {
     pub conn: Connection,
     pub screen: x::ScreenBuf,
+    screen_width: i32,
+    screen_height: i32,
+
+    pub atoms: Atoms,
+
+    pub config: Config,
 
     pub windows: HashMap<x::Window, Window>,
+    pub focused: Option<x::Window>,
+    // managed windows that have had focus, most recently first
+    focus_history: Vec<x::Window>,
+    // tracked windows from bottom to top, as far as we know
+    pub stacking: Vec<x::Window>,
+    // windows we made ourselves, which are never managed
+    own_windows: HashSet<x::Window>,
+    // minimized windows, oldest first
+    minimized: Vec<x::Window>,
+    // mapped windows in the order they came, which is the order they tile in
+    layout_order: Vec<x::Window>,
+
+    // last geometry by WM_CLASS, persisted across sessions
+    remembered: HashMap<String, Geometry>,
+    remembered_dirty: bool,
+
+    pub monitors: Vec<Monitor>,
+    randr: bool,
+
+    // what config sizes have been multiplied by, and what the primary monitor says they should
+    // be unless the config says otherwise
+    scale: u32,
+    auto_scale: u32,
+
+    // workspace showing on each monitor, all the same if they're not per-monitor
+    pub current_workspaces: Vec<usize>,
+    // bit per tag being viewed, across every monitor. never none
+    selected_tags: u32,
+    // workspaces whose tiles are laid out as tabs, on whichever monitor they're showing
+    tabbed: HashSet<usize>,
+    // workspaces where the first tile's width has been changed from the default, in percent
+    master_widths: HashMap<usize, u32>,
 
     drag_state: Option<DragState>,
+    cycle: Option<Cycle>,
+    // picked by swap_windows, waiting for the window to swap it with
+    marked: Option<x::Window>,
+
+    // where windows we're adopting were before we started, taken by map_window
+    restoring: HashMap<x::Window, Restored>,
+
+    // server time of the last key or button press we saw, for telling which new windows the
+    // user asked for
+    last_user_time: u32,
+    // server time of the last event that had one, for SetInputFocus, grabs and WM_TAKE_FOCUS,
+    // so they're ordered against other clients'. toolkits ignore WM_TAKE_FOCUS without it
+    last_time: x::Timestamp,
+
+    // time of the first quit press, when waiting for a second to confirm it
+    quit_armed: Option<u32>,
+    quitting: bool,
+
+    // focused window is fullscreen, so keep out of its way
+    fullscreen_active: bool,
+
+    // where the pointer was when focus last moved without it. crossings while it's still there
+    // are windows mapping or restacking under it, not the user pointing at something
+    pointer_at_focus: Option<(i16, i16)>,
+
+    key_grabs: Vec<KeyGrab>,
+    button_grabs: Vec<ButtonBinding>,
+
+    // readable when a signal we care about has arrived
+    signal_fd: Option<RawFd>,
+
+    // commands from scripts, if the socket could be set up
+    ipc: Option<Ipc>,
+
+    // things to do later, run from the event loop when they're due
+    timers: Vec<(Instant, Timer)>,
+
+    // status lines for an external bar, if configured
+    status: Option<Status>,
+
+    stats: Stats,
+
+    // tiles for striped borders, if they're configured
+    focused_pixmap: Option<x::Pixmap>,
+    unfocused_pixmap: Option<x::Pixmap>,
+
+    // pointer shapes by name, once they're made
+    cursors: HashMap<&'static str, x::Cursor>,
+    // ARGB picture format for cursor theme images, if the server has RENDER
+    cursor_format: Option<render::Pictformat>,
+
+    // dropdown window shown over every workspace when toggled on, and where it was last
+    scratchpad: Option<x::Window>,
+    scratchpad_shown: bool,
+    scratchpad_geometry: Option<Geometry>,
+}
+
+// how much talking to the server we've done, for putting numbers on slowness. reported on
+// SIGUSR1 and by the stats command
+struct Stats {
+    started: Instant,
+    events: u64,
+    // cells, since most of the places that wait for replies only borrow us
+    round_trips: Cell<u64>,
+    flushes: Cell<u64>,
+}
+
+impl Stats {
+    fn lines(&self) -> Vec<String> {
+        vec![
+            format!("uptime={}", self.started.elapsed().as_secs()),
+            format!("events={}", self.events),
+            format!("round_trips={}", self.round_trips.get()),
+            format!("flushes={}", self.flushes.get()),
+        ]
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct KeyGrab {
+    keycode: x::Keycode,
+    modifiers: x::ModMask,
+    action: Action,
 }
 
 #[derive(Clone, Copy, Debug)]
-enum DragButton { Left, Right }
+enum Timer {
+    // raise the window if it still has focus
+    AutoRaise(x::Window),
+    // see if the drag's button is still down
+    StaleDrag,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum DragKind { Move, Resize(Edges) }
+
+impl DragKind {
+    fn cursor_name(&self) -> &'static str {
+        match self {
+            DragKind::Move => "fleur",
+            DragKind::Resize(edges) => edges.cursor_name(),
+        }
+    }
+}
+
+// what a window left over from before we started had, from its own properties or the layout we
+// saved when we last stopped
+#[derive(Clone, Copy, Debug, Default)]
+struct Restored {
+    workspace: Option<usize>,
+    floating: Option<bool>,
+    iconic: bool,
+    tags: Option<u32>,
+    // where it is now and the border it has there, for a window that's already up
+    geometry: Option<(Geometry, u32)>,
+}
+
+// which windows a cycle goes through
+#[derive(Clone, Copy, Debug)]
+enum CycleSet {
+    All,
+    SameClass,
+    SameMonitor,
+}
+
+// picking a window with the keyboard, while the modifier is held
+struct Cycle {
+    // candidates, most recently focused first, and the one showing now
+    windows: Vec<x::Window>,
+    index: usize,
+    // stacking order when it started, so previewed windows can go back where they were
+    original_stacking: Vec<x::Window>,
+    // letting go of any of these picks the one showing
+    release_keycodes: Vec<x::Keycode>,
+}
 
 #[derive(Clone, Copy, Debug)]
 struct DragState {
-    button: DragButton,
+    kind: DragKind,
     window: x::Window,
     off_x: i16,
     off_y: i16,
+    // how far past an edge the window is being pushed while edge resistance holds it
+    overshoot: (i32, i32),
+}
+
+// what we need to hear about on the root window, by what uses it. the first part is needed to be
+// the window manager at all:
+//
+//   SUBSTRUCTURE_REDIRECT  map, configure and circulate requests from top-level windows
+//   SUBSTRUCTURE_NOTIFY    top-level windows being created, destroyed, reparented, unmapped,
+//                          configured or moved by gravity
+//   STRUCTURE_NOTIFY       the root changing size, without randr to tell us
+//
+// the second is for features that can do without:
+//
+//   BUTTON_PRESS           wheel_switches_workspaces and root_menu_command. only one client can
+//                          select these, so someone else having them shouldn't cost us the
+//                          redirect. it's not a grab, so clicks on windows are left alone
+fn root_event_mask(config: &Config, randr: bool) -> (x::EventMask, x::EventMask) {
+    // keymap state arrives when our keyboard grab starts, which a cycle's modifiers are checked
+    // against
+    let mut required = x::EventMask::SUBSTRUCTURE_REDIRECT | x::EventMask::SUBSTRUCTURE_NOTIFY | x::EventMask::KEYMAP_STATE;
+    if !randr {
+        required |= x::EventMask::STRUCTURE_NOTIFY;
+    }
+
+    let mut optional = x::EventMask::empty();
+    if config.wheel_switches_workspaces || config.root_menu_command.is_some() {
+        optional |= x::EventMask::BUTTON_PRESS;
+    }
+
+    (required, optional)
+}
+
+// separate requests, so the optional part failing leaves the rest
+fn select_root_events(conn: &xcb::Connection, root: x::Window, config: &Config, randr: bool) {
+    let (required, optional) = root_event_mask(config, randr);
+
+    conn.send_request_checked(&x::ChangeWindowAttributes {
+        window: root,
+        value_list: &[
+            x::Cw::EventMask(required),
+        ],
+    });
+
+    if optional.is_empty() {
+        return;
+    }
+
+    let cookie = conn.send_request_checked(&x::ChangeWindowAttributes {
+        window: root,
+        value_list: &[
+            x::Cw::EventMask(required | optional),
+        ],
+    });
+    if conn.check_request(cookie).is_err() {
+        warn!("couldn't select {:?} on the root window, something else has them", optional);
+    }
 }
 
 impl Manager {
-    pub fn connect() -> xcb::Result<Manager> {
+    // connect to the X server and take over as window manager
+    pub fn connect(config: Config) -> xcb::Result<Manager> {
+        let display = std::env::var("DISPLAY").unwrap_or_default();
+
         // connect to server
-        let (conn, scr_num) = xcb::Connection::connect(None)?;
+        let (conn, scr_num) = match xcb::Connection::connect_with_extensions(None, &[], &[xcb::Extension::RandR, xcb::Extension::Render]) {
+            Ok(c) => c,
+            Err(e) => {
+                error!("couldn't connect to X server at {:?}: {}", display, e);
+                return Err(e.into());
+            },
+        };
 
-        // get screen handle
-        let screen = conn.get_setup().roots().nth(scr_num as usize).unwrap().to_owned();
+        // get screen handle. libxcb checks the screen number itself these days, but an odd
+        // DISPLAY or a headless server with no screens shouldn't be able to panic us
+        let screen = match conn.get_setup().roots().nth(scr_num as usize) {
+            Some(s) => s.to_owned(),
+            None => {
+                error!("X server at {:?} has no screen {}", display, scr_num);
+                return Err(xcb::Error::Connection(xcb::ConnError::ClosedInvalidScreen));
+            },
+        };
+
+        let randr = conn.active_extensions().any(|e| e == xcb::Extension::RandR);
 
         // ask to be the window manager
-        conn.send_request_checked(&x::ChangeWindowAttributes {
-            window: screen.root(),
-            value_list: &[
-                x::Cw::EventMask(
-                    x::EventMask::SUBSTRUCTURE_REDIRECT |
-                    x::EventMask::STRUCTURE_NOTIFY |
-                    x::EventMask::SUBSTRUCTURE_NOTIFY |
-                    x::EventMask::PROPERTY_CHANGE
-                ),
-            ],
-        });
+        select_root_events(&conn, screen.root(), &config, randr);
+
+        // ask to hear about resolution and monitor changes
+        if randr {
+            conn.send_request_checked(&randr::SelectInput {
+                window: screen.root(),
+                enable: randr::NotifyMask::SCREEN_CHANGE,
+            });
+        }
 
         // release all grabs
         conn.send_request_checked(&x::UngrabKey {
@@ -54,12 +340,13 @@ impl Manager {
             modifiers: x::ModMask::ANY,
         });
 
-        // grab Left (click to raise)
+        // grab Left (click to raise). synchronous, so the click can be passed on to the window
+        // once it's raised
         conn.send_request_checked(&x::GrabButton {
             owner_events: false,
             grab_window: screen.root(),
             event_mask: x::EventMask::BUTTON_PRESS,
-            pointer_mode: x::GrabMode::Async,
+            pointer_mode: x::GrabMode::Sync,
             keyboard_mode: x::GrabMode::Async,
             confine_to: screen.root(),
             cursor: x::CURSOR_NONE,
@@ -95,339 +382,5585 @@ impl Manager {
 
         conn.flush()?;
 
-        Ok(Manager {
+        let mut wm = Manager::new(conn, screen, config)?;
+
+        // SIGHUP to reload config, SIGUSR1 to log stats, SIGUSR2 to turn tracing on and off, and
+        // SIGTERM and SIGINT to quit, letting go of windows properly on the way out
+        let signals = [libc::SIGHUP, libc::SIGUSR1, libc::SIGUSR2, libc::SIGTERM, libc::SIGINT];
+        wm.signal_fd = match signals::install(&signals) {
+            Ok(fd) => Some(fd),
+            Err(e) => {
+                warn!("couldn't set up signal handling: {}", e);
+                None
+            },
+        };
+
+        wm.ipc = match Ipc::listen() {
+            Ok(ipc) => Some(ipc),
+            Err(e) => {
+                warn!("couldn't set up command socket: {}", e);
+                None
+            },
+        };
+
+        wm.remembered = session::load_geometries();
+
+        wm.grab_keys()?;
+        wm.grab_buttons();
+
+        // tell clients which hints we understand
+        wm.conn.send_request_checked(&x::ChangeProperty {
+            mode: x::PropMode::Replace,
+            window: wm.screen.root(),
+            property: wm.atoms.net_supported,
+            r#type: x::ATOM_ATOM,
+            data: &wm.atoms.supported(),
+        });
+        wm.make_supporting_window();
+        if wm.config.background.is_some() {
+            wm.paint_background();
+        }
+        wm.conn.flush()?;
+
+        Ok(wm)
+    }
+
+    // EWMH's proof that a window manager is running: a window of ours named on the root, which
+    // names itself too, so a stale root property left by one that's gone doesn't count. it's
+    // override-redirect and off screen, and kept out of the windows we manage
+    fn make_supporting_window(&mut self) {
+        let window: x::Window = self.conn.generate_id();
+        self.own_windows.insert(window);
+
+        self.conn.send_request_checked(&x::CreateWindow {
+            depth: x::COPY_FROM_PARENT as u8,
+            wid: window,
+            parent: self.screen.root(),
+            x: -1,
+            y: -1,
+            width: 1,
+            height: 1,
+            border_width: 0,
+            class: x::WindowClass::InputOnly,
+            visual: x::COPY_FROM_PARENT,
+            value_list: &[
+                x::Cw::OverrideRedirect(true),
+            ],
+        });
+
+        for target in [self.screen.root(), window] {
+            self.conn.send_request_checked(&x::ChangeProperty {
+                mode: x::PropMode::Replace,
+                window: target,
+                property: self.atoms.net_supporting_wm_check,
+                r#type: x::ATOM_WINDOW,
+                data: &[window],
+            });
+        }
+        self.conn.send_request_checked(&x::ChangeProperty {
+            mode: x::PropMode::Replace,
+            window,
+            property: self.atoms.net_wm_name,
+            r#type: self.atoms.utf8_string,
+            data: b"panko",
+        });
+    }
+
+    // state for managing the screen over an existing connection, without taking anything over
+    // on the server. connect() does that part
+    pub fn new(conn: Connection, screen: x::ScreenBuf, config: Config) -> xcb::Result<Manager> {
+        let atoms = Atoms::intern_all(&conn)?;
+        let randr = conn.active_extensions().any(|e| e == xcb::Extension::RandR);
+
+        let screen_width = screen.width_in_pixels() as i32;
+        let screen_height = screen.height_in_pixels() as i32;
+
+        let status = config.status_output.as_deref().map(Status::new);
+
+        let mut wm = Manager {
             conn,
             screen,
+            screen_width,
+            screen_height,
+            atoms,
+            config,
             windows: HashMap::default(),
+            focused: None,
+            focus_history: Vec::default(),
+            stacking: Vec::default(),
+            minimized: Vec::default(),
+            layout_order: Vec::default(),
+            own_windows: HashSet::default(),
+            remembered: HashMap::default(),
+            remembered_dirty: false,
+            monitors: Vec::default(),
+            randr,
+            scale: 1,
+            auto_scale: 1,
+            current_workspaces: Vec::default(),
+            selected_tags: 1,
+            tabbed: HashSet::default(),
+            master_widths: HashMap::default(),
             drag_state: None,
-        })
-    }
+            cycle: None,
+            marked: None,
+            restoring: HashMap::default(),
+            last_user_time: 0,
+            last_time: x::CURRENT_TIME,
+            quit_armed: None,
+            quitting: false,
+            fullscreen_active: false,
+            pointer_at_focus: None,
+            key_grabs: Vec::default(),
+            button_grabs: Vec::default(),
+            signal_fd: None,
+            ipc: None,
+            timers: Vec::default(),
+            status,
+            stats: Stats {
+                started: Instant::now(),
+                events: 0,
+                round_trips: Cell::new(0),
+                flushes: Cell::new(0),
+            },
+            focused_pixmap: None,
+            unfocused_pixmap: None,
+            cursors: HashMap::default(),
+            cursor_format: None,
+            scratchpad: None,
+            scratchpad_shown: false,
+            scratchpad_geometry: None,
+        };
 
-    pub fn attach_existing_windows(&mut self) -> xcb::Result<()> {
-        self.windows.clear();
+        wm.refresh_monitors()?;
+        wm.make_border_pixmaps();
+        wm.cursor_format = wm.find_cursor_format()?;
+        wm.make_cursors()?;
 
-        let tree = self.conn.wait_for_reply(self.conn.send_request(&x::QueryTree {
-            window: self.screen.root(),
-        }))?;
+        Ok(wm)
+    }
 
-        tree.children().iter().for_each(|&w| {
-            let attr_cookie = self.conn.send_request(&x::GetWindowAttributes {
-                window: w,
-            });
+    fn grab_keys(&mut self) -> xcb::Result<()> {
+        // drop whatever we had before, the mapping may have changed
+        self.conn.send_request_checked(&x::UngrabKey {
+            key: x::GRAB_ANY,
+            grab_window: self.screen.root(),
+            modifiers: x::ModMask::ANY,
+        });
+        self.key_grabs.clear();
 
-            let do_map = match self.conn.wait_for_reply(attr_cookie) {
-                Err(e) => {
-                    debug!("couldn't get window attrs: {:?}", e);
-                    false
-                },
-                Ok(attrs) => {
-                    debug!("existing window {:?}, attrs {:?}", w, attrs);
+        let setup = self.conn.get_setup();
+        let min_keycode = setup.min_keycode();
+        let max_keycode = setup.max_keycode();
 
-                    // map the window if its not already mapped and not managed by someone else
-                    attrs.map_state() != x::MapState::Unmapped && !attrs.override_redirect()
-                },
-            };
+        let mapping = self.wait_for_reply(self.conn.send_request(&x::GetKeyboardMapping {
+            first_keycode: min_keycode,
+            count: max_keycode - min_keycode + 1,
+        }))?;
+        let per_keycode = mapping.keysyms_per_keycode() as usize;
 
-            if do_map {
-                debug!("mapping existing window {:?}", w);
+        for binding in &self.config.key_bindings {
+            // grab every keycode that produces the keysym, there may be more than one
+            let keycodes = mapping.keysyms().chunks(per_keycode)
+                .enumerate()
+                .filter(|(_, syms)| syms.contains(&binding.keysym))
+                .map(|(i, _)| min_keycode + i as x::Keycode);
 
-                self.windows.insert(w, Window {
-                    x_window: w,
+            for keycode in keycodes {
+                self.conn.send_request_checked(&x::GrabKey {
+                    owner_events: false,
+                    grab_window: self.screen.root(),
+                    modifiers: binding.modifiers,
+                    key: keycode,
+                    pointer_mode: x::GrabMode::Async,
+                    keyboard_mode: x::GrabMode::Async,
                 });
 
-                self.map_window(w);
+                self.key_grabs.push(KeyGrab {
+                    keycode,
+                    modifiers: binding.modifiers,
+                    action: binding.action,
+                });
             }
-        });
+        }
 
-        self.conn.flush()?;
+        debug!("key grabs: {:?}", self.key_grabs);
 
         Ok(())
     }
 
-    pub fn run(&mut self) -> xcb::Result<()> {
-        loop {
-            match self.conn.wait_for_event()? {
+    // buttons bound to actions in the config, on top of the fixed click and drag grabs
+    fn grab_buttons(&mut self) {
+        for grab in self.button_grabs.drain(..) {
+            buttons::ungrab(&self.conn, self.screen.root(), grab.button, grab.modifiers);
+        }
 
-                // new client, just track it
-                xcb::Event::X(x::Event::CreateNotify(ev)) => {
-                    debug!("new window: {:?}", ev.window());
+        for binding in &self.config.button_bindings {
+            if buttons::grab(&self.conn, self.screen.root(), binding.button, binding.modifiers) {
+                self.button_grabs.push(*binding);
+            }
+            else {
+                warn!("couldn't grab button {} with {:?}, something else has it", binding.button, binding.modifiers);
+            }
+        }
 
-                    self.windows.insert(ev.window(), Window {
-                        x_window: ev.window(),
-                    });
-                },
+        debug!("button grabs: {:?}", self.button_grabs);
+    }
 
-                // window gone, forget it
-                xcb::Event::X(x::Event::DestroyNotify(ev)) => {
-                    debug!("window destroyed: {:?}", ev.window());
+    fn run_action(&mut self, action: Action) -> xcb::Result<()> {
+        debug!("running action {:?}", action);
 
-                    self.windows.remove(&ev.window());
-                }
+        match action {
+            Action::SendToNextMonitor => self.send_to_monitor(1),
+            Action::SendToPrevMonitor => self.send_to_monitor(-1),
+            Action::TileToNextMonitor => self.tile_to_monitor(1),
+            Action::TileToPrevMonitor => self.tile_to_monitor(-1),
+            Action::FocusNextMonitor => self.focus_monitor(1),
+            Action::FocusPrevMonitor => self.focus_monitor(-1),
+            Action::SwitchWorkspace(n) => self.switch_workspace(n),
+            Action::SendToWorkspace(n) => self.send_to_workspace(n),
+            Action::NextWorkspace => self.step_workspace(1),
+            Action::PrevWorkspace => self.step_workspace(-1),
+            Action::ViewTag(n) => self.view_tags(1 << n),
+            Action::ToggleViewTag(n) => self.view_tags(self.selected_tags ^ 1 << n),
+            Action::ToggleTag(n) => self.toggle_tag(n),
+            Action::BringAll => self.bring_all(),
+            Action::CycleNext => self.cycle_windows(1, CycleSet::All),
+            Action::CyclePrev => self.cycle_windows(-1, CycleSet::All),
+            Action::CycleClassNext => self.cycle_windows(1, CycleSet::SameClass),
+            Action::CycleClassPrev => self.cycle_windows(-1, CycleSet::SameClass),
+            Action::CycleMonitorNext => self.cycle_windows(1, CycleSet::SameMonitor),
+            Action::CycleMonitorPrev => self.cycle_windows(-1, CycleSet::SameMonitor),
+            Action::ToggleMaximize => self.toggle_maximize(),
+            Action::ToggleZoom => self.toggle_zoom(),
+            Action::Minimize => self.minimize_focused(),
+            Action::RestoreMinimized => self.restore_minimized(),
+            Action::ReloadConfig => self.reload_config(),
+            Action::ToggleScratchpad => self.toggle_scratchpad(),
+            Action::CloseWindow => match self.focused {
+                Some(window) => self.close_window(window),
+                None => Ok(()),
+            },
+            Action::ToggleFloating => self.toggle_floating(),
+            Action::CentreWindow => self.centre_window(),
+            Action::ToggleTabbed => self.toggle_tabbed(),
+            Action::TabNext => self.step_tab(1),
+            Action::TabPrev => self.step_tab(-1),
+            Action::GrowMaster => self.resize_master(MASTER_STEP),
+            Action::ShrinkMaster => self.resize_master(-MASTER_STEP),
+            Action::ResetLayout => self.reset_layout(),
+            Action::SwapWindows => self.swap_windows(),
+            Action::OpacityUp => self.step_opacity(OPACITY_STEP),
+            Action::OpacityDown => self.step_opacity(-OPACITY_STEP),
+            Action::Quit => self.quit(),
+        }
+    }
 
-                // client wants to be displayed
-                xcb::Event::X(x::Event::MapRequest(ev)) => {
-                    self.map_window(ev.window());
-                    self.conn.flush()?;
-                },
+    // the class half of WM_CLASS
+    fn get_class(&self, window: x::Window) -> xcb::Result<Option<String>> {
+        let cookie = self.conn.send_request(&x::GetProperty {
+            delete: false,
+            window,
+            property: x::ATOM_WM_CLASS,
+            r#type: x::ATOM_STRING,
+            long_offset: 0,
+            long_length: 256,
+        });
 
-                // left button inside window area
-                xcb::Event::X(x::Event::ButtonPress(ev)) if ev.state().is_empty() => {
-                    // ignore if we're not over a window
-                    if ev.child().is_none() {
-                        continue;
-                    }
+        let reply = match self.wait_for_reply(cookie) {
+            Ok(r) => r,
+            Err(xcb::Error::Protocol(e)) => {
+                debug!("couldn't get class for {:?}: {:?}", window, e);
+                return Ok(None);
+            },
+            Err(e) => return Err(e),
+        };
 
-                    // bring window to front
-                    self.bring_window_to_front(ev.child());
-                    self.conn.flush()?;
-                },
+        if reply.format() != 8 {
+            return Ok(None);
+        }
 
-                // Mod4+button inside window area
-                xcb::Event::X(x::Event::ButtonPress(ev)) => {
-                    // ignore if we're not over a window
-                    if ev.child().is_none() {
-                        continue;
-                    }
+        // instance and class, each nul-terminated
+        let class = reply.value::<u8>().split(|&b| b == 0)
+            .nth(1)
+            .filter(|c| !c.is_empty())
+            .map(|c| String::from_utf8_lossy(c).into_owned());
 
-                    // bring window to front
-                    self.bring_window_to_front(ev.child());
-
-                    // grab the pointer for window move
-                    self.conn.send_request(&x::GrabPointer {
-                        owner_events: false,
-                        grab_window: self.screen.root(),
-                        event_mask: x::EventMask::BUTTON_RELEASE | x::EventMask::BUTTON_MOTION | x::EventMask::POINTER_MOTION_HINT,
-                        pointer_mode: x::GrabMode::Async,
-                        keyboard_mode: x::GrabMode::Async,
-                        confine_to: self.screen.root(),
-                        cursor: x::CURSOR_NONE,
-                        time: x::CURRENT_TIME,
-                    });
+        Ok(class)
+    }
 
-                    // will need window geometry to compute drag offset
-                    let geometry = self.conn.wait_for_reply(self.conn.send_request(&x::GetGeometry {
-                        drawable: x::Drawable::Window(ev.child()),
-                    }))?;
-                    let off_x = ev.root_x() - geometry.x();
-                    let off_y = ev.root_y() - geometry.y();
-
-                    // record window
-                    self.drag_state = match ev.detail() {
-                        1 => Some(DragState {
-                            button: DragButton::Left,
-                            window: ev.child(),
-                            off_x,
-                            off_y,
-                        }),
-                        3 => Some(DragState {
-                            button: DragButton::Right,
-                            window: ev.child(),
-                            off_x,
-                            off_y,
-                        }),
-                        _ => None,
-                    };
+    // the reply, or None if the window's already gone
+    fn get_property(&self, window: x::Window, property: x::Atom, r#type: x::Atom, long_length: u32) -> xcb::Result<Option<x::GetPropertyReply>> {
+        let cookie = self.conn.send_request(&x::GetProperty {
+            delete: false,
+            window,
+            property,
+            r#type,
+            long_offset: 0,
+            long_length,
+        });
 
-                    debug!("button down on {:?}, drag state {:?}", ev.child(), self.drag_state);
-                },
+        match self.wait_for_reply(cookie) {
+            Ok(r) => Ok(Some(r)),
+            Err(xcb::Error::Protocol(e)) => {
+                debug!("couldn't get property {:?} for {:?}: {:?}", property, window, e);
+                Ok(None)
+            },
+            Err(e) => Err(e),
+        }
+    }
 
-                xcb::Event::X(x::Event::ButtonRelease(ev)) => {
-                    // just release the pointer
-                    self.conn.send_request_checked(&x::UngrabPointer {
-                        time: x::CURRENT_TIME,
-                    });
-                    self.conn.flush()?;
+    fn get_pid(&self, window: x::Window) -> xcb::Result<Option<i32>> {
+        let pid = self.get_property(window, self.atoms.net_wm_pid, x::ATOM_CARDINAL, 1)?
+            .filter(|r| r.format() == 32)
+            .and_then(|r| r.value::<u32>().first().copied())
+            .map(|pid| pid as i32)
+            .filter(|&pid| pid > 0);
+
+        Ok(pid)
+    }
+
+    // _NET_WM_USER_TIME, which may be on a separate window named by _NET_WM_USER_TIME_WINDOW
+    fn get_user_time(&self, window: x::Window) -> xcb::Result<Option<u32>> {
+        let time_window = self.get_property(window, self.atoms.net_wm_user_time_window, x::ATOM_WINDOW, 1)?
+            .filter(|r| r.format() == 32)
+            .and_then(|r| r.value::<x::Window>().first().copied())
+            .filter(|w| !w.is_none())
+            .unwrap_or(window);
+
+        let time = self.get_property(time_window, self.atoms.net_wm_user_time, x::ATOM_CARDINAL, 1)?
+            .filter(|r| r.format() == 32)
+            .and_then(|r| r.value::<u32>().first().copied());
+
+        Ok(time)
+    }
+
+    fn get_opacity(&self, window: x::Window) -> xcb::Result<Option<u32>> {
+        let opacity = self.get_property(window, self.atoms.net_wm_window_opacity, x::ATOM_CARDINAL, 1)?
+            .filter(|r| r.format() == 32)
+            .and_then(|r| r.value::<u32>().first().copied());
 
-                    self.drag_state = None;
+        Ok(opacity)
+    }
+
+    // the size the client gave its window, if WM_NORMAL_HINTS says it meant it: USSize (bit 1)
+    // or PSize (bit 3)
+    fn get_requested_size(&self, window: x::Window) -> xcb::Result<Option<(u32, u32)>> {
+        let requested = self.get_property(window, x::ATOM_WM_NORMAL_HINTS, x::ATOM_WM_SIZE_HINTS, 1)?
+            .filter(|r| r.format() == 32)
+            .and_then(|r| r.value::<u32>().first().copied())
+            .is_some_and(|flags| flags & (1 << 1 | 1 << 3) != 0);
+
+        if !requested {
+            return Ok(None);
+        }
 
-                    debug!("button release on {:?}, drag cleared", ev.child());
+        Ok(self.get_geometry(window)?.map(|g| (g.width, g.height)))
+    }
+
+    // PAspect (bit 7) in WM_NORMAL_HINTS, with min_aspect as the ratio. players that want one
+    // exact shape set max_aspect the same
+    fn get_aspect(&self, window: x::Window) -> xcb::Result<Option<(u32, u32)>> {
+        let aspect = self.get_property(window, x::ATOM_WM_NORMAL_HINTS, x::ATOM_WM_SIZE_HINTS, 13)?
+            .filter(|r| r.format() == 32)
+            .and_then(|r| match *r.value::<u32>() {
+                [flags, _, _, _, _, _, _, _, _, _, _, numerator, denominator] if flags & (1 << 7) != 0 => {
+                    Some((numerator, denominator)).filter(|_| numerator > 0 && denominator > 0)
                 },
+                _ => None,
+            });
 
-                xcb::Event::X(x::Event::MotionNotify(_)) => {
-                    if let Some(drag_state) = self.drag_state {
-                        let pointer = self.conn.wait_for_reply(self.conn.send_request(&x::QueryPointer {
-                            window: self.screen.root(),
-                        }))?;
-                        let geometry = self.conn.wait_for_reply(self.conn.send_request(&x::GetGeometry {
-                            drawable: x::Drawable::Window(drag_state.window),
-                        }))?;
-
-                        match drag_state.button {
-                            DragButton::Left => {
-
-                                let win_width = geometry.width() as i32 + 2*BORDER_WIDTH;
-                                let win_height = geometry.height() as i32 + 2*BORDER_WIDTH;
-
-                                let scr_width = self.screen.width_in_pixels() as i32;
-                                let scr_height = self.screen.height_in_pixels() as i32;
-
-                                let off_x = drag_state.off_x as i32;
-                                let off_y = drag_state.off_y as i32;
-
-                                let ptr_x = pointer.root_x() as i32 - off_x;
-                                let ptr_y = pointer.root_y() as i32 - off_y;
-
-                                let new_x = if ptr_x <= 0 {
-                                    0
-                                } else if ptr_x + win_width > scr_width {
-                                    scr_width - win_width
-                                } else {
-                                    ptr_x
-                                };
-                                let new_y = if ptr_y <= 0 {
-                                    0
-                                } else if ptr_y + win_height > scr_height {
-                                    scr_height - win_height
-                                } else {
-                                    ptr_y
-                                };
-
-                                debug!("moving {:?} to {},{}", drag_state.window, new_x, new_y);
-
-                                self.conn.send_request_checked(&x::ConfigureWindow {
-                                    window: drag_state.window,
-                                    value_list: &[
-                                        x::ConfigWindow::X(new_x),
-                                        x::ConfigWindow::Y(new_y),
-                                    ],
-                                });
-                                self.conn.flush()?;
-                            },
-
-                            DragButton::Right => {
-
-                                let win_x = geometry.x() as i32;
-                                let win_y = geometry.y() as i32;
-
-                                let ptr_x = pointer.root_x() as i32;
-                                let ptr_y = pointer.root_y() as i32;
-
-                                let new_width = ptr_x - win_x + 1 - BORDER_WIDTH*2;
-                                let new_height = ptr_y - win_y + 1 - BORDER_WIDTH*2;
-
-                                if new_width >= 32 && new_height >= 32 {
-                                    debug!("resizing {:?} to {}x{}", drag_state.window, new_width, new_height);
-
-                                    self.conn.send_request_checked(&x::ConfigureWindow {
-                                        window: drag_state.window,
-                                        value_list: &[
-                                            x::ConfigWindow::Width(new_width as u32),
-                                            x::ConfigWindow::Height(new_height as u32),
-                                        ],
-                                    });
-                                    self.conn.flush()?;
-                                }
-                            },
-                        }
-                    }
+        Ok(aspect)
+    }
+
+    // PMinSize (bit 4) in WM_NORMAL_HINTS, or PBaseSize (bit 3) if there's no minimum, which
+    // ICCCM says stands in for it
+    fn get_min_size(&self, window: x::Window) -> xcb::Result<Option<(u32, u32)>> {
+        let min_size = self.get_property(window, x::ATOM_WM_NORMAL_HINTS, x::ATOM_WM_SIZE_HINTS, 17)?
+            .filter(|r| r.format() == 32)
+            .and_then(|r| match *r.value::<u32>() {
+                [flags, _, _, _, _, min_width, min_height, ..] if flags & (1 << 4) != 0 => Some((min_width, min_height)),
+                [flags, _, _, _, _, _, _, _, _, _, _, _, _, _, _, base_width, base_height] if flags & (1 << 3) != 0 => {
+                    Some((base_width, base_height))
                 },
+                _ => None,
+            });
 
-                xcb::Event::X(x::Event::EnterNotify(ev)) => {
-                    debug!("pointer entered {:?}, focusing", ev.event());
+        Ok(min_size)
+    }
 
-                    // focus follows mouse :)
-                    self.focus_window(ev.event());
-                    self.conn.flush()?;
+    // PMinSize (bit 4) and PMaxSize (bit 5) in WM_NORMAL_HINTS, both the same size
+    fn get_fixed_size(&self, window: x::Window) -> xcb::Result<bool> {
+        let fixed = self.get_property(window, x::ATOM_WM_NORMAL_HINTS, x::ATOM_WM_SIZE_HINTS, 9)?
+            .filter(|r| r.format() == 32)
+            .is_some_and(|r| match *r.value::<u32>() {
+                [flags, _, _, _, _, min_width, min_height, max_width, max_height] => {
+                    flags & (1 << 4 | 1 << 5) == 1 << 4 | 1 << 5 && max_width > 0 && max_height > 0 &&
+                        (min_width, min_height) == (max_width, max_height)
                 },
+                _ => false,
+            });
 
-                xcb::Event::X(x::Event::FocusIn(ev)) => {
-                    debug!("{:?} received focus", ev.event());
+        Ok(fixed)
+    }
 
-                    self.conn.send_request_checked(&x::ChangeWindowAttributes {
-                        window: ev.event(),
-                        value_list: &[
-                            x::Cw::BorderPixel(0x0055ff),
-                        ],
-                    });
+    // PWinGravity (bit 9) in WM_NORMAL_HINTS, and win_gravity at the end. without it it's
+    // northwest
+    fn get_gravity(&self, window: x::Window) -> xcb::Result<x::Gravity> {
+        let gravity = self.get_property(window, x::ATOM_WM_NORMAL_HINTS, x::ATOM_WM_SIZE_HINTS, 18)?
+            .filter(|r| r.format() == 32)
+            .and_then(|r| match *r.value::<u32>() {
+                [flags, _, _, _, _, _, _, _, _, _, _, _, _, _, _, _, _, gravity] if flags & (1 << 9) != 0 => Some(gravity),
+                _ => None,
+            });
 
-                    self.conn.flush()?;
-                },
+        Ok(gravity.map_or(x::Gravity::NorthWest, gravity_from))
+    }
 
-                xcb::Event::X(x::Event::FocusOut(ev)) => {
-                    debug!("{:?} lost focus", ev.event());
+    // inner size for a config size on a monitor
+    fn resolve_size(&self, (width, height): (Length, Length), monitor: &Monitor) -> (u32, u32) {
+        let border = 2*self.config.border_width;
+        (
+            width.resolve(monitor.width).saturating_sub(border).max(1),
+            height.resolve(monitor.height).saturating_sub(border).max(1),
+        )
+    }
 
-                    self.conn.send_request_checked(&x::ChangeWindowAttributes {
-                        window: ev.event(),
-                        value_list: &[
-                            x::Cw::BorderPixel(0x000000),
-                        ],
-                    });
+    fn get_client_machine(&self, window: x::Window) -> xcb::Result<Option<String>> {
+        let machine = self.get_property(window, x::ATOM_WM_CLIENT_MACHINE, x::ATOM_ANY, 64)?
+            .filter(|r| r.format() == 8 && !r.value::<u8>().is_empty())
+            .map(|r| String::from_utf8_lossy(r.value::<u8>()).into_owned());
+
+        Ok(machine)
+    }
+
+    fn supports_protocol(&self, window: x::Window, protocol: x::Atom) -> xcb::Result<bool> {
+        let supported = self.get_property(window, self.atoms.wm_protocols, x::ATOM_ATOM, 32)?
+            .filter(|r| r.format() == 32)
+            .is_some_and(|r| r.value::<x::Atom>().contains(&protocol));
+
+        Ok(supported)
+    }
+
+    fn supports_delete(&self, window: x::Window) -> xcb::Result<bool> {
+        self.supports_protocol(window, self.atoms.wm_delete_window)
+    }
 
-                    self.conn.flush()?;
+    // InputHint (bit 0) in WM_HINTS, and the input field after it. without it we give it focus
+    // anyway, like most window managers, since plenty of clients don't bother saying
+    fn wants_input(&self, window: x::Window) -> xcb::Result<bool> {
+        let input = self.get_property(window, x::ATOM_WM_HINTS, x::ATOM_WM_HINTS, 2)?
+            .filter(|r| r.format() == 32)
+            .is_none_or(|r| match r.value::<u32>() {
+                [flags, input, ..] => flags & 1 == 0 || *input != 0,
+                _ => true,
+            });
+
+        Ok(input)
+    }
+
+    // _NET_WM_NAME if it has one, otherwise WM_NAME
+    fn get_title(&self, window: x::Window) -> xcb::Result<Option<String>> {
+        for (property, r#type) in [(self.atoms.net_wm_name, self.atoms.utf8_string), (x::ATOM_WM_NAME, x::ATOM_ANY)] {
+            let cookie = self.conn.send_request(&x::GetProperty {
+                delete: false,
+                window,
+                property,
+                r#type,
+                long_offset: 0,
+                long_length: 256,
+            });
+
+            let reply = match self.wait_for_reply(cookie) {
+                Ok(r) => r,
+                Err(xcb::Error::Protocol(e)) => {
+                    debug!("couldn't get title for {:?}: {:?}", window, e);
+                    return Ok(None);
                 },
+                Err(e) => return Err(e),
+            };
+
+            if reply.format() == 8 && !reply.value::<u8>().is_empty() {
+                return Ok(Some(String::from_utf8_lossy(reply.value::<u8>()).into_owned()));
+            }
+        }
+
+        Ok(None)
+    }
 
-                // silence debug for ones we aren't interested in
-                xcb::Event::X(x::Event::ConfigureRequest(_)) => {},
+    // states the client set on _NET_WM_STATE itself, as raw atoms for change_net_wm_state
+    fn get_net_wm_state(&self, window: x::Window) -> xcb::Result<Vec<u32>> {
+        let state = self.get_property(window, self.atoms.net_wm_state, x::ATOM_ATOM, 32)?
+            .filter(|r| r.format() == 32)
+            .map(|r| r.value::<x::Atom>().iter().map(|a| a.resource_id()).collect())
+            .unwrap_or_default();
 
-                xcb::Event::X(x::Event::ConfigureNotify(_)) => {},
-                xcb::Event::X(x::Event::MapNotify(_)) => {},
-                xcb::Event::X(x::Event::UnmapNotify(_)) => {},
-                xcb::Event::X(x::Event::MappingNotify(_)) => {},
+        Ok(state)
+    }
+
+    // first type in _NET_WM_WINDOW_TYPE that we know about, it's in order of preference
+    fn get_window_type(&self, window: x::Window) -> xcb::Result<WindowType> {
+        let cookie = self.conn.send_request(&x::GetProperty {
+            delete: false,
+            window,
+            property: self.atoms.net_wm_window_type,
+            r#type: x::ATOM_ATOM,
+            long_offset: 0,
+            long_length: 32,
+        });
 
-                xcb::Event::X(x::Event::ClientMessage(_)) => {},
+        let reply = match self.wait_for_reply(cookie) {
+            Ok(r) => r,
+            Err(xcb::Error::Protocol(e)) => {
+                debug!("couldn't get window type for {:?}: {:?}", window, e);
+                return Ok(WindowType::Normal);
+            },
+            Err(e) => return Err(e),
+        };
 
-                e => {
-                    debug!("UNHANDLED: {:?}", e);
-                }
+        if reply.format() != 32 {
+            return Ok(WindowType::Normal);
+        }
+
+        let window_type = reply.value::<x::Atom>().iter().find_map(|&atom| {
+            if atom == self.atoms.net_wm_window_type_splash {
+                Some(WindowType::Splash)
+            }
+            else if atom == self.atoms.net_wm_window_type_dialog {
+                Some(WindowType::Dialog)
+            }
+            else if atom == self.atoms.net_wm_window_type_utility {
+                Some(WindowType::Utility)
             }
+            else if atom == self.atoms.net_wm_window_type_toolbar {
+                Some(WindowType::Toolbar)
+            }
+            else if atom == self.atoms.net_wm_window_type_menu {
+                Some(WindowType::Menu)
+            }
+            else {
+                None
+            }
+        });
+
+        Ok(window_type.unwrap_or(WindowType::Normal))
+    }
+
+    fn get_transient_for(&self, window: x::Window) -> xcb::Result<Option<x::Window>> {
+        let cookie = self.conn.send_request(&x::GetProperty {
+            delete: false,
+            window,
+            property: x::ATOM_WM_TRANSIENT_FOR,
+            r#type: x::ATOM_WINDOW,
+            long_offset: 0,
+            long_length: 1,
+        });
+
+        let reply = match self.wait_for_reply(cookie) {
+            Ok(r) => r,
+            Err(xcb::Error::Protocol(e)) => {
+                debug!("couldn't get transient for {:?}: {:?}", window, e);
+                return Ok(None);
+            },
+            Err(e) => return Err(e),
+        };
+
+        if reply.format() != 32 {
+            return Ok(None);
         }
+
+        Ok(reply.value::<x::Window>().first().copied().filter(|w| !w.is_none()))
     }
 
-    fn map_window(&mut self, window: x::Window) {
-        // XXX some policy or whatever
-        let x = 0;
-        let y = 0;
-        let w = 640;
-        let h = 480;
+    fn get_geometry(&self, window: x::Window) -> xcb::Result<Option<Geometry>> {
+        let cookie = self.conn.send_request(&x::GetGeometry {
+            drawable: x::Drawable::Window(window),
+        });
+
+        match self.wait_for_reply(cookie) {
+            Ok(g) => Ok(Some(Geometry {
+                x: g.x() as i32,
+                y: g.y() as i32,
+                width: g.width() as u32,
+                height: g.height() as u32,
+            })),
+            Err(xcb::Error::Protocol(e)) => {
+                debug!("couldn't get geometry for {:?}: {:?}", window, e);
+                Ok(None)
+            },
+            Err(e) => Err(e),
+        }
+    }
 
-        debug!("mapping {:?} to {},{} {}x{}", window, x, y, w, h);
+    // note where the window is now, so the next one of its class can open there. only normal
+    // windows, like the ones it's used for, and only where they'd be by themselves: not
+    // maximized, fullscreen, zoomed or in a tile
+    fn remember_geometry(&mut self, window: x::Window) -> xcb::Result<()> {
+        let class = match self.windows.get(&window) {
+            Some(w) if w.window_type == WindowType::Normal && !w.maximized_horz && !w.maximized_vert &&
+                !w.fullscreen && !w.zoomed && (w.floating || !self.config.tiling) => w.class.clone(),
+            _ => None,
+        };
+        let class = match class {
+            Some(c) => c,
+            None => return Ok(()),
+        };
 
-        // position and size
-        // XXX maybe we should take defaults or preferences from ConfigureRequest
-        self.conn.send_request_checked(&x::ConfigureWindow {
-            window: window,
-            value_list: &[
-                x::ConfigWindow::X(x),
-                x::ConfigWindow::Y(y),
-                x::ConfigWindow::Width(w),
-                x::ConfigWindow::Height(h),
-                x::ConfigWindow::BorderWidth(BORDER_WIDTH as u32),
-            ],
+        let cookie = self.conn.send_request(&x::GetGeometry {
+            drawable: x::Drawable::Window(window),
         });
+        let geometry = match self.wait_for_reply(cookie) {
+            Ok(g) => g,
+            Err(xcb::Error::Protocol(e)) => {
+                debug!("couldn't get geometry for {:?}: {:?}", window, e);
+                return Ok(());
+            },
+            Err(e) => return Err(e),
+        };
+
+        let geometry = Geometry {
+            x: geometry.x() as i32,
+            y: geometry.y() as i32,
+            width: geometry.width() as u32,
+            height: geometry.height() as u32,
+        };
+
+        debug!("remembering {:?} for class {}", geometry, class);
+
+        self.remembered.insert(class, geometry);
+        self.remembered_dirty = true;
+
+        Ok(())
+    }
+
+    // what to pick up again next time, see session.rs
+    fn layout(&self) -> session::Layout {
+        let windows = self.layout_order.iter()
+            .filter_map(|w| self.windows.get(w))
+            .filter(|w| w.mapped)
+            .map(|w| session::SavedWindow {
+                id: w.x_window.resource_id(),
+                workspace: w.workspace,
+                floating: w.floating,
+                iconic: w.iconic,
+                tags: Some(w.tags),
+            })
+            .collect();
+
+        session::Layout {
+            workspaces: self.current_workspaces.clone(),
+            tags: Some(self.selected_tags),
+            master_widths: self.master_widths.clone(),
+            tabbed: self.tabbed.iter().copied().collect(),
+            windows,
+        }
+    }
+
+    fn save_remembered(&mut self) {
+        if self.remembered_dirty {
+            session::save_geometries(&self.remembered);
+            self.remembered_dirty = false;
+        }
+    }
+
+    fn border_colour(&self, window: x::Window) -> u32 {
+        if self.focused == Some(window) {
+            self.config.border_focused
+        }
+        else if self.windows.get(&window).is_some_and(|w| w.demands_attention) {
+            self.config.border_attention
+        }
+        else {
+            self.config.border_unfocused
+        }
+    }
 
-        // request enter and focus events
+    // solid colour first, then the pattern over it if there is one. windows that aren't the
+    // root's depth, like ARGB ones, can't take our pixmap, and the second request failing leaves
+    // them with the colour
+    fn apply_border(&self, window: x::Window) {
         self.conn.send_request_checked(&x::ChangeWindowAttributes {
-            window: window,
+            window,
             value_list: &[
-                x::Cw::EventMask(
-                    x::EventMask::ENTER_WINDOW |
-                    x::EventMask::FOCUS_CHANGE
-                ),
+                x::Cw::BorderPixel(self.border_colour(window)),
             ],
         });
 
-        // be visible!
-        self.conn.send_request_checked(&x::MapWindow {
-            window: window,
-        });
+        let attention = self.windows.get(&window).is_some_and(|w| w.demands_attention);
+        let pixmap = if self.focused == Some(window) {
+            self.focused_pixmap
+        }
+        else if attention {
+            None
+        }
+        else {
+            self.unfocused_pixmap
+        };
+        if let Some(pixmap) = pixmap {
+            self.conn.send_request_checked(&x::ChangeWindowAttributes {
+                window,
+                value_list: &[
+                    x::Cw::BorderPixmap(pixmap),
+                ],
+            });
+        }
     }
 
-    fn bring_window_to_front(&mut self, window: x::Window) {
-        self.conn.send_request_checked(&x::ConfigureWindow {
-            window: window,
+    fn make_border_pixmaps(&mut self) {
+        for pixmap in [self.focused_pixmap.take(), self.unfocused_pixmap.take()].into_iter().flatten() {
+            self.conn.send_request(&x::FreePixmap {
+                pixmap,
+            });
+        }
+
+        self.focused_pixmap = self.config.border_focused_stripes
+            .map(|stripes| self.stripes_pixmap(self.config.border_focused, stripes));
+        self.unfocused_pixmap = self.config.border_unfocused_stripes
+            .map(|stripes| self.stripes_pixmap(self.config.border_unfocused, stripes));
+    }
+
+    // diagonal stripes, four pixels wide. the server tiles it around the border from the
+    // window's corner, and eight square lines up with itself
+    fn stripes_pixmap(&self, colour: u32, stripes: u32) -> x::Pixmap {
+        const SIZE: i16 = 8;
+
+        let pixmap: x::Pixmap = self.conn.generate_id();
+        self.conn.send_request(&x::CreatePixmap {
+            depth: self.screen.root_depth(),
+            pid: pixmap,
+            drawable: x::Drawable::Window(self.screen.root()),
+            width: SIZE as u16,
+            height: SIZE as u16,
+        });
+
+        let gc: x::Gcontext = self.conn.generate_id();
+        self.conn.send_request(&x::CreateGc {
+            cid: gc,
+            drawable: x::Drawable::Pixmap(pixmap),
             value_list: &[
-                x::ConfigWindow::StackMode(x::StackMode::Above),
+                x::Gc::Foreground(colour),
+            ],
+        });
+        self.conn.send_request(&x::PolyFillRectangle {
+            drawable: x::Drawable::Pixmap(pixmap),
+            gc,
+            rectangles: &[x::Rectangle { x: 0, y: 0, width: SIZE as u16, height: SIZE as u16 }],
+        });
+
+        let points: Vec<_> = (0..SIZE).flat_map(|y| (0..SIZE).map(move |x| x::Point { x, y }))
+            .filter(|p| (p.x + p.y)/4 % 2 == 1)
+            .collect();
+        self.conn.send_request(&x::ChangeGc {
+            gc,
+            value_list: &[
+                x::Gc::Foreground(stripes),
+            ],
+        });
+        self.conn.send_request(&x::PolyPoint {
+            coordinate_mode: x::CoordMode::Origin,
+            drawable: x::Drawable::Pixmap(pixmap),
+            gc,
+            points: &points,
+        });
+
+        self.conn.send_request(&x::FreeGc {
+            gc,
+        });
+
+        pixmap
+    }
+
+    // the format theme cursors are uploaded in: 32 bits, alpha on top, then red, green and blue
+    fn find_cursor_format(&self) -> xcb::Result<Option<render::Pictformat>> {
+        if !self.conn.active_extensions().any(|e| e == xcb::Extension::Render) {
+            return Ok(None);
+        }
+
+        self.wait_for_reply(self.conn.send_request(&render::QueryVersion {
+            client_major_version: 0,
+            client_minor_version: 11,
+        }))?;
+        let formats = self.wait_for_reply(self.conn.send_request(&render::QueryPictFormats {}))?;
+
+        Ok(formats.formats().iter()
+            .find(|f| {
+                let direct = f.direct();
+                f.r#type() == render::PictType::Direct && f.depth() == 32 &&
+                    (direct.alpha_shift, direct.red_shift, direct.green_shift, direct.blue_shift) == (24, 16, 8, 0) &&
+                    [direct.alpha_mask, direct.red_mask, direct.green_mask, direct.blue_mask] == [0xff; 4]
+            })
+            .map(|f| f.id()))
+    }
+
+    // the pointer shapes, from the cursor theme where it has them and the cursor font where it
+    // doesn't or there's no theme, replacing any made before. the root gets its one here, and
+    // drags take theirs when they grab the pointer
+    fn make_cursors(&mut self) -> xcb::Result<()> {
+        for (_, cursor) in self.cursors.drain() {
+            self.conn.send_request(&x::FreeCursor {
+                cursor,
+            });
+        }
+
+        let font: x::Font = self.conn.generate_id();
+        self.conn.send_request(&x::OpenFont {
+            fid: font,
+            name: b"cursor",
+        });
+
+        let theme = self.config.cursor_theme.as_deref().zip(self.cursor_format);
+        for (name, glyph) in CURSORS {
+            let image = theme.and_then(|(theme, format)| Some((xcursor::load(theme, name, self.config.cursor_size)?, format)));
+            let cursor = match image {
+                Some((image, format)) => self.image_cursor(&image, format),
+                None => self.glyph_cursor(font, glyph),
+            };
+            self.cursors.insert(name, cursor);
+        }
+
+        self.conn.send_request(&x::CloseFont {
+            font,
+        });
+
+        self.conn.send_request_checked(&x::ChangeWindowAttributes {
+            window: self.screen.root(),
+            value_list: &[
+                x::Cw::Cursor(self.cursor("left_ptr")),
+            ],
+        });
+        self.flush()
+    }
+
+    // black on white, like every other program using the cursor font
+    fn glyph_cursor(&self, font: x::Font, glyph: u16) -> x::Cursor {
+        let cursor: x::Cursor = self.conn.generate_id();
+        self.conn.send_request(&x::CreateGlyphCursor {
+            cid: cursor,
+            source_font: font,
+            mask_font: font,
+            source_char: glyph,
+            mask_char: glyph + 1,
+            fore_red: 0,
+            fore_green: 0,
+            fore_blue: 0,
+            back_red: 0xffff,
+            back_green: 0xffff,
+            back_blue: 0xffff,
+        });
+        cursor
+    }
+
+    // a theme image, through a pixmap and a picture of it that can go once the cursor's made
+    fn image_cursor(&self, image: &xcursor::Image, format: render::Pictformat) -> x::Cursor {
+        let pixmap: x::Pixmap = self.conn.generate_id();
+        self.conn.send_request(&x::CreatePixmap {
+            depth: 32,
+            pid: pixmap,
+            drawable: x::Drawable::Window(self.screen.root()),
+            width: image.width as u16,
+            height: image.height as u16,
+        });
+
+        let gc: x::Gcontext = self.conn.generate_id();
+        self.conn.send_request(&x::CreateGc {
+            cid: gc,
+            drawable: x::Drawable::Pixmap(pixmap),
+            value_list: &[],
+        });
+
+        let data: Vec<u8> = match self.conn.get_setup().image_byte_order() {
+            x::ImageOrder::LsbFirst => image.pixels.iter().flat_map(|p| p.to_le_bytes()).collect(),
+            x::ImageOrder::MsbFirst => image.pixels.iter().flat_map(|p| p.to_be_bytes()).collect(),
+        };
+        self.conn.send_request(&x::PutImage {
+            format: x::ImageFormat::ZPixmap,
+            drawable: x::Drawable::Pixmap(pixmap),
+            gc,
+            width: image.width as u16,
+            height: image.height as u16,
+            dst_x: 0,
+            dst_y: 0,
+            left_pad: 0,
+            depth: 32,
+            data: &data,
+        });
+
+        let picture: render::Picture = self.conn.generate_id();
+        self.conn.send_request(&render::CreatePicture {
+            pid: picture,
+            drawable: x::Drawable::Pixmap(pixmap),
+            format,
+            value_list: &[],
+        });
+
+        let cursor: x::Cursor = self.conn.generate_id();
+        self.conn.send_request(&render::CreateCursor {
+            cid: cursor,
+            source: picture,
+            x: image.xhot as u16,
+            y: image.yhot as u16,
+        });
+
+        self.conn.send_request(&render::FreePicture {
+            picture,
+        });
+        self.conn.send_request(&x::FreeGc {
+            gc,
+        });
+        self.conn.send_request(&x::FreePixmap {
+            pixmap,
+        });
+
+        cursor
+    }
+
+    fn cursor(&self, name: &str) -> x::Cursor {
+        self.cursors.get(name).copied().unwrap_or(x::CURSOR_NONE)
+    }
+
+    // the whole root in the background colour. the pixel is the colour as it is, like borders,
+    // so it assumes a truecolour visual. clearing repaints what's showing now, and the server
+    // does the rest as parts of the root are uncovered. with no colour the root gets the
+    // server's default back, since whatever was there before we painted over it is gone
+    fn paint_background(&self) {
+        let background = match self.config.background {
+            Some(colour) => x::Cw::BackPixel(colour),
+            None => x::Cw::BackPixmap(x::PIXMAP_NONE),
+        };
+
+        self.conn.send_request_checked(&x::ChangeWindowAttributes {
+            window: self.screen.root(),
+            value_list: &[
+                background,
+            ],
+        });
+        self.conn.send_request_checked(&x::ClearArea {
+            exposures: false,
+            window: self.screen.root(),
+            x: 0,
+            y: 0,
+            width: 0,
+            height: 0,
+        });
+    }
+
+    fn border_width(&self) -> i32 {
+        self.config.border_width as i32
+    }
+
+    // the border it has when it isn't fullscreen or zoomed
+    fn window_border(&self, window: &Window) -> u32 {
+        if window.lone {
+            0
+        }
+        else {
+            self.config.border_width
+        }
+    }
+
+    // with smart_borders, a window that's the only one showing on its monitor loses its border
+    // and gets it back when another one shows up. floating windows keep still by their gravity
+    // and maximized ones take up the difference, and tiled ones are laid out again after this
+    fn update_lone_borders(&mut self) {
+        let mut showing = vec![0; self.monitors.len()];
+        for w in self.windows.values().filter(|w| self.is_visible(w) && w.window_type != WindowType::Splash) {
+            if let Some(n) = showing.get_mut(w.monitor) {
+                *n += 1;
+            }
+        }
+
+        let changed: Vec<_> = self.windows.values()
+            .filter(|w| {
+                let lone = self.config.smart_borders && self.is_visible(w) &&
+                    w.window_type != WindowType::Splash && showing.get(w.monitor) == Some(&1);
+                w.lone != lone
+            })
+            .map(|w| w.x_window)
+            .collect();
+
+        for window in changed {
+            let border_width = self.config.border_width;
+            let w = self.windows.get_mut(&window).unwrap();
+            w.lone = !w.lone;
+
+            // they have no border either way, and get the right one back when they come out
+            if w.fullscreen || w.zoomed {
+                continue;
+            }
+
+            let (old, new) = if w.lone { (border_width, 0) } else { (0, border_width) };
+            debug!("{:?} border {} -> {}", window, old, new);
+
+            let geometry = w.geometry.filter(|_| w.floating || w.maximized_horz || w.maximized_vert).map(|g| {
+                let grow = |size: u32, maximized: bool| {
+                    if maximized {
+                        (size + 2*old).saturating_sub(2*new).max(1)
+                    }
+                    else {
+                        size
+                    }
+                };
+                let (width, height) = (grow(g.width, w.maximized_horz), grow(g.height, w.maximized_vert));
+                if w.maximized_horz || w.maximized_vert {
+                    Geometry { width, height, ..g }
+                }
+                else {
+                    g.resized(old, width, height, new, w.gravity)
+                }
+            });
+
+            match geometry {
+                Some(g) => {
+                    self.conn.send_request_checked(&x::ConfigureWindow {
+                        window,
+                        value_list: &[
+                            x::ConfigWindow::X(g.x),
+                            x::ConfigWindow::Y(g.y),
+                            x::ConfigWindow::Width(g.width),
+                            x::ConfigWindow::Height(g.height),
+                            x::ConfigWindow::BorderWidth(new),
+                        ],
+                    });
+                    self.send_configure_notify(window, g, new);
+                },
+                None => {
+                    self.conn.send_request_checked(&x::ConfigureWindow {
+                        window,
+                        value_list: &[
+                            x::ConfigWindow::BorderWidth(new),
+                        ],
+                    });
+                },
+            }
+            self.set_frame_extents(window, new);
+        }
+    }
+
+    fn reload_config(&mut self) -> xcb::Result<()> {
+        debug!("reloading config");
+
+        let old_config = std::mem::replace(&mut self.config, Config::load());
+        self.scale = 1;
+        self.scale_config();
+
+        let changes = old_config.changes(&self.config);
+        if changes.is_empty() {
+            info!("config reloaded, nothing changed");
+        }
+        for change in changes {
+            info!("config changed: {}", change);
+        }
+
+        self.grab_keys()?;
+        self.grab_buttons();
+        self.make_border_pixmaps();
+
+        if root_event_mask(&self.config, self.randr) != root_event_mask(&old_config, self.randr) {
+            select_root_events(&self.conn, self.screen.root(), &self.config, self.randr);
+        }
+
+        if self.config.status_output != old_config.status_output {
+            self.status = self.config.status_output.as_deref().map(Status::new);
+        }
+
+        if self.config.background != old_config.background {
+            self.paint_background();
+        }
+
+        // sizes are scaled by now, so this catches a new scale too
+        if (&self.config.cursor_theme, self.config.cursor_size) != (&old_config.cursor_theme, old_config.cursor_size) {
+            self.make_cursors()?;
+        }
+
+        // workspaces may have gone away
+        let workspace_count = self.config.workspace_count;
+        self.current_workspaces.iter_mut()
+            .filter(|c| **c >= workspace_count)
+            .for_each(|c| *c = 0);
+
+        // shared workspaces means every monitor shows the same one
+        if !self.config.per_monitor_workspaces {
+            let current = self.current_workspaces[0];
+            self.current_workspaces.iter_mut().for_each(|c| *c = current);
+        }
+        let current_workspaces = self.current_workspaces.clone();
+        self.windows.values_mut()
+            .filter(|w| w.workspace >= workspace_count)
+            .for_each(|w| w.workspace = current_workspaces[w.monitor]);
+
+        // rule numbers may have moved; take what matches now as already applied
+        let matched: Vec<_> = self.windows.values()
+            .map(|w| (w.x_window, self.matching_rules(w.class.as_deref(), w.title.as_deref())))
+            .collect();
+        for (window, matched_rules) in matched {
+            if let Some(w) = self.windows.get_mut(&window) {
+                w.matched_rules = matched_rules;
+            }
+        }
+
+        // new border appearance
+        self.restyle_windows(old_config.border_width)?;
+
+        // the click grab may need to go either way now
+        if self.fullscreen_active {
+            self.set_click_grab(!self.config.fullscreen_release_click);
+        }
+
+        self.show_workspaces();
+        self.flush()?;
+
+        Ok(())
+    }
+
+    // default sizes are pixels at 96dpi; make them real ones for the scale we're at now. ones
+    // set in the config are real pixels already. the old border width if anything changed
+    fn scale_config(&mut self) -> Option<u32> {
+        let (from, to) = (self.scale, self.config.scale.unwrap_or(self.auto_scale));
+        if from == to {
+            return None;
+        }
+
+        info!("scaling sizes by {}", to);
+
+        let old_border_width = self.config.border_width;
+        let set = self.config.set_sizes;
+        if !set.border_width {
+            self.config.border_width = self.config.border_width / from * to;
+        }
+        if !set.snap_grid {
+            self.config.snap_grid = self.config.snap_grid.map(|g| g / from * to);
+        }
+        if !set.edge_resistance {
+            self.config.edge_resistance = self.config.edge_resistance / from as i32 * to as i32;
+        }
+        if !set.cursor_size {
+            self.config.cursor_size = self.config.cursor_size / from * to;
+        }
+        self.scale = to;
+
+        Some(old_border_width)
+    }
+
+    // borders as the config has them now, on every window that has one
+    fn restyle_windows(&mut self, old_border_width: u32) -> xcb::Result<()> {
+        let managed: Vec<_> = self.windows.values()
+            .filter(|w| w.mapped && !w.fullscreen && !w.zoomed && w.window_type != WindowType::Splash)
+            .map(|w| (w.x_window, w.maximized_horz, w.maximized_vert, self.window_border(w)))
+            .collect();
+
+        for (window, horz, vert, border_width) in managed {
+            // floating windows stay put by their gravity; tiled ones are laid out again anyway
+            let moved = self.windows.get(&window)
+                .filter(|w| w.floating && !w.lone && self.config.border_width != old_border_width)
+                .and_then(|w| w.geometry.map(|g| (g, w.gravity)))
+                .map(|(g, gravity)| g.resized(old_border_width, g.width, g.height, self.config.border_width, gravity));
+
+            match moved {
+                Some(g) => self.conn.send_request_checked(&x::ConfigureWindow {
+                    window,
+                    value_list: &[
+                        x::ConfigWindow::X(g.x),
+                        x::ConfigWindow::Y(g.y),
+                        x::ConfigWindow::BorderWidth(border_width),
+                    ],
+                }),
+                None => self.conn.send_request_checked(&x::ConfigureWindow {
+                    window,
+                    value_list: &[
+                        x::ConfigWindow::BorderWidth(border_width),
+                    ],
+                }),
+            };
+            self.set_frame_extents(window, border_width);
+            self.apply_border(window);
+            self.show_opacity(window);
+
+            // maximized size depends on the border
+            if horz || vert {
+                self.set_maximized(window, horz, vert)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // the setup info is a snapshot from connect time, so don't read dimensions from it
+    pub fn screen_size(&self) -> (i32, i32) {
+        (self.screen_width, self.screen_height)
+    }
+
+    // the monitor less the reserved edges, for anything that places windows on it
+    fn work_area(&self, monitor: usize) -> Monitor {
+        self.reserve(self.monitors[monitor])
+    }
+
+    // the whole screen less the reserved edges, for keeping moves on screen
+    fn screen_work_area(&self) -> Monitor {
+        self.reserve(Monitor { x: 0, y: 0, width: self.screen_width, height: self.screen_height })
+    }
+
+    fn reserve(&self, area: Monitor) -> Monitor {
+        area.inset(self.config.reserved_top as i32, self.config.reserved_bottom as i32,
+            self.config.reserved_left as i32, self.config.reserved_right as i32)
+    }
+
+    fn refresh_screen_size(&mut self) -> xcb::Result<()> {
+        let geometry = self.wait_for_reply(self.conn.send_request(&x::GetGeometry {
+            drawable: x::Drawable::Window(self.screen.root()),
+        }))?;
+
+        self.screen_width = geometry.width() as i32;
+        self.screen_height = geometry.height() as i32;
+
+        debug!("screen size now {}x{}", self.screen_width, self.screen_height);
+
+        Ok(())
+    }
+
+    fn refresh_monitors(&mut self) -> xcb::Result<()> {
+        self.monitors.clear();
+
+        // the screen's own idea of its size, unless randr knows better. the millimetres are from
+        // connect time, but they don't change with the resolution
+        let (screen_width, _) = self.screen_size();
+        let mut auto_scale = monitor::scale_for(screen_width as u32, self.screen.width_in_millimeters() as u32);
+
+        if self.randr {
+            let cookie = self.conn.send_request(&randr::GetMonitors {
+                window: self.screen.root(),
+                get_active: true,
+            });
+            match self.wait_for_reply(cookie) {
+                Ok(reply) => {
+                    self.monitors.extend(reply.monitors().map(|m| Monitor {
+                        x: m.x() as i32,
+                        y: m.y() as i32,
+                        width: m.width() as i32,
+                        height: m.height() as i32,
+                    }));
+
+                    // X borders belong to the window wherever it goes, so there's one scale for
+                    // everything, from the primary monitor or else the first
+                    let primary = reply.monitors().find(|m| m.primary()).or_else(|| reply.monitors().next());
+                    if let Some(m) = primary {
+                        auto_scale = monitor::scale_for(m.width() as u32, m.width_in_millimeters());
+                    }
+                },
+                Err(xcb::Error::Protocol(e)) => {
+                    debug!("couldn't get monitors: {:?}", e);
+                },
+                Err(e) => return Err(e),
+            }
+        }
+
+        // no randr or nothing useful from it, so the whole screen is one monitor
+        if self.monitors.is_empty() {
+            let (width, height) = self.screen_size();
+            self.monitors.push(Monitor {
+                x: 0,
+                y: 0,
+                width,
+                height,
+            });
+        }
+
+        debug!("monitors: {:?}", self.monitors);
+
+        self.auto_scale = auto_scale;
+        if let Some(old_border_width) = self.scale_config() {
+            self.restyle_windows(old_border_width)?;
+
+            // none yet when we're starting up
+            if !self.cursors.is_empty() {
+                self.make_cursors()?;
+            }
+        }
+
+        // new monitors show the first workspace, or whatever everyone else shows if shared
+        let default_workspace = if self.config.per_monitor_workspaces {
+            0
+        }
+        else {
+            self.current_workspaces.first().copied().unwrap_or(0)
+        };
+        self.current_workspaces.resize(self.monitors.len(), default_workspace);
+
+        // anything left on a monitor that went away gets adopted by the first one
+        let monitor_count = self.monitors.len();
+        let current = self.current_workspaces[0];
+        self.windows.values_mut()
+            .filter(|w| w.monitor >= monitor_count)
+            .for_each(|w| {
+                w.monitor = 0;
+                w.workspace = current;
+            });
+
+        self.show_workspaces();
+
+        Ok(())
+    }
+
+    // monitor of the focused window, or the one under the pointer if nothing is focused
+    fn active_monitor(&self) -> xcb::Result<usize> {
+        if let Some(window) = self.focused.and_then(|f| self.windows.get(&f)) {
+            return Ok(window.monitor);
+        }
+
+        let pointer = self.wait_for_reply(self.conn.send_request(&x::QueryPointer {
+            window: self.screen.root(),
+        }))?;
+
+        Ok(self.monitor_at(pointer.root_x() as i32, pointer.root_y() as i32))
+    }
+
+    fn is_visible(&self, window: &Window) -> bool {
+        if window.iconic {
+            return false;
+        }
+        if self.scratchpad == Some(window.x_window) {
+            return window.mapped && self.scratchpad_shown;
+        }
+        window.mapped && (window.sticky || window.workspace == self.current_workspaces[window.monitor]) &&
+            window.tags & self.selected_tags != 0
+    }
+
+    // map and unmap managed windows to match the workspaces currently showing
+    fn show_workspaces(&mut self) {
+        let visibility: Vec<_> = self.windows.values()
+            .filter(|w| w.mapped)
+            .map(|w| (w.x_window, self.is_visible(w)))
+            .collect();
+
+        for (window, visible) in visibility {
+            let w = self.windows.get_mut(&window).unwrap();
+
+            if visible && w.hidden {
+                debug!("showing {:?}", window);
+                w.hidden = false;
+                self.conn.send_request_checked(&x::MapWindow {
+                    window,
+                });
+            }
+            else if !visible && !w.hidden {
+                debug!("hiding {:?}", window);
+                w.hidden = true;
+                w.ignore_unmaps += 1;
+                self.conn.send_request_checked(&x::UnmapWindow {
+                    window,
+                });
+            }
+        }
+
+        // the focused window may have just been hidden
+        self.update_fullscreen_active();
+
+        self.publish_workspaces();
+        self.arrange();
+    }
+
+    // _NET_WM_DESKTOP for pagers and scripts, on windows whose workspace has changed since
+    fn publish_workspaces(&mut self) {
+        let changed: Vec<_> = self.windows.values()
+            .filter(|w| w.mapped && w.published_workspace != Some(w.workspace))
+            .map(|w| (w.x_window, w.workspace))
+            .collect();
+
+        for (window, workspace) in changed {
+            self.conn.send_request_checked(&x::ChangeProperty {
+                mode: x::PropMode::Replace,
+                window,
+                property: self.atoms.net_wm_desktop,
+                r#type: x::ATOM_CARDINAL,
+                data: &[workspace as u32],
+            });
+            if let Some(w) = self.windows.get_mut(&window) {
+                w.published_workspace = Some(workspace);
+            }
+        }
+    }
+
+    // everything onto the workspaces showing where it is, minimized or not, for when windows
+    // have been sent somewhere and lost. the scratchpad stays as it is
+    fn bring_all(&mut self) -> xcb::Result<()> {
+        let windows: Vec<_> = self.windows.values()
+            .filter(|w| w.mapped && self.scratchpad != Some(w.x_window))
+            .map(|w| w.x_window)
+            .collect();
+
+        debug!("bringing {} windows to the current workspaces", windows.len());
+
+        for window in windows {
+            let w = self.windows.get_mut(&window).unwrap();
+            w.workspace = self.current_workspaces[w.monitor];
+            w.tags |= self.selected_tags;
+
+            if w.iconic {
+                w.iconic = false;
+                self.minimized.retain(|&m| m != window);
+                self.set_wm_state(window, NORMAL_STATE);
+            }
+        }
+
+        self.show_workspaces();
+        self.flush()?;
+
+        Ok(())
+    }
+
+    fn switch_workspace(&mut self, workspace: usize) -> xcb::Result<()> {
+        if workspace >= self.config.workspace_count {
+            return Ok(());
+        }
+
+        let monitor = self.active_monitor()?;
+        let from = self.current_workspaces[monitor];
+
+        // all of them switch together without per-monitor workspaces
+        let switching = self.config.per_monitor_workspaces.then_some(monitor);
+        if from != workspace {
+            self.announce_switch(true, switching, from, workspace);
+        }
+
+        if self.config.per_monitor_workspaces {
+            debug!("switching monitor {} to workspace {}", monitor, workspace);
+            self.current_workspaces[monitor] = workspace;
+        }
+        else {
+            debug!("switching to workspace {}", workspace);
+            self.current_workspaces.iter_mut().for_each(|c| *c = workspace);
+        }
+
+        self.show_workspaces();
+        self.focus_top_window()?;
+        if from != workspace {
+            self.announce_switch(false, switching, from, workspace);
+        }
+        self.flush()?;
+
+        Ok(())
+    }
+
+    // _PANKO_WORKSPACE_SWITCH on the root window, for something outside like a compositor to
+    // animate workspace switches with. it's set twice around each switch, as four CARDINALs:
+    //
+    //   <phase> <monitor> <from> <to>
+    //
+    // phase is 1 just before any window is unmapped or mapped for the switch and 0 once they all
+    // have been. monitor is the one switching, numbered from 1, or 0 when they all switch
+    // together; from and to are workspaces, numbered from 1. the server sends the PropertyNotify
+    // for the first before any of the switch's Unmap- and MapNotifys, and the second after them.
+    // there's no waiting on whoever's listening, so this is notice, not a chance to hold it up
+    fn announce_switch(&self, starting: bool, monitor: Option<usize>, from: usize, to: usize) {
+        let monitor = monitor.map_or(0, |m| m as u32 + 1);
+
+        self.conn.send_request_checked(&x::ChangeProperty {
+            mode: x::PropMode::Replace,
+            window: self.screen.root(),
+            property: self.atoms.panko_workspace_switch,
+            r#type: x::ATOM_CARDINAL,
+            data: &[starting as u32, monitor, from as u32 + 1, to as u32 + 1],
+        });
+    }
+
+    // dwm-style tags, which narrow down what each workspace shows. turning off the last tag
+    // being viewed does nothing, rather than showing nothing at all
+    fn view_tags(&mut self, tags: u32) -> xcb::Result<()> {
+        if tags == 0 || tags == self.selected_tags {
+            return Ok(());
+        }
+
+        debug!("viewing tags {:#x}", tags);
+        self.selected_tags = tags;
+
+        self.show_workspaces();
+        self.focus_top_window()?;
+        self.flush()?;
+
+        Ok(())
+    }
+
+    // the same goes for windows, which always keep at least one tag
+    fn toggle_tag(&mut self, tag: usize) -> xcb::Result<()> {
+        let window = match self.focused.and_then(|f| self.windows.get_mut(&f)) {
+            Some(w) if w.tags ^ 1 << tag != 0 => w,
+            _ => return Ok(()),
+        };
+
+        window.tags ^= 1 << tag;
+        debug!("{:?} now has tags {:#x}", window.x_window, window.tags);
+
+        self.show_workspaces();
+        self.focus_top_window()?;
+        self.flush()?;
+
+        Ok(())
+    }
+
+    // the workspace after or before the one showing where the user is, wrapping around
+    fn step_workspace(&mut self, step: i32) -> xcb::Result<()> {
+        let current = self.current_workspaces[self.active_monitor()?];
+        let count = self.config.workspace_count as i32;
+        self.switch_workspace((current as i32 + step).rem_euclid(count) as usize)
+    }
+
+    fn send_to_workspace(&mut self, workspace: usize) -> xcb::Result<()> {
+        if workspace >= self.config.workspace_count {
+            return Ok(());
+        }
+
+        if let Some(window) = self.focused.and_then(|f| self.windows.get_mut(&f)) {
+            debug!("sending {:?} to workspace {}", window.x_window, workspace);
+            window.workspace = workspace;
+        }
+
+        self.show_workspaces();
+        self.focus_top_window()?;
+        self.flush()?;
+
+        Ok(())
+    }
+
+    // scratchpad hidden: show it. shown but not focused: focus it. shown and focused: hide it
+    fn toggle_scratchpad(&mut self) -> xcb::Result<()> {
+        let window = match self.scratchpad {
+            Some(w) => w,
+            None => {
+                match self.config.scratchpad_command.clone() {
+                    Some(command) => spawn(&command),
+                    None => debug!("no scratchpad window and no command to start one"),
+                }
+                return Ok(());
+            },
+        };
+
+        if self.scratchpad_shown && self.focused == Some(window) {
+            debug!("hiding scratchpad {:?}", window);
+            self.scratchpad_geometry = self.get_geometry(window)?;
+            self.scratchpad_shown = false;
+            self.show_workspaces();
+            self.focus_top_window()?;
+        }
+        else {
+            debug!("showing scratchpad {:?}", window);
+
+            if !self.scratchpad_shown {
+                if let Some(g) = self.scratchpad_geometry {
+                    self.conn.send_request_checked(&x::ConfigureWindow {
+                        window,
+                        value_list: &[
+                            x::ConfigWindow::X(g.x),
+                            x::ConfigWindow::Y(g.y),
+                            x::ConfigWindow::Width(g.width),
+                            x::ConfigWindow::Height(g.height),
+                        ],
+                    });
+
+                    let monitor = self.monitor_at(g.x + g.width as i32/2, g.y + g.height as i32/2);
+                    if let Some(w) = self.windows.get_mut(&window) {
+                        w.monitor = monitor;
+                    }
+                }
+
+                self.scratchpad_shown = true;
+                self.show_workspaces();
+            }
+
+            self.bring_window_to_front(window);
+            self.focus_by_keyboard(window)?;
+        }
+
+        self.flush()?;
+
+        Ok(())
+    }
+
+    // ask the focused window to close with WM_DELETE_WINDOW. if it's still around next time, kill
+    // its process with SIGTERM and then SIGKILL, or disconnect it from the server if it's not
+    // running here
+    fn close_window(&mut self, window: x::Window) -> xcb::Result<()> {
+        let (attempt, pid, machine) = match self.windows.get_mut(&window) {
+            Some(w) => {
+                w.close_attempts += 1;
+                (w.close_attempts, w.pid, w.client_machine.clone())
+            },
+            None => return Ok(()),
+        };
+
+        if attempt == 1 && self.supports_delete(window)? {
+            debug!("asking {:?} to close", window);
+
+            let event = x::ClientMessageEvent::new(window, self.atoms.wm_protocols,
+                x::ClientMessageData::Data32([self.atoms.wm_delete_window.resource_id(), self.last_time, 0, 0, 0]));
+            self.conn.send_request_checked(&x::SendEvent {
+                propagate: false,
+                destination: x::SendEventDest::Window(window),
+                event_mask: x::EventMask::NO_EVENT,
+                event: &event,
+            });
+            self.flush()?;
+
+            return Ok(());
+        }
+
+        let local = machine.is_some() && machine == hostname();
+
+        match pid {
+            Some(pid) if local => {
+                let signal = match attempt {
+                    1 | 2 => libc::SIGTERM,
+                    _ => libc::SIGKILL,
+                };
+                info!("sending signal {} to pid {} for {:?}", signal, pid, window);
+
+                if unsafe { libc::kill(pid, signal) } < 0 {
+                    warn!("couldn't signal pid {}: {}", pid, std::io::Error::last_os_error());
+                }
+            },
+
+            _ => {
+                info!("killing X client for {:?}", window);
+
+                self.conn.send_request_checked(&x::KillClient {
+                    resource: window.resource_id(),
+                });
+                self.flush()?;
+            },
+        }
+
+        Ok(())
+    }
+
+    // put the window on whichever monitor its centre is now over
+    fn update_window_monitor(&mut self, window: x::Window) -> xcb::Result<()> {
+        let geometry = self.wait_for_reply(self.conn.send_request(&x::GetGeometry {
+            drawable: x::Drawable::Window(window),
+        }))?;
+
+        let centre_x = geometry.x() as i32 + geometry.width() as i32/2 + geometry.border_width() as i32;
+        let centre_y = geometry.y() as i32 + geometry.height() as i32/2 + geometry.border_width() as i32;
+        let monitor = self.monitor_at(centre_x, centre_y);
+
+        self.set_window_monitor(window, monitor);
+
+        Ok(())
+    }
+
+    fn set_window_monitor(&mut self, window: x::Window, monitor: usize) {
+        let current = self.current_workspaces[monitor];
+        let per_monitor = self.config.per_monitor_workspaces;
+
+        if let Some(w) = self.windows.get_mut(&window) {
+            if w.monitor != monitor {
+                debug!("{:?} now on monitor {}", window, monitor);
+
+                w.monitor = monitor;
+
+                // it's on screen there, so it belongs to the workspace showing there
+                if per_monitor {
+                    w.workspace = current;
+                }
+            }
+        }
+    }
+
+    // index of the monitor containing the point, or the one closest to it
+    fn monitor_at(&self, x: i32, y: i32) -> usize {
+        self.monitors.iter()
+            .enumerate()
+            .min_by_key(|(_, m)| m.distance_to(x, y))
+            .map(|(i, _)| i)
+            .unwrap_or(0)
+    }
+
+    // move the focused window along the monitor list, keeping its relative position
+    fn send_to_monitor(&mut self, step: i32) -> xcb::Result<()> {
+        let window = match self.focused {
+            Some(w) => w,
+            None => return Ok(()),
+        };
+
+        if self.monitors.len() < 2 {
+            return Ok(());
+        }
+
+        let geometry = self.wait_for_reply(self.conn.send_request(&x::GetGeometry {
+            drawable: x::Drawable::Window(window),
+        }))?;
+
+        let win_x = geometry.x() as i32;
+        let win_y = geometry.y() as i32;
+        let win_width = geometry.width() as i32 + 2*geometry.border_width() as i32;
+        let win_height = geometry.height() as i32 + 2*geometry.border_width() as i32;
+
+        let from_idx = self.monitor_at(win_x + win_width/2, win_y + win_height/2);
+        let to_idx = (from_idx as i32 + step).rem_euclid(self.monitors.len() as i32) as usize;
+
+        let from = self.work_area(from_idx);
+        let to = self.work_area(to_idx);
+
+        // position as a fraction of the space the window can move around in
+        let frac = |pos: i32, start: i32, free: i32| {
+            if free > 0 { (pos - start) as f64 / free as f64 } else { 0.0 }
+        };
+        let frac_x = frac(win_x, from.x, from.width - win_width);
+        let frac_y = frac(win_y, from.y, from.height - win_height);
+
+        let new_x = to.x + (frac_x * (to.width - win_width).max(0) as f64).round() as i32;
+        let new_y = to.y + (frac_y * (to.height - win_height).max(0) as f64).round() as i32;
+        let (new_x, new_y) = to.clamp(new_x, new_y, win_width, win_height);
+
+        debug!("sending {:?} from monitor {} to {}, now at {},{}", window, from_idx, to_idx, new_x, new_y);
+
+        self.conn.send_request_checked(&x::ConfigureWindow {
+            window,
+            value_list: &[
+                x::ConfigWindow::X(new_x),
+                x::ConfigWindow::Y(new_y),
+            ],
+        });
+        let moved = Geometry { x: new_x, y: new_y, width: geometry.width() as u32, height: geometry.height() as u32 };
+        self.send_configure_notify(window, moved, geometry.border_width() as u32);
+
+        self.set_window_monitor(window, to_idx);
+        self.remember_geometry(window)?;
+
+        // it's still the one we're working with, so keep it on top and focused
+        self.bring_window_to_front(window);
+        self.focus_by_keyboard(window)?;
+
+        // it may have left one tiling for another
+        self.arrange();
+
+        self.flush()?;
+
+        Ok(())
+    }
+
+    // the focused tiled window into the next monitor's tiling, as the same tile it was here:
+    // second in the stack here is second in the stack there, if there are enough. anything
+    // that isn't tiled is just sent along like send_to_monitor does
+    fn tile_to_monitor(&mut self, step: i32) -> xcb::Result<()> {
+        let (window, from) = match self.focused.and_then(|f| self.windows.get(&f)) {
+            Some(w) => (w.x_window, w.monitor),
+            None => return Ok(()),
+        };
+
+        if self.monitors.len() < 2 {
+            return Ok(());
+        }
+
+        let index = match self.tiled_windows(from).iter().position(|&w| w == window) {
+            Some(i) if self.config.tiling => i,
+            _ => return self.send_to_monitor(step),
+        };
+
+        let to = (from as i32 + step).rem_euclid(self.monitors.len() as i32) as usize;
+        let there = self.tiled_windows(to);
+
+        // tiles are in layout order, so that's where it goes: in front of whatever has its
+        // place there now, or after the last one
+        self.layout_order.retain(|&w| w != window);
+        let position = match (there.get(index), there.last()) {
+            (Some(taken), _) => self.layout_order.iter().position(|w| w == taken),
+            (None, Some(last)) => self.layout_order.iter().position(|w| w == last).map(|p| p + 1),
+            (None, None) => None,
+        };
+        self.layout_order.insert(position.unwrap_or(self.layout_order.len()), window);
+
+        debug!("tiling {:?} on monitor {} at {}, from {}", window, to, index, from);
+
+        self.set_window_monitor(window, to);
+        self.arrange();
+
+        self.bring_window_to_front(window);
+        self.focus_by_keyboard(window)?;
+        self.flush()?;
+
+        Ok(())
+    }
+
+    // keyboard focus to the top window on the next monitor along. the pointer goes to the middle
+    // of it first, if we're warping, so the window it lands on doesn't take focus back
+    fn focus_monitor(&mut self, step: i32) -> xcb::Result<()> {
+        if self.monitors.len() < 2 {
+            return Ok(());
+        }
+
+        let from = self.active_monitor()?;
+        let to = (from as i32 + step).rem_euclid(self.monitors.len() as i32) as usize;
+
+        debug!("focusing monitor {} from {}", to, from);
+
+        if self.config.focus_monitor_warps {
+            let monitor = self.monitors[to];
+            self.conn.send_request_checked(&x::WarpPointer {
+                src_window: x::WINDOW_NONE,
+                dst_window: self.screen.root(),
+                src_x: 0,
+                src_y: 0,
+                src_width: 0,
+                src_height: 0,
+                dst_x: (monitor.x + monitor.width/2) as i16,
+                dst_y: (monitor.y + monitor.height/2) as i16,
+            });
+        }
+
+        self.focus_top_window_on(to)?;
+        self.flush()?;
+
+        Ok(())
+    }
+
+    fn toggle_maximize(&mut self) -> xcb::Result<()> {
+        let window = match self.focused.and_then(|f| self.windows.get(&f)) {
+            Some(w) => w,
+            None => return Ok(()),
+        };
+
+        // all or nothing; if only one axis is maximized, finish the job
+        let maximize = !(window.maximized_horz && window.maximized_vert);
+        let (window, zoomed) = (window.x_window, window.zoomed);
+
+        // zoom shares the restore point, so come out of it first
+        if zoomed {
+            self.set_zoomed(window, false)?;
+        }
+
+        self.set_maximized(window, maximize, maximize)?;
+        self.flush()?;
+
+        Ok(())
+    }
+
+    // with confirm_quit, the first press only arms it and a second one within QUIT_CONFIRM_TIME
+    // quits. the press that triggered this is the last user time
+    fn quit(&mut self) -> xcb::Result<()> {
+        let now = self.last_user_time;
+
+        let confirmed = match self.quit_armed {
+            _ if !self.config.confirm_quit => true,
+            Some(armed) => time_since(now, armed) <= QUIT_CONFIRM_TIME,
+            None => false,
+        };
+
+        if confirmed {
+            self.quitting = true;
+        }
+        else {
+            info!("press quit again within {}s to quit", QUIT_CONFIRM_TIME/1000);
+            self.quit_armed = Some(now);
+        }
+
+        Ok(())
+    }
+
+    // the first press grabs the keyboard and starts from the top of the stack; each one after
+    // raises the next window down (or up) to show it. nothing is focused until the modifier
+    // is let go
+    fn cycle_windows(&mut self, step: i32, set: CycleSet) -> xcb::Result<()> {
+        if self.cycle.is_none() && !self.start_cycle(set)? {
+            return Ok(());
+        }
+
+        let (previous, next) = match self.cycle.as_mut() {
+            Some(cycle) => {
+                let previous = cycle.windows[cycle.index];
+                cycle.index = (cycle.index as i32 + step).rem_euclid(cycle.windows.len() as i32) as usize;
+                (previous, cycle.windows[cycle.index])
+            },
+            None => return Ok(()),
+        };
+
+        self.unpreview(previous);
+        debug!("cycling to {:?}", next);
+        self.bring_window_to_front(next);
+
+        // already let go, so that was just a quick switch
+        if self.cycle.as_ref().is_some_and(|c| c.release_keycodes.is_empty()) {
+            self.finish_cycle()?;
+        }
+
+        self.flush()?;
+
+        Ok(())
+    }
+
+    // through everything showing, just windows of the focused one's class, or just the ones on
+    // the monitor with focus or the pointer. the choice is made here, so once it's going any of
+    // the bindings steps through the same set
+    fn start_cycle(&mut self, set: CycleSet) -> xcb::Result<bool> {
+        let class = match set {
+            CycleSet::SameClass => match self.focused.and_then(|f| self.windows.get(&f)).and_then(|w| w.class.clone()) {
+                Some(class) => Some(class),
+                None => return Ok(false),
+            },
+            _ => None,
+        };
+        let monitor = match set {
+            CycleSet::SameMonitor => Some(self.active_monitor()?),
+            _ => None,
+        };
+
+        // by where the middle of the window is now
+        let on_monitor = |w: &Window| match w.geometry {
+            Some(g) => self.monitor_at(g.x + g.width as i32/2, g.y + g.height as i32/2),
+            None => w.monitor,
+        };
+
+        let mut windows: Vec<_> = self.stacking.iter().rev()
+            .filter_map(|w| self.windows.get(w))
+            .filter(|w| self.is_visible(w) && w.window_type != WindowType::Splash)
+            .filter(|w| class.is_none() || w.class == class)
+            .filter(|w| monitor.is_none_or(|m| on_monitor(w) == m))
+            .map(|w| w.x_window)
+            .collect();
+
+        // flipping back and forth goes between the last two focused, whatever's been raised
+        // since. ones that haven't had focus yet come after, top first
+        windows.sort_by_key(|w| self.focus_history.iter().position(|h| h == w).unwrap_or(usize::MAX));
+
+        if windows.len() < 2 {
+            return Ok(false);
+        }
+
+        let grab = self.wait_for_reply(self.conn.send_request(&x::GrabKeyboard {
+            owner_events: false,
+            grab_window: self.screen.root(),
+            time: self.last_time,
+            pointer_mode: x::GrabMode::Async,
+            keyboard_mode: x::GrabMode::Async,
+        }))?;
+        if grab.status() != x::GrabStatus::Success {
+            debug!("couldn't grab keyboard for cycling: {:?}", grab.status());
+            return Ok(false);
+        }
+
+        // the binding's modifiers should still be held, and letting go of any but shift ends it.
+        // asked after grabbing, so a release can't slip in between unseen
+        let pointer = self.wait_for_reply(self.conn.send_request(&x::QueryPointer {
+            window: self.screen.root(),
+        }))?;
+        let held = pointer.mask().bits() & 0xff &
+            !(x::ModMask::SHIFT | x::ModMask::LOCK | x::ModMask::N2).bits();
+
+        let mapping = self.wait_for_reply(self.conn.send_request(&x::GetModifierMapping {}))?;
+        let per_modifier = mapping.keycodes_per_modifier() as usize;
+        let release_keycodes: Vec<_> = mapping.keycodes().chunks(per_modifier.max(1))
+            .enumerate()
+            .filter(|(i, _)| held & (1 << i) != 0)
+            .flat_map(|(_, keycodes)| keycodes.iter().copied().filter(|&k| k != 0))
+            .collect();
+
+        debug!("cycling through {:?}", windows);
+
+        self.cycle = Some(Cycle {
+            windows,
+            index: 0,
+            original_stacking: self.stacking.clone(),
+            release_keycodes,
+        });
+
+        Ok(true)
+    }
+
+    // put a previewed window back where it was in the stack
+    fn unpreview(&mut self, window: x::Window) {
+        let above = self.cycle.as_ref().and_then(|c| {
+            let i = c.original_stacking.iter().position(|&w| w == window)?;
+            c.original_stacking[i+1..].iter().find(|w| self.stacking.contains(w)).copied()
+        });
+
+        if let Some(above) = above {
+            self.stack_below(window, above);
+        }
+    }
+
+    fn finish_cycle(&mut self) -> xcb::Result<()> {
+        let cycle = match self.cycle.take() {
+            Some(c) => c,
+            None => return Ok(()),
+        };
+
+        self.conn.send_request_checked(&x::UngrabKeyboard {
+            time: self.last_time,
+        });
+
+        let window = cycle.windows[cycle.index];
+        if self.windows.contains_key(&window) {
+            debug!("cycled to {:?}", window);
+            self.bring_window_to_front(window);
+            self.focus_by_keyboard(window)?;
+        }
+
+        self.flush()?;
+
+        Ok(())
+    }
+
+    // in whole percent steps, between min_opacity and opaque
+    fn step_opacity(&mut self, step: i32) -> xcb::Result<()> {
+        let window = match self.focused.and_then(|f| self.windows.get(&f)) {
+            Some(w) => w,
+            None => return Ok(()),
+        };
+
+        let percent = (window.opacity as u64 * 100 + u32::MAX as u64/2) / u32::MAX as u64;
+        let percent = (percent as i32 + step).clamp(self.config.min_opacity.min(100) as i32, 100);
+        let opacity = (u32::MAX as u64 * percent as u64 / 100) as u32;
+
+        debug!("{:?} opacity {}%", window.x_window, percent);
+
+        self.set_opacity(window.x_window, opacity);
+        self.flush()?;
+
+        Ok(())
+    }
+
+    fn set_opacity(&mut self, window: x::Window, opacity: u32) {
+        if let Some(w) = self.windows.get_mut(&window) {
+            w.opacity = opacity;
+        }
+        self.show_opacity(window);
+    }
+
+    // a window and its dialogs, and theirs, go together for dimming: the top of its chain of
+    // WM_TRANSIENT_FOR. so a dialog for the focused window isn't dimmed, and neither is the
+    // window a focused dialog belongs to
+    fn focus_group(&self, window: x::Window) -> x::Window {
+        let mut top = window;
+
+        // a loop of transients is a client bug, but it shouldn't hang us
+        for _ in 0..self.windows.len() {
+            match self.windows.get(&top).and_then(|w| w.transient_for) {
+                Some(parent) if self.windows.contains_key(&parent) => top = parent,
+                _ => break,
+            }
+        }
+
+        top
+    }
+
+    // after focus moves, for the group that had it as well as the one that has it now. only
+    // the ones we manage: menus and popups, and anything never mapped, keep their own
+    fn show_all_opacity(&mut self) {
+        let windows: Vec<_> = self.windows.values()
+            .filter(|w| w.mapped)
+            .map(|w| w.x_window)
+            .collect();
+        for window in windows {
+            self.show_opacity(window);
+        }
+    }
+
+    // put the window's opacity on the property, dimmed by unfocused_opacity if it isn't in the
+    // focused window's group
+    fn show_opacity(&mut self, window: x::Window) {
+        let focused = self.focused.is_some_and(|f| self.focus_group(f) == self.focus_group(window));
+        let percent = self.config.unfocused_opacity.min(100) as u64;
+
+        let opacity = match self.windows.get_mut(&window) {
+            Some(w) if w.mapped => {
+                let opacity = if focused {
+                    w.opacity
+                }
+                else {
+                    (w.opacity as u64 * percent / 100) as u32
+                };
+                if opacity == w.shown_opacity {
+                    return;
+                }
+                w.shown_opacity = opacity;
+                opacity
+            },
+            _ => return,
+        };
+
+        // no property means opaque to a compositor, so don't leave one lying around
+        if opacity == u32::MAX {
+            self.conn.send_request_checked(&x::DeleteProperty {
+                window,
+                property: self.atoms.net_wm_window_opacity,
+            });
+        }
+        else {
+            self.conn.send_request_checked(&x::ChangeProperty {
+                mode: x::PropMode::Replace,
+                window,
+                property: self.atoms.net_wm_window_opacity,
+                r#type: x::ATOM_CARDINAL,
+                data: &[opacity],
+            });
+        }
+    }
+
+    fn minimize_focused(&mut self) -> xcb::Result<()> {
+        if let Some(window) = self.focused {
+            self.minimize(window)?;
+        }
+
+        Ok(())
+    }
+
+    // hidden like it's on another workspace until it's restored
+    fn minimize(&mut self, window: x::Window) -> xcb::Result<()> {
+        match self.windows.get_mut(&window) {
+            Some(w) if w.mapped && !w.iconic => w.iconic = true,
+            _ => return Ok(()),
+        }
+
+        debug!("minimizing {:?}", window);
+
+        self.minimized.push(window);
+        self.set_wm_state(window, ICONIC_STATE);
+        self.show_workspaces();
+
+        if self.focused == Some(window) {
+            self.focus_top_window()?;
+        }
+
+        self.flush()?;
+
+        Ok(())
+    }
+
+    fn restore_minimized(&mut self) -> xcb::Result<()> {
+        if let Some(&window) = self.minimized.last() {
+            self.restore(window)?;
+        }
+
+        Ok(())
+    }
+
+    // back on the workspace showing where it is, on top and focused
+    fn restore(&mut self, window: x::Window) -> xcb::Result<()> {
+        self.minimized.retain(|&w| w != window);
+
+        match self.windows.get_mut(&window) {
+            Some(w) if w.iconic => {
+                w.iconic = false;
+                w.workspace = self.current_workspaces[w.monitor];
+            },
+            _ => return Ok(()),
+        }
+
+        debug!("restoring {:?}", window);
+
+        self.set_wm_state(window, NORMAL_STATE);
+        self.show_workspaces();
+        self.bring_window_to_front(window);
+        self.focus_by_keyboard(window)?;
+        self.flush()?;
+
+        Ok(())
+    }
+
+    // as it is now, which can be different from when it was created. a window that's gone isn't,
+    // so it carries on as it would have and fails quietly
+    fn is_override_redirect(&self, window: x::Window) -> xcb::Result<bool> {
+        let cookie = self.conn.send_request(&x::GetWindowAttributes {
+            window,
+        });
+
+        match self.wait_for_reply(cookie) {
+            Ok(attrs) => Ok(attrs.override_redirect()),
+            Err(xcb::Error::Protocol(e)) => {
+                debug!("couldn't get attributes for {:?}: {:?}", window, e);
+                Ok(false)
+            },
+            Err(e) => Err(e),
+        }
+    }
+
+    // WM_STATE as we, or another window manager, last set it
+    fn get_wm_state(&self, window: x::Window) -> xcb::Result<Option<u32>> {
+        let state = self.get_property(window, self.atoms.wm_state, self.atoms.wm_state, 1)?
+            .filter(|r| r.format() == 32)
+            .and_then(|r| r.value::<u32>().first().copied());
+
+        Ok(state)
+    }
+
+    // WM_STATE is ours to set, and says whether the window is showing, minimized or withdrawn
+    fn set_wm_state(&self, window: x::Window, state: u32) {
+        self.conn.send_request_checked(&x::ChangeProperty {
+            mode: x::PropMode::Replace,
+            window,
+            property: self.atoms.wm_state,
+            r#type: self.atoms.wm_state,
+            data: &[state, x::WINDOW_NONE.resource_id()],
+        });
+    }
+
+    // WM_HINTS asks to start minimized: StateHint (bit 1) with initial_state, the third word,
+    // set to IconicState
+    fn wants_iconic(&self, window: x::Window) -> xcb::Result<bool> {
+        let iconic = self.get_property(window, x::ATOM_WM_HINTS, x::ATOM_WM_HINTS, 3)?
+            .filter(|r| r.format() == 32)
+            .is_some_and(|r| match r.value::<u32>() {
+                [flags, _, state, ..] => flags & (1 << 1) != 0 && *state == ICONIC_STATE,
+                _ => false,
+            });
+
+        Ok(iconic)
+    }
+
+    // indexes of the config rules matching a class and title
+    fn matching_rules(&self, class: Option<&str>, title: Option<&str>) -> Vec<usize> {
+        self.config.rules.iter()
+            .enumerate()
+            .filter(|(_, r)| r.matches(class, title))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    // an ignore rule says to leave it be. only checked as it's mapped, since after that it's
+    // not something we're tracking
+    fn is_ignored(&self, window: x::Window) -> xcb::Result<bool> {
+        if !self.config.rules.iter().any(|r| r.ignore) {
+            return Ok(false);
+        }
+
+        let class = self.get_class(window)?;
+        let title = self.get_title(window)?;
+        let ignored = self.matching_rules(class.as_deref(), title.as_deref()).into_iter()
+            .any(|i| self.config.rules[i].ignore);
+
+        Ok(ignored)
+    }
+
+    // titles often turn up after the window is mapped, or change as it goes. title rules that
+    // have just started matching get applied now
+    fn apply_title_rules(&mut self, window: x::Window) -> xcb::Result<()> {
+        let (matched, started) = match self.windows.get(&window) {
+            Some(w) if w.mapped => {
+                let matched = self.matching_rules(w.class.as_deref(), w.title.as_deref());
+                let started: Vec<_> = matched.iter().copied()
+                    .filter(|i| !w.matched_rules.contains(i) && self.config.rules[*i].title.is_some())
+                    .collect();
+                (matched, started)
+            },
+            _ => return Ok(()),
+        };
+
+        if let Some(w) = self.windows.get_mut(&window) {
+            w.matched_rules = matched;
+        }
+
+        if started.is_empty() {
+            return Ok(());
+        }
+
+        debug!("{:?} now matches rules {:?}", window, started);
+
+        for i in started {
+            let (floating, workspace, monitor, pin_width) = {
+                let rule = &self.config.rules[i];
+                (rule.floating, rule.workspace, rule.monitor, rule.pin_width)
+            };
+
+            if let Some(monitor) = monitor.filter(|&m| m < self.monitors.len()) {
+                self.move_to_monitor(window, monitor);
+            }
+
+            let workspace_count = self.config.workspace_count;
+            if let Some(w) = self.windows.get_mut(&window) {
+                if let Some(workspace) = workspace.filter(|&w| w < workspace_count) {
+                    w.workspace = workspace;
+                }
+                if let Some(floating) = floating {
+                    w.floating = floating;
+                }
+                if pin_width.is_some() {
+                    w.pinned_width = pin_width;
+                }
+            }
+
+            if floating == Some(true) {
+                self.bring_window_to_front(window);
+            }
+        }
+
+        self.show_workspaces();
+        if self.focused == Some(window) && self.windows.get(&window).is_some_and(|w| !self.is_visible(w)) {
+            self.focus_top_window()?;
+        }
+        self.flush()?;
+
+        Ok(())
+    }
+
+    // same place relative to the monitor, kept on it
+    fn move_to_monitor(&mut self, window: x::Window, monitor: usize) {
+        let (from, geometry, border_width) = match self.windows.get(&window) {
+            Some(w) if w.monitor != monitor => (self.work_area(w.monitor), w.geometry, self.window_border(w)),
+            _ => return,
+        };
+        let to = self.work_area(monitor);
+
+        if let Some(g) = geometry {
+            let outer_width = g.width as i32 + 2*border_width as i32;
+            let outer_height = g.height as i32 + 2*border_width as i32;
+            let (x, y) = to.clamp(g.x - from.x + to.x, g.y - from.y + to.y, outer_width, outer_height);
+
+            self.conn.send_request_checked(&x::ConfigureWindow {
+                window,
+                value_list: &[
+                    x::ConfigWindow::X(x),
+                    x::ConfigWindow::Y(y),
+                ],
+            });
+            self.send_configure_notify(window, Geometry { x, y, ..g }, border_width);
+        }
+
+        self.set_window_monitor(window, monitor);
+    }
+
+    fn toggle_floating(&mut self) -> xcb::Result<()> {
+        let window = match self.focused.and_then(|f| self.windows.get_mut(&f)) {
+            Some(w) => w,
+            None => return Ok(()),
+        };
+
+        window.floating = !window.floating;
+        debug!("{:?} floating {}", window.x_window, window.floating);
+
+        // floating windows go over the tiles
+        if window.floating {
+            let window = window.x_window;
+            self.bring_window_to_front(window);
+        }
+
+        self.arrange();
+        self.flush()?;
+
+        Ok(())
+    }
+
+    // same size, middle of the work area. tiles and anything filling the monitor already have
+    // their place
+    fn centre_window(&mut self) -> xcb::Result<()> {
+        let (window, monitor) = match self.focused.and_then(|f| self.windows.get(&f)) {
+            Some(w) if w.fullscreen || w.zoomed || w.maximized_horz || w.maximized_vert => return Ok(()),
+            Some(w) if self.config.tiling && !w.floating => return Ok(()),
+            Some(w) => (w.x_window, w.monitor),
+            None => return Ok(()),
+        };
+
+        let geometry = self.wait_for_reply(self.conn.send_request(&x::GetGeometry {
+            drawable: x::Drawable::Window(window),
+        }))?;
+        let (width, height, border_width) = (geometry.width() as u32, geometry.height() as u32, geometry.border_width() as u32);
+
+        let (x, y) = layout::centre(&self.work_area(monitor), width, height, border_width as i32);
+
+        debug!("centring {:?} at {},{}", window, x, y);
+
+        self.conn.send_request_checked(&x::ConfigureWindow {
+            window,
+            value_list: &[
+                x::ConfigWindow::X(x),
+                x::ConfigWindow::Y(y),
+            ],
+        });
+        self.send_configure_notify(window, Geometry { x, y, width, height }, border_width);
+        self.flush()?;
+
+        Ok(())
+    }
+
+    // the windows arrange lays out on the monitor, in layout order
+    fn tiled_windows(&self, monitor_idx: usize) -> Vec<x::Window> {
+        self.layout_order.iter()
+            .filter_map(|w| self.windows.get(w))
+            .filter(|w| w.monitor == monitor_idx && self.is_visible(w))
+            .filter(|w| !w.floating && !w.fullscreen && !w.zoomed)
+            .filter(|w| !w.maximized_horz && !w.maximized_vert)
+            .filter(|w| w.window_type != WindowType::Splash && self.scratchpad != Some(w.x_window))
+            .map(|w| w.x_window)
+            .collect()
+    }
+
+    // the workspace showing where the user is, between tiles side by side and tabs
+    fn toggle_tabbed(&mut self) -> xcb::Result<()> {
+        if !self.config.tiling {
+            return Ok(());
+        }
+
+        let monitor = self.active_monitor()?;
+        let workspace = self.current_workspaces[monitor];
+        if !self.tabbed.remove(&workspace) {
+            self.tabbed.insert(workspace);
+        }
+
+        debug!("workspace {} tabbed {}", workspace, self.tabbed.contains(&workspace));
+
+        self.arrange();
+
+        // whichever tab has focus should be the one showing
+        if let Some(focused) = self.focused.filter(|f| self.tiled_windows(monitor).contains(f)) {
+            self.bring_window_to_front(focused);
+        }
+
+        self.flush()?;
+
+        Ok(())
+    }
+
+    fn master_width(&self, workspace: usize) -> u32 {
+        self.master_widths.get(&workspace).copied().unwrap_or(layout::MASTER_DEFAULT)
+    }
+
+    // the first tile on the workspace showing where the user is, wider or narrower
+    fn resize_master(&mut self, step: i32) -> xcb::Result<()> {
+        if !self.config.tiling {
+            return Ok(());
+        }
+
+        let workspace = self.current_workspaces[self.active_monitor()?];
+        let width = (self.master_width(workspace) as i32 + step).clamp(layout::MASTER_MIN as i32, layout::MASTER_MAX as i32) as u32;
+        debug!("workspace {} master width {}%", workspace, width);
+        self.master_widths.insert(workspace, width);
+
+        self.arrange();
+        self.flush()?;
+
+        Ok(())
+    }
+
+    // forget whatever's been adjusted on the workspace showing where the user is, so it tiles
+    // the way it did to begin with
+    fn reset_layout(&mut self) -> xcb::Result<()> {
+        if !self.config.tiling {
+            return Ok(());
+        }
+
+        let workspace = self.current_workspaces[self.active_monitor()?];
+        debug!("workspace {} layout reset", workspace);
+        self.master_widths.remove(&workspace);
+
+        self.arrange();
+        self.flush()?;
+
+        Ok(())
+    }
+
+    // first time marks the focused window, second time swaps it with the marked one: places in
+    // the tiling if they're both tiled, where they are on the screen if they're both floating
+    fn swap_windows(&mut self) -> xcb::Result<()> {
+        let focused = match self.focused.filter(|f| self.windows.contains_key(f)) {
+            Some(f) => f,
+            None => return Ok(()),
+        };
+
+        let marked = match self.marked.take().filter(|&m| m != focused) {
+            Some(m) if self.windows.get(&m).is_some_and(|w| self.is_visible(w)) => m,
+            _ => {
+                debug!("marked {:?} to swap", focused);
+                self.marked = Some(focused);
+                return Ok(());
+            },
+        };
+
+        let tiled = |wm: &Manager, window| wm.windows.get(&window).is_some_and(|w| wm.tiled_windows(w.monitor).contains(&window));
+
+        let (marked_tiled, focused_tiled) = (tiled(self, marked), tiled(self, focused));
+        if marked_tiled && focused_tiled {
+            debug!("swapping tiles {:?} and {:?}", marked, focused);
+
+            let a = self.layout_order.iter().position(|&w| w == marked);
+            let b = self.layout_order.iter().position(|&w| w == focused);
+            if let (Some(a), Some(b)) = (a, b) {
+                self.layout_order.swap(a, b);
+            }
+        }
+        else if !marked_tiled && !focused_tiled {
+            debug!("swapping {:?} and {:?}", marked, focused);
+
+            let a = self.conn.send_request(&x::GetGeometry {
+                drawable: x::Drawable::Window(marked),
+            });
+            let b = self.conn.send_request(&x::GetGeometry {
+                drawable: x::Drawable::Window(focused),
+            });
+            let (a, b) = (self.wait_for_reply(a)?, self.wait_for_reply(b)?);
+
+            for (window, g, border_width) in [(marked, &b, a.border_width()), (focused, &a, b.border_width())] {
+                let geometry = Geometry {
+                    x: g.x() as i32,
+                    y: g.y() as i32,
+                    width: g.width() as u32,
+                    height: g.height() as u32,
+                };
+                self.conn.send_request_checked(&x::ConfigureWindow {
+                    window,
+                    value_list: &[
+                        x::ConfigWindow::X(geometry.x),
+                        x::ConfigWindow::Y(geometry.y),
+                        x::ConfigWindow::Width(geometry.width),
+                        x::ConfigWindow::Height(geometry.height),
+                    ],
+                });
+                self.send_configure_notify(window, geometry, border_width as u32);
+            }
+        }
+        else {
+            debug!("not swapping {:?} and {:?}, only one of them is tiled", marked, focused);
+            return Ok(());
+        }
+
+        // each takes the other's monitor and workspace too, for swaps between monitors
+        let place = |wm: &Manager, window| wm.windows.get(&window).map(|w| (w.monitor, w.workspace));
+        if let (Some(a), Some(b)) = (place(self, marked), place(self, focused)) {
+            for (window, (monitor, workspace)) in [(marked, b), (focused, a)] {
+                if let Some(w) = self.windows.get_mut(&window) {
+                    w.monitor = monitor;
+                    w.workspace = workspace;
+                }
+            }
+        }
+
+        self.publish_workspaces();
+        self.arrange();
+        self.flush()?;
+
+        Ok(())
+    }
+
+    // the next or previous tile on the active monitor to the front, with focus. on a tabbed
+    // workspace that's switching tabs
+    fn step_tab(&mut self, step: i32) -> xcb::Result<()> {
+        let tiled = self.tiled_windows(self.active_monitor()?);
+        if tiled.is_empty() {
+            return Ok(());
+        }
+
+        let next = match self.focused.and_then(|f| tiled.iter().position(|&w| w == f)) {
+            Some(i) => tiled[(i as i32 + step).rem_euclid(tiled.len() as i32) as usize],
+            None => tiled[0],
+        };
+
+        debug!("switching to tab {:?}", next);
+
+        self.bring_window_to_front(next);
+        self.focus_by_keyboard(next)?;
+        self.flush()?;
+
+        Ok(())
+    }
+
+    // lay out the tiled windows showing on each monitor. floating, fullscreen, zoomed and
+    // maximized windows are left where they are
+    fn arrange(&mut self) {
+        self.update_lone_borders();
+
+        if !self.config.tiling {
+            return;
+        }
+
+        for monitor_idx in 0..self.monitors.len() {
+            let area = self.work_area(monitor_idx);
+            let monitor = &match self.config.tiling_max_width {
+                Some(max_width) => layout::pillarbox(&area, max_width.resolve(area.width)),
+                None => area,
+            };
+            let tiled = self.tiled_windows(monitor_idx);
+
+            // one tiled window might be the only thing there, and have no border
+            let border_width = match tiled.as_slice() {
+                [only] => self.windows.get(only).map_or(self.config.border_width, |w| self.window_border(w)) as i32,
+                _ => self.border_width(),
+            };
+
+            // pinned tiles get their columns first, and the rest tile in what's left
+            let (pinned, rest): (Vec<_>, Vec<_>) = tiled.into_iter()
+                .partition(|t| self.windows.get(t).is_some_and(|w| w.pinned_width.is_some()));
+            let widths: Vec<_> = pinned.iter()
+                .filter_map(|t| self.windows.get(t)?.pinned_width)
+                .map(|pin| pin.resolve(monitor.width))
+                .collect();
+            let (pinned_cells, area) = layout::pin_columns(monitor, &widths, border_width);
+
+            let workspace = self.current_workspaces[monitor_idx];
+            let cells = if self.tabbed.contains(&workspace) {
+                layout::tabbed(&area, rest.len(), border_width)
+            }
+            else {
+                layout::tile(&area, rest.len(), self.master_width(workspace), border_width)
+            };
+
+            for (window, g) in pinned.into_iter().zip(pinned_cells).chain(rest.into_iter().zip(cells)) {
+                debug!("tiling {:?} at {},{} {}x{}", window, g.x, g.y, g.width, g.height);
+                self.conn.send_request_checked(&x::ConfigureWindow {
+                    window,
+                    value_list: &[
+                        x::ConfigWindow::X(g.x),
+                        x::ConfigWindow::Y(g.y),
+                        x::ConfigWindow::Width(g.width),
+                        x::ConfigWindow::Height(g.height),
+                    ],
+                });
+            }
+        }
+    }
+
+    fn toggle_zoom(&mut self) -> xcb::Result<()> {
+        let window = match self.focused.and_then(|f| self.windows.get(&f)) {
+            Some(w) => w,
+            None => return Ok(()),
+        };
+
+        // the client's own fullscreen wins, and it comes back zoomed or not as it went in
+        if window.fullscreen {
+            debug!("{:?} is fullscreen, not zooming", window.x_window);
+            return Ok(());
+        }
+
+        self.set_zoomed(window.x_window, !window.zoomed)?;
+        self.flush()?;
+
+        Ok(())
+    }
+
+    // fill the monitor with no border, or go back to the saved geometry. if it's maximized the
+    // saved geometry is already the maximize restore point, so leave it alone and maximize again
+    // on the way out
+    fn set_zoomed(&mut self, window: x::Window, zoomed: bool) -> xcb::Result<()> {
+        let geometry = self.wait_for_reply(self.conn.send_request(&x::GetGeometry {
+            drawable: x::Drawable::Window(window),
+        }))?;
+
+        let (monitor, border_width) = match self.windows.get(&window) {
+            Some(w) => (self.work_area(w.monitor), self.window_border(w)),
+            None => return Ok(()),
+        };
+
+        let win = match self.windows.get_mut(&window) {
+            Some(w) => w,
+            None => return Ok(()),
+        };
+
+        if win.zoomed == zoomed {
+            return Ok(());
+        }
+        win.zoomed = zoomed;
+
+        let current = Geometry {
+            x: geometry.x() as i32,
+            y: geometry.y() as i32,
+            width: geometry.width() as u32,
+            height: geometry.height() as u32,
+        };
+        let maximized = (win.maximized_horz, win.maximized_vert);
+
+        debug!("{:?} zoomed {}", window, zoomed);
+
+        if zoomed {
+            if maximized == (false, false) {
+                win.saved_geometry = Some(current);
+            }
+
+            self.conn.send_request_checked(&x::ConfigureWindow {
+                window,
+                value_list: &[
+                    x::ConfigWindow::X(monitor.x),
+                    x::ConfigWindow::Y(monitor.y),
+                    x::ConfigWindow::Width(monitor.width as u32),
+                    x::ConfigWindow::Height(monitor.height as u32),
+                    x::ConfigWindow::BorderWidth(0),
+                ],
+            });
+            self.bring_window_to_front(window);
+        }
+        else {
+            self.conn.send_request_checked(&x::ConfigureWindow {
+                window,
+                value_list: &[
+                    x::ConfigWindow::BorderWidth(border_width),
+                ],
+            });
+
+            if maximized == (false, false) {
+                let saved = win.saved_geometry.take().unwrap_or(current);
+                self.conn.send_request_checked(&x::ConfigureWindow {
+                    window,
+                    value_list: &[
+                        x::ConfigWindow::X(saved.x),
+                        x::ConfigWindow::Y(saved.y),
+                        x::ConfigWindow::Width(saved.width),
+                        x::ConfigWindow::Height(saved.height),
+                    ],
+                });
+            }
+            else {
+                self.set_maximized(window, maximized.0, maximized.1)?;
+            }
+
+            self.arrange();
+        }
+
+        self.set_frame_extents(window, if zoomed { 0 } else { border_width });
+        self.update_allowed_actions(window);
+
+        Ok(())
+    }
+
+    // maximize or restore each axis independently against the monitor
+    fn set_maximized(&mut self, window: x::Window, horz: bool, vert: bool) -> xcb::Result<()> {
+        let geometry = self.wait_for_reply(self.conn.send_request(&x::GetGeometry {
+            drawable: x::Drawable::Window(window),
+        }))?;
+
+        let (monitor, keep_aspect, border_width) = match self.windows.get(&window) {
+            Some(w) => (
+                self.work_area(w.monitor),
+                w.matched_rules.iter().any(|&i| self.config.rules[i].keep_aspect),
+                self.window_border(w) as i32,
+            ),
+            None => return Ok(()),
+        };
+
+        let win = match self.windows.get_mut(&window) {
+            Some(w) => w,
+            None => return Ok(()),
+        };
+
+        let current = Geometry {
+            x: geometry.x() as i32,
+            y: geometry.y() as i32,
+            width: geometry.width() as u32,
+            height: geometry.height() as u32,
+        };
+        let saved = win.saved_geometry.unwrap_or(current);
+
+        // the shape it asked for, or the one it had before, made as big as it'll go
+        let fitted = if keep_aspect && horz && vert {
+            Some(layout::fit_aspect(&monitor, win.aspect.unwrap_or((saved.width, saved.height)), border_width))
+        }
+        else {
+            None
+        };
+
+        // maximized axes fill the monitor, ones coming out of maximize go back to where they
+        // were, and the rest stay put
+        let (x, width) = if let Some(f) = fitted {
+            (f.x, f.width)
+        } else if horz {
+            (monitor.x, (monitor.width - 2*border_width) as u32)
+        } else if win.maximized_horz {
+            (saved.x, saved.width)
+        } else {
+            (current.x, current.width)
+        };
+        let (y, height) = if let Some(f) = fitted {
+            (f.y, f.height)
+        } else if vert {
+            (monitor.y, (monitor.height - 2*border_width) as u32)
+        } else if win.maximized_vert {
+            (saved.y, saved.height)
+        } else {
+            (current.y, current.height)
+        };
+
+        // keep the restore point for maximized axes, and track the others as they are now
+        win.saved_geometry = if horz || vert {
+            Some(Geometry {
+                x: if horz { saved.x } else { x },
+                y: if vert { saved.y } else { y },
+                width: if horz { saved.width } else { width },
+                height: if vert { saved.height } else { height },
+            })
+        }
+        else {
+            None
+        };
+
+        win.maximized_horz = horz;
+        win.maximized_vert = vert;
+
+        debug!("{:?} maximized horz {} vert {}, now {},{} {}x{}", window, horz, vert, x, y, width, height);
+
+        self.conn.send_request_checked(&x::ConfigureWindow {
+            window,
+            value_list: &[
+                x::ConfigWindow::X(x),
+                x::ConfigWindow::Y(y),
+                x::ConfigWindow::Width(width),
+                x::ConfigWindow::Height(height),
+            ],
+        });
+
+        self.update_net_wm_state(window);
+
+        Ok(())
+    }
+
+    // what we put around the window: left, right, top and bottom. with no frames that's the
+    // border, but clients like GTK use it to size shadows and place popovers
+    fn set_frame_extents(&self, window: x::Window, border_width: u32) {
+        self.conn.send_request_checked(&x::ChangeProperty {
+            mode: x::PropMode::Replace,
+            window,
+            property: self.atoms.net_frame_extents,
+            r#type: x::ATOM_CARDINAL,
+            data: &[border_width; 4],
+        });
+    }
+
+    // reflect our idea of the window's state back to it
+    fn update_net_wm_state(&self, window: x::Window) {
+        let win = match self.windows.get(&window) {
+            Some(w) => w,
+            None => return,
+        };
+
+        let mut state = vec![];
+        if win.fullscreen {
+            state.push(self.atoms.net_wm_state_fullscreen);
+        }
+        if win.maximized_horz {
+            state.push(self.atoms.net_wm_state_maximized_horz);
+        }
+        if win.maximized_vert {
+            state.push(self.atoms.net_wm_state_maximized_vert);
+        }
+        if win.demands_attention {
+            state.push(self.atoms.net_wm_state_demands_attention);
+        }
+        if win.sticky {
+            state.push(self.atoms.net_wm_state_sticky);
+        }
+        if win.above {
+            state.push(self.atoms.net_wm_state_above);
+        }
+        if win.below {
+            state.push(self.atoms.net_wm_state_below);
+        }
+        let data: Vec<u32> = state.iter()
+            .map(|a| a.resource_id())
+            .chain(win.other_states.iter().copied())
+            .collect();
+
+        self.conn.send_request_checked(&x::ChangeProperty {
+            mode: x::PropMode::Replace,
+            window,
+            property: self.atoms.net_wm_state,
+            r#type: x::ATOM_ATOM,
+            data: &data,
+        });
+    }
+
+    // what pagers and title bars can offer for the window. splash screens go away by
+    // themselves, fullscreen and zoomed windows are stuck where they are until they come out of
+    // it, and one that can only be one size can't be resized or maximized
+    fn update_allowed_actions(&self, window: x::Window) {
+        let win = match self.windows.get(&window) {
+            Some(w) => w,
+            None => return,
+        };
+
+        let mut actions = vec![self.atoms.net_wm_action_close];
+        if win.window_type != WindowType::Splash {
+            actions.extend([
+                self.atoms.net_wm_action_minimize,
+                self.atoms.net_wm_action_stick,
+                self.atoms.net_wm_action_change_desktop,
+                self.atoms.net_wm_action_fullscreen,
+                self.atoms.net_wm_action_above,
+                self.atoms.net_wm_action_below,
+            ]);
+
+            if !win.fullscreen && !win.zoomed {
+                actions.push(self.atoms.net_wm_action_move);
+                if !win.fixed_size {
+                    actions.extend([
+                        self.atoms.net_wm_action_resize,
+                        self.atoms.net_wm_action_maximize_horz,
+                        self.atoms.net_wm_action_maximize_vert,
+                    ]);
+                }
+            }
+        }
+
+        self.conn.send_request_checked(&x::ChangeProperty {
+            mode: x::PropMode::Replace,
+            window,
+            property: self.atoms.net_wm_allowed_actions,
+            r#type: x::ATOM_ATOM,
+            data: &actions,
+        });
+    }
+
+    // _NET_WM_STATE client message: data is action, first property, second property
+    fn handle_net_wm_state(&mut self, window: x::Window, data: [u32; 5]) -> xcb::Result<()> {
+        self.change_net_wm_state(window, data[0], &data[1..3])
+    }
+
+    // add, remove or toggle states, all at once so maximizing both ways is one change
+    fn change_net_wm_state(&mut self, window: x::Window, action: u32, props: &[u32]) -> xcb::Result<()> {
+        let (horz, vert, fullscreen, attention, sticky, above, below) = match self.windows.get(&window) {
+            Some(w) => (w.maximized_horz, w.maximized_vert, w.fullscreen, w.demands_attention, w.sticky, w.above, w.below),
+            None => return Ok(()),
+        };
+
+        let mut new_horz = horz;
+        let mut new_vert = vert;
+        let mut new_fullscreen = fullscreen;
+        let mut new_attention = attention;
+        let mut new_sticky = sticky;
+        let mut new_above = above;
+        let mut new_below = below;
+        let mut others_changed = false;
+
+        for &prop in props.iter().filter(|&&p| p != 0) {
+            if prop == self.atoms.net_wm_state_fullscreen.resource_id() {
+                new_fullscreen = apply_state_action(action, fullscreen);
+            }
+            else if prop == self.atoms.net_wm_state_maximized_horz.resource_id() {
+                new_horz = apply_state_action(action, horz);
+            }
+            else if prop == self.atoms.net_wm_state_maximized_vert.resource_id() {
+                new_vert = apply_state_action(action, vert);
+            }
+            else if prop == self.atoms.net_wm_state_demands_attention.resource_id() {
+                new_attention = apply_state_action(action, attention);
+            }
+            else if prop == self.atoms.net_wm_state_sticky.resource_id() {
+                new_sticky = apply_state_action(action, sticky);
+            }
+            else if prop == self.atoms.net_wm_state_above.resource_id() {
+                new_above = apply_state_action(action, above);
+            }
+            else if prop == self.atoms.net_wm_state_below.resource_id() {
+                new_below = apply_state_action(action, below);
+            }
+            // not one of ours, but it's kept so it isn't lost when we write the ones that are
+            else if let Some(w) = self.windows.get_mut(&window) {
+                debug!("unsupported _NET_WM_STATE property {:?} on {:?}", prop, window);
+
+                let had = w.other_states.contains(&prop);
+                if apply_state_action(action, had) != had {
+                    if had {
+                        w.other_states.retain(|&a| a != prop);
+                    }
+                    else {
+                        w.other_states.push(prop);
+                    }
+                    others_changed = true;
+                }
+            }
+        }
+
+        if new_horz != horz || new_vert != vert {
+            self.set_maximized(window, new_horz, new_vert)?;
+        }
+        if new_fullscreen != fullscreen {
+            self.set_fullscreen(window, new_fullscreen)?;
+        }
+        if new_attention != attention {
+            self.set_demands_attention(window, new_attention);
+        }
+        if new_sticky != sticky {
+            self.set_sticky(window, new_sticky);
+        }
+        if new_above != above || new_below != below {
+            // it can't be both, so the one it's just asked for wins
+            let (new_above, new_below) = match (new_above, new_below) {
+                (true, true) if !above => (true, false),
+                (true, true) => (false, true),
+                layer => layer,
+            };
+            self.set_layer(window, new_above, new_below);
+        }
+        if others_changed {
+            self.update_net_wm_state(window);
+        }
+
+        Ok(())
+    }
+
+    // shown whichever workspace its monitor is on, or back to just its own
+    fn set_sticky(&mut self, window: x::Window, sticky: bool) {
+        let win = match self.windows.get_mut(&window) {
+            Some(w) => w,
+            None => return,
+        };
+
+        win.sticky = sticky;
+        debug!("{:?} sticky {}", window, sticky);
+
+        // leaving it on the workspace it's on now, not the one it came from
+        if !sticky {
+            win.workspace = self.current_workspaces[win.monitor];
+        }
+
+        self.show_workspaces();
+        self.update_net_wm_state(window);
+    }
+
+    // over or under everything else, or neither
+    fn set_layer(&mut self, window: x::Window, above: bool, below: bool) {
+        let win = match self.windows.get_mut(&window) {
+            Some(w) => w,
+            None => return,
+        };
+
+        win.above = above;
+        win.below = below;
+        debug!("{:?} above {} below {}", window, above, below);
+
+        if below {
+            self.send_window_to_back(window);
+        }
+        else {
+            self.bring_window_to_front(window);
+        }
+        self.update_net_wm_state(window);
+    }
+
+    fn set_demands_attention(&mut self, window: x::Window, attention: bool) {
+        // it already has the user's attention
+        let attention = attention && self.focused != Some(window);
+
+        let win = match self.windows.get_mut(&window) {
+            Some(w) if w.demands_attention != attention => w,
+            _ => return,
+        };
+
+        win.demands_attention = attention;
+        let elsewhere = win.hidden;
+        let obscured = win.obscured;
+
+        debug!("{:?} {} attention{}", window, if attention { "demands" } else { "no longer demands" },
+            if elsewhere { " on another workspace" } else { "" });
+
+        // nobody would see it; on_visibility_notify catches up when they can
+        if !obscured {
+            self.apply_border(window);
+        }
+        self.update_net_wm_state(window);
+    }
+
+    // some clients change their state or urgency hint directly instead of asking
+    fn handle_property_change(&mut self, window: x::Window, property: x::Atom) -> xcb::Result<()> {
+        if property == x::ATOM_WM_NAME || property == self.atoms.net_wm_name {
+            let title = self.get_title(window)?;
+            if let Some(w) = self.windows.get_mut(&window) {
+                w.title = title;
+            }
+            return self.apply_title_rules(window);
+        }
+
+        if property == x::ATOM_WM_NORMAL_HINTS {
+            let gravity = self.get_gravity(window)?;
+            let min_size = self.get_min_size(window)?;
+            let fixed_size = self.get_fixed_size(window)?;
+            if let Some(w) = self.windows.get_mut(&window) {
+                w.gravity = gravity;
+                w.min_size = min_size;
+                if std::mem::replace(&mut w.fixed_size, fixed_size) != fixed_size && w.mapped {
+                    self.update_allowed_actions(window);
+                }
+            }
+            return Ok(());
+        }
+
+        // the client, or something else, changed it; ours comes back through here too, and is
+        // the one thing that matches what we last showed. anyone else's is the window's own
+        // opacity now, which might need dimming again
+        if property == self.atoms.net_wm_window_opacity {
+            let opacity = self.get_opacity(window)?.unwrap_or(u32::MAX);
+            if let Some(w) = self.windows.get_mut(&window) {
+                if opacity == w.shown_opacity {
+                    return Ok(());
+                }
+                w.opacity = opacity;
+                w.shown_opacity = opacity;
+            }
+            self.show_opacity(window);
+            self.flush()?;
+            return Ok(());
+        }
+
+        let attention = if property == x::ATOM_WM_HINTS {
+            let cookie = self.conn.send_request(&x::GetProperty {
+                delete: false,
+                window,
+                property,
+                r#type: x::ATOM_WM_HINTS,
+                long_offset: 0,
+                long_length: 1,
+            });
+
+            // first word is the flags, UrgencyHint is bit 8
+            match self.wait_for_reply(cookie) {
+                Ok(r) if r.format() == 32 => r.value::<u32>().first().is_some_and(|f| f & (1 << 8) != 0),
+                Ok(_) => false,
+                Err(xcb::Error::Protocol(e)) => {
+                    debug!("couldn't get WM_HINTS for {:?}: {:?}", window, e);
+                    return Ok(());
+                },
+                Err(e) => return Err(e),
+            }
+        }
+        else if property == self.atoms.net_wm_state {
+            let cookie = self.conn.send_request(&x::GetProperty {
+                delete: false,
+                window,
+                property,
+                r#type: x::ATOM_ATOM,
+                long_offset: 0,
+                long_length: 32,
+            });
+
+            match self.wait_for_reply(cookie) {
+                Ok(r) if r.format() == 32 => r.value::<x::Atom>().contains(&self.atoms.net_wm_state_demands_attention),
+                Ok(_) => false,
+                Err(xcb::Error::Protocol(e)) => {
+                    debug!("couldn't get _NET_WM_STATE for {:?}: {:?}", window, e);
+                    return Ok(());
+                },
+                Err(e) => return Err(e),
+            }
+        }
+        else {
+            return Ok(());
+        };
+
+        self.set_demands_attention(window, attention);
+
+        Ok(())
+    }
+
+    // cover the whole monitor with no border, or put it back how it was
+    fn set_fullscreen(&mut self, window: x::Window, fullscreen: bool) -> xcb::Result<()> {
+        let geometry = self.wait_for_reply(self.conn.send_request(&x::GetGeometry {
+            drawable: x::Drawable::Window(window),
+        }))?;
+
+        let win = match self.windows.get_mut(&window) {
+            Some(w) => w,
+            None => return Ok(()),
+        };
+
+        let current = Geometry {
+            x: geometry.x() as i32,
+            y: geometry.y() as i32,
+            width: geometry.width() as u32,
+            height: geometry.height() as u32,
+        };
+
+        let (new, border_width) = if fullscreen {
+            let monitor = self.monitors[win.monitor];
+            win.fullscreen_geometry = Some(current);
+            (Geometry {
+                x: monitor.x,
+                y: monitor.y,
+                width: monitor.width as u32,
+                height: monitor.height as u32,
+            }, 0)
+        } else {
+            let border_width = if win.zoomed || win.lone { 0 } else { self.config.border_width };
+            (win.fullscreen_geometry.take().unwrap_or(current), border_width)
+        };
+
+        win.fullscreen = fullscreen;
+
+        debug!("{:?} fullscreen {}, now {:?}", window, fullscreen, new);
+
+        self.conn.send_request_checked(&x::ConfigureWindow {
+            window,
+            value_list: &[
+                x::ConfigWindow::X(new.x),
+                x::ConfigWindow::Y(new.y),
+                x::ConfigWindow::Width(new.width),
+                x::ConfigWindow::Height(new.height),
+                x::ConfigWindow::BorderWidth(border_width),
+            ],
+        });
+
+        if fullscreen {
+            self.bring_window_to_front(window);
+        }
+
+        self.set_frame_extents(window, border_width);
+        self.update_net_wm_state(window);
+        self.update_allowed_actions(window);
+        self.update_fullscreen_active();
+
+        Ok(())
+    }
+
+    // fullscreen things (mostly games) get the pointer to themselves while they have focus
+    fn update_fullscreen_active(&mut self) {
+        let active = self.focused
+            .and_then(|f| self.windows.get(&f))
+            .map(|w| w.fullscreen && w.mapped && !w.hidden)
+            .unwrap_or(false);
+
+        if active == self.fullscreen_active {
+            return;
+        }
+
+        debug!("fullscreen active {}, focus follows mouse {}", active, !active);
+
+        self.fullscreen_active = active;
+
+        if self.config.fullscreen_release_click {
+            self.set_click_grab(!active);
+        }
+    }
+
+    // plain left click on a window raises it, unless we're staying out of the way
+    fn set_click_grab(&self, grab: bool) {
+        if grab {
+            self.conn.send_request_checked(&x::GrabButton {
+                owner_events: false,
+                grab_window: self.screen.root(),
+                event_mask: x::EventMask::BUTTON_PRESS,
+                pointer_mode: x::GrabMode::Sync,
+                keyboard_mode: x::GrabMode::Async,
+                confine_to: self.screen.root(),
+                cursor: x::CURSOR_NONE,
+                button: x::ButtonIndex::N1,
+                modifiers: x::ModMask::empty(),
+            });
+        }
+        else {
+            self.conn.send_request_checked(&x::UngrabButton {
+                button: x::ButtonIndex::N1,
+                grab_window: self.screen.root(),
+                modifiers: x::ModMask::empty(),
+            });
+        }
+    }
+
+    // after a screen change, pull back any window that no longer has its centre on a monitor
+    fn bring_windows_into_view(&mut self) -> xcb::Result<()> {
+        // send everything first, then collect; hidden ones too, they'll be shown again later
+        let cookies: Vec<_> = self.windows.values().filter(|w| w.mapped).map(|w| (
+            w.x_window,
+            self.conn.send_request(&x::GetGeometry {
+                drawable: x::Drawable::Window(w.x_window),
+            }),
+        )).collect();
+
+        for (window, cookie) in cookies {
+            let geometry = match self.wait_for_reply(cookie) {
+                Ok(geometry) => geometry,
+                Err(e) => {
+                    debug!("couldn't get geometry for {:?}, not moving it: {:?}", window, e);
+                    continue;
+                },
+            };
+
+            let win_x = geometry.x() as i32;
+            let win_y = geometry.y() as i32;
+            let win_width = geometry.width() as i32 + 2*geometry.border_width() as i32;
+            let win_height = geometry.height() as i32 + 2*geometry.border_width() as i32;
+
+            let centre_x = win_x + win_width/2;
+            let centre_y = win_y + win_height/2;
+
+            if self.monitors.iter().any(|m| m.contains(centre_x, centre_y)) {
+                continue;
+            }
+
+            let monitor_idx = self.monitor_at(centre_x, centre_y);
+            let monitor = self.work_area(monitor_idx);
+            let (new_x, new_y) = monitor.clamp(win_x, win_y, win_width, win_height);
+
+            debug!("{:?} is off screen, moving to {},{}", window, new_x, new_y);
+
+            self.conn.send_request_checked(&x::ConfigureWindow {
+                window,
+                value_list: &[
+                    x::ConfigWindow::X(new_x),
+                    x::ConfigWindow::Y(new_y),
+                ],
+            });
+
+            self.set_window_monitor(window, monitor_idx);
+        }
+
+        // moved windows may now be on a workspace that isn't showing
+        self.show_workspaces();
+
+        Ok(())
+    }
+
+    pub fn attach_existing_windows(&mut self) -> xcb::Result<()> {
+        self.windows.clear();
+
+        // if we're starting again, show what was showing
+        let layout = session::take_layout();
+        for (current, &saved) in self.current_workspaces.iter_mut().zip(&layout.workspaces) {
+            if saved < self.config.workspace_count {
+                *current = saved;
+            }
+        }
+        if let Some(tags) = layout.tags {
+            self.selected_tags = tags;
+        }
+        self.master_widths = layout.master_widths.into_iter()
+            .filter(|&(workspace, _)| workspace < self.config.workspace_count)
+            .map(|(workspace, width)| (workspace, width.clamp(layout::MASTER_MIN, layout::MASTER_MAX)))
+            .collect();
+        self.tabbed = layout.tabbed.into_iter()
+            .filter(|&workspace| workspace < self.config.workspace_count)
+            .collect();
+
+        let tree = self.wait_for_reply(self.conn.send_request(&x::QueryTree {
+            window: self.screen.root(),
+        }))?;
+
+        // ask about all of them before waiting for any answers, so there's one round trip
+        // rather than two per window. our own are left out from the start
+        let cookies: Vec<_> = tree.children().iter().filter(|w| !self.own_windows.contains(w)).map(|&w| (
+            w,
+            self.conn.send_request(&x::GetWindowAttributes {
+                window: w,
+            }),
+            self.conn.send_request(&x::GetGeometry {
+                drawable: x::Drawable::Window(w),
+            }),
+        )).collect();
+
+        // windows can go away at any point while we look at them, which shows up as a protocol
+        // error. that only means skipping that window, the rest still get adopted
+        // both are waited for either way, so neither reply is left behind in xcb
+        for (w, attrs_cookie, geometry_cookie) in cookies {
+            let (attrs, geometry, border_width) = match (self.wait_for_reply(attrs_cookie), self.wait_for_reply(geometry_cookie)) {
+                (Ok(attrs), Ok(g)) => (attrs, Geometry {
+                    x: g.x() as i32,
+                    y: g.y() as i32,
+                    width: g.width() as u32,
+                    height: g.height() as u32,
+                }, g.border_width() as u32),
+                (Err(xcb::Error::Connection(e)), _) | (_, Err(xcb::Error::Connection(e))) => {
+                    return Err(xcb::Error::Connection(e));
+                },
+                (Err(e), _) | (_, Err(e)) => {
+                    debug!("couldn't look at {:?}, skipping: {:?}", w, e);
+                    continue;
+                },
+            };
+
+            debug!("existing window {:?}, attrs {:?}", w, attrs);
+
+            // not ours to manage, and nothing to show
+            if attrs.override_redirect() || attrs.class() == x::WindowClass::InputOnly {
+                continue;
+            }
+            if self.is_ignored(w)? {
+                debug!("existing window {:?} is ignored", w);
+                continue;
+            }
+
+            // track it even if it's not showing, so we know about it when it's mapped
+            let mut window = Window::new(w);
+            window.geometry = Some(geometry);
+            self.windows.insert(w, window);
+            self.restacked(w, x::Place::OnTop);
+
+            // unmapped ones with a WM_STATE other than withdrawn were hidden by whoever was
+            // managing them before, and want to come back
+            let state = self.get_wm_state(w)?;
+            if attrs.map_state() == x::MapState::Unmapped && !matches!(state, Some(NORMAL_STATE | ICONIC_STATE)) {
+                continue;
+            }
+
+            // _NET_WM_DESKTOP is the client's own, so it wins over the file
+            let saved = layout.windows.iter().find(|s| s.id == w.resource_id());
+            let desktop = self.get_property(w, self.atoms.net_wm_desktop, x::ATOM_CARDINAL, 1)?
+                .filter(|r| r.format() == 32)
+                .and_then(|r| r.value::<u32>().first().copied())
+                .map(|d| d as usize);
+            let restored = Restored {
+                workspace: desktop.or(saved.map(|s| s.workspace)).filter(|&d| d < self.config.workspace_count),
+                floating: saved.map(|s| s.floating),
+                // we leave minimized windows mapped and withdrawn when we stop, so the file is
+                // the only place that still knows
+                iconic: state == Some(ICONIC_STATE) || saved.is_some_and(|s| s.iconic),
+                tags: saved.and_then(|s| s.tags),
+                geometry: Some((geometry, border_width)),
+            };
+            self.restoring.insert(w, restored);
+
+            if geometry.width == 0 || geometry.height == 0 {
+                warn!("existing window {:?} has no size, not adopting it", w);
+                continue;
+            }
+
+            debug!("mapping existing window {:?}", w);
+
+            // already up, so leave the stacking and focus how we found them
+            match self.map_window(w, false) {
+                Ok(()) => {},
+                Err(xcb::Error::Protocol(e)) => {
+                    warn!("couldn't adopt existing window {:?}: {:?}", w, e);
+                    self.forget_window(w);
+                },
+                Err(e) => return Err(e),
+            }
+
+            // showing, but it belongs somewhere that isn't
+            let viewable = attrs.map_state() != x::MapState::Unmapped;
+            if let Some(win) = self.windows.get_mut(&w).filter(|win| viewable && win.hidden) {
+                win.ignore_unmaps += 1;
+                self.conn.send_request_checked(&x::UnmapWindow {
+                    window: w,
+                });
+            }
+        }
+        self.restoring.clear();
+
+        // tile in the same order as before
+        let order = |w: &x::Window| layout.windows.iter().position(|s| s.id == w.resource_id()).unwrap_or(usize::MAX);
+        self.layout_order.sort_by_key(order);
+        self.arrange();
+
+        self.flush()?;
+
+        Ok(())
+    }
+
+    // wait for the next X event, dealing with any signals that arrive in the meantime
+    fn next_event(&mut self) -> xcb::Result<xcb::Event> {
+        loop {
+            if let Some(event) = self.conn.poll_for_event()? {
+                return Ok(event);
+            }
+
+            self.flush()?;
+
+            let mut fds = vec![libc::pollfd {
+                fd: self.conn.as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            }];
+            if let Some(fd) = self.signal_fd {
+                fds.push(libc::pollfd {
+                    fd,
+                    events: libc::POLLIN,
+                    revents: 0,
+                });
+            }
+            for fd in self.ipc.iter().flat_map(|ipc| ipc.fds()) {
+                fds.push(libc::pollfd {
+                    fd,
+                    events: libc::POLLIN,
+                    revents: 0,
+                });
+            }
+
+            // wake up in time for the next timer, rounding up so it's due when we do
+            let timeout = match self.timers.iter().map(|(due, _)| *due).min() {
+                Some(due) => {
+                    let wait = due.saturating_duration_since(Instant::now());
+                    wait.as_micros().div_ceil(1000).min(i32::MAX as u128) as i32
+                },
+                None => -1,
+            };
+
+            // interrupted or not, we go round again and look at what's waiting
+            unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, timeout) };
+
+            if let Some(fd) = self.signal_fd {
+                signals::drain(fd);
+                self.handle_signals()?;
+            }
+
+            self.handle_commands();
+            self.run_timers()?;
+        }
+    }
+
+    fn handle_commands(&mut self) {
+        let commands = match self.ipc.as_mut() {
+            Some(ipc) => ipc.read_commands(),
+            None => return,
+        };
+
+        for (client, command) in commands {
+            debug!("command: {:?}", command);
+
+            let response = match self.run_command(&command) {
+                Ok(lines) => std::iter::once("ok".to_string()).chain(lines).collect::<Vec<_>>().join("\n"),
+                Err(e) => format!("error {}", e),
+            };
+
+            if let Some(ipc) = self.ipc.as_mut() {
+                ipc.reply(client, &response);
+            }
+        }
+
+        if let Some(ipc) = self.ipc.as_mut() {
+            ipc.drop_closed();
+        }
+    }
+
+    // said either side of the change, so it's clear where a trace starts and stops, whatever the
+    // log level is otherwise
+    fn set_tracing(&self, on: bool) {
+        if on {
+            logging::set_tracing(true);
+        }
+        info!("event tracing {}", if on { "on" } else { "off" });
+        if !on {
+            logging::set_tracing(false);
+        }
+    }
+
+    // a command from the socket, answered with key=value lines or what went wrong
+    fn run_command(&mut self, command: &str) -> Result<Vec<String>, String> {
+        let mut words = command.split_whitespace();
+
+        match (words.next(), words.next()) {
+            (Some("query"), Some(id)) => self.query_window(id),
+            (Some("query"), None) => Err("query needs a window id".to_string()),
+            (Some("stats"), _) => Ok(self.stats.lines()),
+            (Some("dump"), _) => Ok(vec![self.dump_layout().to_string()]),
+            (Some("restore"), Some(_)) => {
+                let text = command.trim_start().strip_prefix("restore").unwrap_or_default();
+                self.restore_layout(&json::parse(text)?)
+            },
+            (Some("restore"), None) => Err("restore needs the JSON from dump".to_string()),
+            (Some("trace"), Some("on")) => {
+                self.set_tracing(true);
+                Ok(vec![])
+            },
+            (Some("trace"), Some("off")) => {
+                self.set_tracing(false);
+                Ok(vec![])
+            },
+            (Some("trace"), _) => Err("trace needs on or off".to_string()),
+            (Some(name), _) => Err(format!("unknown command {:?}", name)),
+            (None, _) => Err("empty command".to_string()),
+        }
+    }
+
+    // what we know about a window, by id in hex (0x...) or decimal. monitors and workspaces are
+    // numbered from 1, like in the config
+    fn query_window(&self, id: &str) -> Result<Vec<String>, String> {
+        let parsed = match id.strip_prefix("0x") {
+            Some(hex) => u32::from_str_radix(hex, 16),
+            None => id.parse(),
+        };
+        let window = match parsed {
+            Ok(n) => x::Window::new(n),
+            Err(_) => return Err(format!("bad window id {:?}", id)),
+        };
+
+        let w = match self.windows.get(&window) {
+            Some(w) => w,
+            None => return Err(format!("unknown window {}", id)),
+        };
+
+        // one line each, whatever the client put in them
+        let clean = |s: &Option<String>| s.as_deref().unwrap_or_default().replace(['\n', '\r'], " ");
+
+        let mut lines = vec![
+            format!("id=0x{:x}", window.resource_id()),
+            format!("class={}", clean(&w.class)),
+            format!("title={}", clean(&w.title)),
+        ];
+        if let Some(g) = w.geometry {
+            lines.push(format!("x={}", g.x));
+            lines.push(format!("y={}", g.y));
+            lines.push(format!("width={}", g.width));
+            lines.push(format!("height={}", g.height));
+        }
+        lines.extend([
+            format!("mapped={}", w.mapped && !w.hidden),
+            format!("focused={}", self.focused == Some(window)),
+            format!("floating={}", w.floating),
+            format!("fullscreen={}", w.fullscreen),
+            format!("monitor={}", w.monitor + 1),
+            format!("workspace={}", w.workspace + 1),
+        ]);
+
+        Ok(lines)
+    }
+
+    // the layout for the dump command. windows are bottom to top, and monitors and workspaces
+    // are numbered from 1 like query has them
+    fn dump_layout(&self) -> Value {
+        let id = |window: x::Window| Value::from(format!("{:#x}", window.resource_id()).as_str());
+        let number = |n: usize| Value::from(n as i64 + 1);
+
+        let monitors = self.monitors.iter()
+            .map(|m| Value::Object(vec![
+                ("x".to_string(), (m.x as i64).into()),
+                ("y".to_string(), (m.y as i64).into()),
+                ("width".to_string(), (m.width as i64).into()),
+                ("height".to_string(), (m.height as i64).into()),
+            ]))
+            .collect();
+
+        let windows = self.stacking.iter()
+            .filter_map(|w| self.windows.get(w))
+            .filter(|w| w.mapped)
+            .map(|w| {
+                let mut fields = vec![
+                    ("id".to_string(), id(w.x_window)),
+                    ("class".to_string(), w.class.as_deref().into()),
+                    ("title".to_string(), w.title.as_deref().into()),
+                ];
+                if let Some(g) = w.geometry {
+                    fields.extend([
+                        ("x".to_string(), (g.x as i64).into()),
+                        ("y".to_string(), (g.y as i64).into()),
+                        ("width".to_string(), (g.width as i64).into()),
+                        ("height".to_string(), (g.height as i64).into()),
+                    ]);
+                }
+                fields.extend([
+                    ("monitor".to_string(), number(w.monitor)),
+                    ("workspace".to_string(), number(w.workspace)),
+                    ("tags".to_string(), (w.tags as i64).into()),
+                    ("floating".to_string(), w.floating.into()),
+                    ("minimized".to_string(), w.iconic.into()),
+                    ("fullscreen".to_string(), w.fullscreen.into()),
+                    ("maximized_horz".to_string(), w.maximized_horz.into()),
+                    ("maximized_vert".to_string(), w.maximized_vert.into()),
+                    ("zoomed".to_string(), w.zoomed.into()),
+                    ("sticky".to_string(), w.sticky.into()),
+                ]);
+                Value::Object(fields)
+            })
+            .collect();
+
+        Value::Object(vec![
+            ("monitors".to_string(), Value::Array(monitors)),
+            ("workspaces".to_string(), Value::Array(self.current_workspaces.iter().map(|&w| number(w)).collect())),
+            ("focused".to_string(), self.focused.map(id).unwrap_or(Value::Null)),
+            ("windows".to_string(), Value::Array(windows)),
+        ])
+    }
+
+    // a dump put back, as far as it can be: the workspace showing on each monitor, and the
+    // monitor, workspace, floating and geometry of windows that are still here with the same
+    // id. windows that have gone are skipped, and anything else in the dump is just for reading
+    fn restore_layout(&mut self, dump: &Value) -> Result<Vec<String>, String> {
+        let saved_windows = dump.get("windows").and_then(Value::as_array).ok_or("no windows in the dump")?;
+
+        // numbered from 1, and has to be one we have
+        let index = |value: Option<&Value>, count: usize| {
+            value.and_then(Value::as_i64).filter(|&n| n >= 1 && n as usize <= count).map(|n| n as usize - 1)
+        };
+
+        let (mut restored, mut skipped) = (0, 0);
+        for saved in saved_windows {
+            let window = saved.get("id").and_then(Value::as_str)
+                .and_then(|id| u32::from_str_radix(id.strip_prefix("0x")?, 16).ok())
+                .map(x::Window::new)
+                .filter(|w| self.windows.get(w).is_some_and(|w| w.mapped));
+            let window = match window {
+                Some(w) => w,
+                None => {
+                    skipped += 1;
+                    continue;
+                },
+            };
+
+            if let Some(monitor) = index(saved.get("monitor"), self.monitors.len()) {
+                self.set_window_monitor(window, monitor);
+            }
+
+            let workspace = index(saved.get("workspace"), self.config.workspace_count);
+            let floating = saved.get("floating").and_then(Value::as_bool);
+            let Some(w) = self.windows.get_mut(&window) else {
+                continue;
+            };
+            if let Some(workspace) = workspace {
+                w.workspace = workspace;
+            }
+            if let Some(floating) = floating {
+                w.floating = floating;
+            }
+
+            // tiles get laid out again anyway, and these have a geometry of their own
+            let placed = w.fullscreen || w.zoomed || w.maximized_horz || w.maximized_vert;
+            let geometry = ["x", "y", "width", "height"].map(|k| saved.get(k).and_then(Value::as_i64));
+            if !placed {
+                if let [Some(x), Some(y), Some(width), Some(height)] = geometry {
+                    let geometry = Geometry {
+                        x: x.clamp(i16::MIN as i64, i16::MAX as i64) as i32,
+                        y: y.clamp(i16::MIN as i64, i16::MAX as i64) as i32,
+                        width: width.clamp(1, u16::MAX as i64) as u32,
+                        height: height.clamp(1, u16::MAX as i64) as u32,
+                    };
+                    let border_width = self.window_border(&self.windows[&window]);
+                    self.conn.send_request_checked(&x::ConfigureWindow {
+                        window,
+                        value_list: &[
+                            x::ConfigWindow::X(geometry.x),
+                            x::ConfigWindow::Y(geometry.y),
+                            x::ConfigWindow::Width(geometry.width),
+                            x::ConfigWindow::Height(geometry.height),
+                        ],
+                    });
+                    self.send_configure_notify(window, geometry, border_width);
+                }
+            }
+
+            restored += 1;
+        }
+
+        let saved_workspaces = dump.get("workspaces").and_then(Value::as_array).unwrap_or_default();
+        for (monitor, saved) in saved_workspaces.iter().enumerate().take(self.monitors.len()) {
+            if let Some(workspace) = index(Some(saved), self.config.workspace_count) {
+                self.current_workspaces[monitor] = workspace;
+            }
+        }
+        if !self.config.per_monitor_workspaces {
+            let current = self.current_workspaces[0];
+            self.current_workspaces.iter_mut().for_each(|c| *c = current);
+        }
+
+        debug!("restored {} windows from a dump, skipped {}", restored, skipped);
+
+        self.show_workspaces();
+        let result = match self.focused.and_then(|f| self.windows.get(&f)) {
+            Some(w) if self.is_visible(w) => self.flush(),
+            _ => self.focus_top_window().and_then(|_| self.flush()),
+        };
+        result.map_err(|e| format!("couldn't restore: {}", e))?;
+
+        Ok(vec![
+            format!("restored={}", restored),
+            format!("skipped={}", skipped),
+        ])
+    }
+
+    // replaces any pending timer of the same kind
+    fn set_timer(&mut self, delay: Duration, timer: Timer) {
+        self.cancel_timer(timer);
+        self.timers.push((Instant::now() + delay, timer));
+    }
+
+    fn cancel_timer(&mut self, timer: Timer) {
+        let kind = std::mem::discriminant(&timer);
+        self.timers.retain(|(_, t)| std::mem::discriminant(t) != kind);
+    }
+
+    fn run_timers(&mut self) -> xcb::Result<()> {
+        let now = Instant::now();
+        let (due, pending) = self.timers.drain(..).partition(|(at, _)| *at <= now);
+        self.timers = pending;
+
+        for (_, timer) in due {
+            debug!("timer fired: {:?}", timer);
+
+            match timer {
+                Timer::AutoRaise(window) => {
+                    if self.focused == Some(window) {
+                        self.bring_window_to_front(window);
+                    }
+                },
+                Timer::StaleDrag => self.check_drag()?,
+            }
+        }
+
+        self.flush()?;
+
+        Ok(())
+    }
+
+    fn update_status(&mut self) {
+        if self.status.is_none() {
+            return;
+        }
+
+        let focused = self.focused.and_then(|f| self.windows.get(&f));
+        let monitor = focused.map_or(0, |w| w.monitor);
+        let title = focused.and_then(|w| w.title.clone()).unwrap_or_default();
+
+        // in shared mode every monitor has the same workspaces, so look at all of them
+        let per_monitor = self.config.per_monitor_workspaces;
+        let on_monitor = |w: &&Window| {
+            w.mapped && (!per_monitor || w.monitor == monitor) && self.scratchpad != Some(w.x_window)
+        };
+
+        let workspaces: Vec<_> = (0..self.config.workspace_count).map(|n| {
+            let mut windows = self.windows.values().filter(on_monitor).filter(|w| w.workspace == n);
+
+            if n == self.current_workspaces[monitor] {
+                format!("[{}]", n+1)
+            }
+            else if windows.clone().any(|w| w.demands_attention) {
+                format!("{}!", n+1)
+            }
+            else if windows.next().is_some() {
+                format!("{}+", n+1)
+            }
+            else {
+                format!("{}", n+1)
+            }
+        }).collect();
+
+        let line = format!("{}\t{}", workspaces.join(" "), title.replace(['\t', '\n'], " "));
+
+        if let Some(status) = self.status.as_mut() {
+            status.update(line);
+        }
+    }
+
+    // window isn't ours anymore
+    fn forget_window(&mut self, window: x::Window) {
+        self.stacking.retain(|&w| w != window);
+        self.minimized.retain(|&w| w != window);
+        self.layout_order.retain(|&w| w != window);
+        self.focus_history.retain(|&w| w != window);
+
+        if self.marked == Some(window) {
+            self.marked = None;
+        }
+
+        if self.windows.remove(&window).is_none() {
+            return;
+        }
+
+        self.save_remembered();
+
+        if self.scratchpad == Some(window) {
+            self.scratchpad = None;
+        }
+
+        if self.focused == Some(window) {
+            self.focused = None;
+            self.update_fullscreen_active();
+        }
+
+        self.arrange();
+    }
+
+    fn handle_signals(&mut self) -> xcb::Result<()> {
+        if signals::take(libc::SIGHUP) {
+            info!("SIGHUP received, reloading config");
+            self.reload_config()?;
+        }
+
+        if signals::take(libc::SIGUSR1) {
+            info!("stats: {}", self.stats.lines().join(" "));
+        }
+
+        if signals::take(libc::SIGUSR2) {
+            self.set_tracing(!logging::tracing());
+        }
+
+        for (signal, name) in [(libc::SIGTERM, "SIGTERM"), (libc::SIGINT, "SIGINT")] {
+            if signals::take(signal) {
+                info!("{} received, quitting", name);
+                self.quitting = true;
+            }
+        }
+
+        Ok(())
+    }
+
+    // every round trip goes through here, so they can be counted
+    fn wait_for_reply<C: xcb::CookieWithReplyChecked>(&self, cookie: C) -> xcb::Result<C::Reply> {
+        self.stats.round_trips.set(self.stats.round_trips.get() + 1);
+        self.conn.wait_for_reply(cookie)
+    }
+
+    fn flush(&self) -> xcb::Result<()> {
+        self.stats.flushes.set(self.stats.flushes.get() + 1);
+        self.conn.flush()?;
+        Ok(())
+    }
+
+    pub fn run(&mut self) -> xcb::Result<()> {
+        while !self.quitting {
+            let event = self.next_event()?;
+            self.stats.events += 1;
+            self.handle_event(event)?;
+
+            // tell the bar if anything it shows has changed
+            self.update_status();
+        }
+
+        info!("quitting");
+        self.save_remembered();
+        session::save_layout(&self.layout());
+        self.release_windows()?;
+
+        Ok(())
+    }
+
+    // leave things as though no window manager had been running, for whatever comes next, as
+    // ICCCM asks: every window mapped, including ones on other workspaces or minimized, with
+    // no border and its contents where they were, withdrawn as far as WM_STATE goes and with
+    // nothing selected on it. the root loses the hints that said we were here
+    fn release_windows(&mut self) -> xcb::Result<()> {
+        let managed: Vec<_> = self.windows.values()
+            .filter(|w| w.mapped)
+            .map(|w| {
+                let border_width = if w.fullscreen || w.zoomed { 0 } else { self.window_border(w) };
+                (w.x_window, w.hidden || w.iconic, w.geometry, border_width)
+            })
+            .collect();
+
+        for (window, hidden, geometry, border_width) in managed {
+            debug!("releasing {:?}", window);
+
+            self.conn.send_request_checked(&x::ChangeWindowAttributes {
+                window,
+                value_list: &[
+                    x::Cw::EventMask(x::EventMask::empty()),
+                ],
+            });
+
+            match geometry.map(|g| g.resized(border_width, g.width, g.height, 0, x::Gravity::Static)) {
+                Some(g) => self.conn.send_request_checked(&x::ConfigureWindow {
+                    window,
+                    value_list: &[
+                        x::ConfigWindow::X(g.x),
+                        x::ConfigWindow::Y(g.y),
+                        x::ConfigWindow::BorderWidth(0),
+                    ],
+                }),
+                None => self.conn.send_request_checked(&x::ConfigureWindow {
+                    window,
+                    value_list: &[
+                        x::ConfigWindow::BorderWidth(0),
+                    ],
+                }),
+            };
+
+            if hidden {
+                self.conn.send_request_checked(&x::MapWindow {
+                    window,
+                });
+            }
+
+            self.set_wm_state(window, WITHDRAWN_STATE);
+            let properties = [
+                self.atoms.net_wm_desktop,
+                self.atoms.net_wm_state,
+                self.atoms.net_wm_allowed_actions,
+                self.atoms.net_frame_extents,
+            ];
+            for property in properties {
+                self.conn.send_request_checked(&x::DeleteProperty {
+                    window,
+                    property,
+                });
+            }
+        }
+
+        // we don't set the client list or active window ourselves, but one left over from
+        // before us would be wrong by now too
+        let root = self.screen.root();
+        for property in [self.atoms.net_supported, self.atoms.net_supporting_wm_check, self.atoms.net_client_list,
+            self.atoms.net_active_window, self.atoms.panko_workspace_switch] {
+            self.conn.send_request_checked(&x::DeleteProperty {
+                window: root,
+                property,
+            });
+        }
+        for window in self.own_windows.drain() {
+            self.conn.send_request_checked(&x::DestroyWindow {
+                window,
+            });
+        }
+
+        self.conn.send_request_checked(&x::SetInputFocus {
+            revert_to: x::InputFocus::PointerRoot,
+            focus: x::INPUTFOCUS_POINTER_ROOT,
+            time: x::CURRENT_TIME,
+        });
+
+        self.flush()
+    }
+
+    fn handle_event(&mut self, event: xcb::Event) -> xcb::Result<()> {
+        trace!("event {:?}", event);
+
+        if let Some(time) = event_time(&event) {
+            self.last_time = time;
+        }
+
+        match event {
+            xcb::Event::X(x::Event::CreateNotify(ev)) => self.on_create_notify(&ev),
+            xcb::Event::X(x::Event::DestroyNotify(ev)) => self.on_destroy_notify(&ev),
+            xcb::Event::X(x::Event::ReparentNotify(ev)) => self.on_reparent_notify(&ev),
+            xcb::Event::X(x::Event::MapRequest(ev)) => self.on_map_request(&ev),
+            xcb::Event::X(x::Event::UnmapNotify(ev)) => self.on_unmap_notify(&ev),
+            xcb::Event::X(x::Event::KeyPress(ev)) => self.on_key_press(&ev),
+            xcb::Event::X(x::Event::KeyRelease(ev)) => self.on_key_release(&ev),
+            xcb::Event::X(x::Event::KeymapNotify(ev)) => self.on_keymap_notify(&ev),
+            xcb::Event::X(x::Event::ButtonPress(ev)) => self.on_button_press(&ev),
+            xcb::Event::X(x::Event::ButtonRelease(ev)) => self.on_button_release(&ev),
+            xcb::Event::X(x::Event::MotionNotify(ev)) => self.on_motion(&ev),
+            xcb::Event::X(x::Event::EnterNotify(ev)) => self.on_enter_notify(&ev),
+            xcb::Event::X(x::Event::FocusIn(ev)) => self.on_focus_in(&ev),
+            xcb::Event::X(x::Event::FocusOut(ev)) => self.on_focus_out(&ev),
+            xcb::Event::X(x::Event::ClientMessage(ev)) => self.on_client_message(&ev),
+            xcb::Event::X(x::Event::PropertyNotify(ev)) => self.on_property_notify(&ev),
+            xcb::Event::X(x::Event::CirculateRequest(ev)) => self.on_circulate_request(&ev),
+            xcb::Event::X(x::Event::CirculateNotify(ev)) => self.on_circulate_notify(&ev),
+            xcb::Event::X(x::Event::ConfigureNotify(ev)) => self.on_configure_notify(&ev),
+            xcb::Event::X(x::Event::GravityNotify(ev)) => self.on_gravity_notify(&ev),
+            xcb::Event::X(x::Event::ConfigureRequest(ev)) => self.on_configure_request(&ev),
+            xcb::Event::X(x::Event::VisibilityNotify(ev)) => self.on_visibility_notify(&ev),
+            xcb::Event::X(x::Event::MappingNotify(ev)) => self.on_mapping_notify(&ev),
+            xcb::Event::RandR(randr::Event::ScreenChangeNotify(ev)) => self.on_screen_change(&ev),
+
+            // silence debug for ones we aren't interested in
+            xcb::Event::X(x::Event::MapNotify(_)) => Ok(()),
+
+            e => {
+                debug!("UNHANDLED: {:?}", e);
+                Ok(())
+            }
+        }
+    }
+
+    // new client, just track it. override-redirect ones too, since that can change before
+    // they're mapped; on_map_request decides whether it's managed
+    fn on_create_notify(&mut self, ev: &x::CreateNotifyEvent) -> xcb::Result<()> {
+        if self.own_windows.contains(&ev.window()) {
+            return Ok(());
+        }
+
+        debug!("new window: {:?}", ev.window());
+
+        self.windows.insert(ev.window(), Window::new(ev.window()));
+        self.restacked(ev.window(), x::Place::OnTop);
+
+        Ok(())
+    }
+
+    // window gone, forget it
+    fn on_destroy_notify(&mut self, ev: &x::DestroyNotifyEvent) -> xcb::Result<()> {
+        debug!("window destroyed: {:?}", ev.window());
+
+        self.forget_window(ev.window());
+
+        Ok(())
+    }
+
+    fn on_reparent_notify(&mut self, ev: &x::ReparentNotifyEvent) -> xcb::Result<()> {
+        // moved under root, so it's a top-level window we should track
+        if ev.parent() == self.screen.root() && !self.own_windows.contains(&ev.window()) {
+            debug!("{:?} reparented to root", ev.window());
+
+            self.windows.entry(ev.window()).or_insert_with(|| Window::new(ev.window()));
+            self.restacked(ev.window(), x::Place::OnTop);
+        }
+
+        // taken away from root by someone else, it's theirs now
+        else {
+            debug!("{:?} reparented away to {:?}", ev.window(), ev.parent());
+
+            self.forget_window(ev.window());
+        }
+
+        Ok(())
+    }
+
+    // client wants to be displayed
+    fn on_map_request(&mut self, ev: &x::MapRequestEvent) -> xcb::Result<()> {
+        // mapping a minimized window is asking for it back
+        if self.windows.get(&ev.window()).is_some_and(|w| w.iconic) {
+            return self.restore(ev.window());
+        }
+
+        // the server only redirects maps of windows that weren't override-redirect when they
+        // asked, but clients can set and clear it whenever they like. what it is now decides,
+        // not what it was at CreateNotify, so one created override-redirect that's cleared it is
+        // managed like any other, and one that's set it since asking is left to itself
+        if self.is_override_redirect(ev.window())? {
+            debug!("{:?} is override-redirect now, mapping it as it is", ev.window());
+            self.conn.send_request_checked(&x::MapWindow {
+                window: ev.window(),
+            });
+            self.flush()?;
+            return Ok(());
+        }
+
+        // not ours, so it gets what it asked for and nothing else
+        if self.is_ignored(ev.window())? {
+            debug!("{:?} is ignored, mapping it as it is", ev.window());
+            self.forget_window(ev.window());
+            self.conn.send_request_checked(&x::MapWindow {
+                window: ev.window(),
+            });
+            self.flush()?;
+            return Ok(());
+        }
+
+        self.map_window(ev.window(), true)?;
+        self.flush()?;
+
+        Ok(())
+    }
+
+    // window unmapped, by us or by the client withdrawing it
+    fn on_unmap_notify(&mut self, ev: &x::UnmapNotifyEvent) -> xcb::Result<()> {
+        // going away, so note where it was while it's still there to ask and still has the
+        // states that say whether that's worth remembering
+        if self.windows.get(&ev.window()).is_some_and(|w| w.ignore_unmaps == 0 && w.mapped) {
+            self.remember_geometry(ev.window())?;
+        }
+
+        if let Some(w) = self.windows.get_mut(&ev.window()) {
+            if w.ignore_unmaps > 0 {
+                w.ignore_unmaps -= 1;
+            }
+            else {
+                debug!("{:?} withdrawn", ev.window());
+                w.mapped = false;
+                w.iconic = false;
+                w.published_workspace = None;
+
+                // its states are forgotten too, so whatever it has when it comes back is what
+                // it's asking for, not what we last set
+                w.fullscreen = false;
+                w.fullscreen_geometry = None;
+                w.maximized_horz = false;
+                w.maximized_vert = false;
+                w.saved_geometry = None;
+                w.demands_attention = false;
+                w.sticky = false;
+                w.above = false;
+                w.below = false;
+                w.other_states.clear();
+
+                self.minimized.retain(|&m| m != ev.window());
+                self.focus_history.retain(|&f| f != ev.window());
+                self.set_wm_state(ev.window(), WITHDRAWN_STATE);
+
+                // EWMH says these go with the window, and it gets new ones if it comes back
+                self.conn.send_request_checked(&x::DeleteProperty {
+                    window: ev.window(),
+                    property: self.atoms.net_wm_desktop,
+                });
+                self.conn.send_request_checked(&x::DeleteProperty {
+                    window: ev.window(),
+                    property: self.atoms.net_wm_state,
+                });
+                self.conn.send_request_checked(&x::DeleteProperty {
+                    window: ev.window(),
+                    property: self.atoms.net_wm_allowed_actions,
+                });
+
+                // let the next one of its class take over
+                if self.scratchpad == Some(ev.window()) {
+                    self.scratchpad = None;
+                }
+
+                self.save_remembered();
+                self.update_fullscreen_active();
+                self.arrange();
+                self.flush()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // bound key
+    fn on_key_press(&mut self, ev: &x::KeyPressEvent) -> xcb::Result<()> {
+        self.last_user_time = ev.time();
+
+        let modifiers = x::ModMask::from_bits_truncate(ev.state().bits() & 0xff);
+
+        let action = self.key_grabs.iter()
+            .find(|g| g.keycode == ev.detail() && g.modifiers == modifiers)
+            .map(|g| g.action);
+
+        if let Some(action) = action {
+            self.run_action(action)?;
+        }
+
+        Ok(())
+    }
+
+    // only seen while we have the keyboard grabbed for cycling
+    fn on_key_release(&mut self, ev: &x::KeyReleaseEvent) -> xcb::Result<()> {
+        let release = self.cycle.as_ref().is_some_and(|c| c.release_keycodes.contains(&ev.detail()));
+        if release {
+            self.finish_cycle()?;
+        }
+
+        Ok(())
+    }
+
+    // every key that's down, right after focus or the pointer comes to the root, which includes
+    // our keyboard grab for cycling starting. the modifiers a cycle is waiting on were read
+    // before then, so one let go in between is never seen released; the cycle keeps just the
+    // keys still held, and ends if there are none
+    fn on_keymap_notify(&mut self, ev: &x::KeymapNotifyEvent) -> xcb::Result<()> {
+        let cycle = match self.cycle.as_mut() {
+            Some(c) => c,
+            None => return Ok(()),
+        };
+
+        // keycodes 8 and up, a bit each; the first byte of the full vector isn't sent
+        let keys = ev.keys();
+        let down = |k: x::Keycode| k >= 8 && keys[k as usize/8 - 1] & (1 << (k % 8)) != 0;
+
+        cycle.release_keycodes.retain(|&k| down(k));
+        trace!("keys held for cycling: {:?}", cycle.release_keycodes);
+
+        if cycle.release_keycodes.is_empty() {
+            debug!("cycle modifiers already let go");
+            self.finish_cycle()?;
+            self.flush()?;
+        }
+
+        Ok(())
+    }
+
+    fn on_button_press(&mut self, ev: &x::ButtonPressEvent) -> xcb::Result<()> {
+        let result = self.handle_button_press(ev);
+
+        // a plain left click came through the synchronous grab, and the pointer is frozen until
+        // we let it go. whatever we did with it, it goes on to the window it was meant for
+        let modifiers = x::ModMask::from_bits_truncate(ev.state().bits() & 0xff);
+        if ev.detail() == 1 && modifiers.is_empty() {
+            self.conn.send_request_checked(&x::AllowEvents {
+                mode: x::Allow::ReplayPointer,
+                time: ev.time(),
+            });
+            self.flush()?;
+        }
+
+        result
+    }
+
+    fn handle_button_press(&mut self, ev: &x::ButtonPressEvent) -> xcb::Result<()> {
+        self.last_user_time = ev.time();
+
+        // our drag grab only asks for releases, so a press means it's been lost along with the
+        // release that should have ended the drag
+        if let Some(drag_state) = self.drag_state {
+            warn!("button press during a drag of {:?}, ending it", drag_state.window);
+            self.end_drag()?;
+        }
+
+        // bound in the config, wherever the pointer is
+        let modifiers = x::ModMask::from_bits_truncate(ev.state().bits() & 0xff);
+        let action = self.button_grabs.iter()
+            .find(|b| b.button == ev.detail() && b.modifiers == modifiers)
+            .map(|b| b.action);
+
+        if let Some(action) = action {
+            return self.run_action(action);
+        }
+
+        // ignore if we're not over a window, except to scroll through workspaces or open the
+        // desktop menu. presses over client windows that don't want them come here too, but they
+        // have a child. Mod4 is for dragging, so only plain presses count
+        if ev.child().is_none() {
+            if ev.event() == self.screen.root() && modifiers.is_empty() {
+                let wheel = self.config.wheel_switches_workspaces;
+                match ev.detail() {
+                    3 => {
+                        if let Some(command) = &self.config.root_menu_command {
+                            spawn(command);
+                        }
+                    },
+                    4 if wheel => return self.step_workspace(-1),
+                    5 if wheel => return self.step_workspace(1),
+                    _ => {},
+                }
+            }
+            return Ok(());
+        }
+
+        // left button inside window area. other buttons only get here by propagating from
+        // windows that don't want them, and shouldn't do anything
+        if ev.state().is_empty() {
+            // a fullscreen window has the floor
+            if ev.detail() != 1 || self.fullscreen_active {
+                return Ok(());
+            }
+
+            // already focused and in front, the click is just for the app. focusing and raising
+            // again changes nothing, but some apps flicker when they're restacked
+            if self.focused == Some(ev.child()) && (!self.config.click_raises || self.in_front(ev.child())) {
+                return Ok(());
+            }
+
+            // it has the pointer, so it probably has focus already, but not if focus is frozen
+            let focusable = self.windows.get(&ev.child()).is_some_and(|w| w.window_type != WindowType::Splash);
+            if focusable {
+                self.focus_window(ev.child());
+            }
+
+            if self.config.click_raises {
+                self.bring_window_to_front(ev.child());
+            }
+            self.flush()?;
+
+            return Ok(());
+        }
+
+        // Mod4+button inside window area
+        let kind = match ev.detail() {
+            1 => DragKind::Move,
+            3 => DragKind::Resize(Edges::BOTTOM_RIGHT),
+            _ => return Ok(()),
+        };
+
+        self.start_drag(ev.child(), kind, ev.root_x(), ev.root_y())
+    }
+
+    // move or resize with the pointer until the button comes up
+    fn start_drag(&mut self, window: x::Window, kind: DragKind, root_x: i16, root_y: i16) -> xcb::Result<()> {
+        // bring window to front
+        self.bring_window_to_front(window);
+
+        // grab the pointer for window move
+        self.conn.send_request(&x::GrabPointer {
+            owner_events: false,
+            grab_window: self.screen.root(),
+            event_mask: x::EventMask::BUTTON_RELEASE | x::EventMask::BUTTON_MOTION | x::EventMask::POINTER_MOTION_HINT,
+            pointer_mode: x::GrabMode::Async,
+            keyboard_mode: x::GrabMode::Async,
+            confine_to: self.screen.root(),
+            cursor: self.cursor(kind.cursor_name()),
+            time: self.last_time,
+        });
+
+        // will need window geometry to compute drag offset
+        let geometry = self.wait_for_reply(self.conn.send_request(&x::GetGeometry {
+            drawable: x::Drawable::Window(window),
+        }))?;
+
+        // record window
+        self.drag_state = Some(DragState {
+            kind,
+            window,
+            off_x: root_x - geometry.x(),
+            off_y: root_y - geometry.y(),
+            overshoot: (0, 0),
+        });
+
+        debug!("button down on {:?}, drag state {:?}", window, self.drag_state);
+        self.watch_drag();
+
+        Ok(())
+    }
+
+    // (re)start the wait before checking on the drag, unless that's turned off
+    fn watch_drag(&mut self) {
+        if self.config.drag_timeout > 0 {
+            self.set_timer(Duration::from_millis(self.config.drag_timeout as u64), Timer::StaleDrag);
+        }
+    }
+
+    // the drag has been still for a while, which is fine if the button's still being held. if
+    // it isn't, the release went somewhere else, like when another client breaks our grab, and
+    // the drag would carry on with the next motion we see
+    fn check_drag(&mut self) -> xcb::Result<()> {
+        let window = match self.drag_state {
+            Some(d) => d.window,
+            None => return Ok(()),
+        };
+
+        let pointer = self.wait_for_reply(self.conn.send_request(&x::QueryPointer {
+            window: self.screen.root(),
+        }))?;
+        let buttons = x::KeyButMask::BUTTON1 | x::KeyButMask::BUTTON2 | x::KeyButMask::BUTTON3 |
+            x::KeyButMask::BUTTON4 | x::KeyButMask::BUTTON5;
+
+        if pointer.mask().intersects(buttons) {
+            self.watch_drag();
+            return Ok(());
+        }
+
+        warn!("drag of {:?} missed its button release, ending it", window);
+        self.end_drag()
+    }
+
+    fn end_drag(&mut self) -> xcb::Result<()> {
+        self.cancel_timer(Timer::StaleDrag);
+
+        // just release the pointer
+        self.conn.send_request_checked(&x::UngrabPointer {
+            time: self.last_time,
+        });
+
+        // a move may have taken it to another monitor
+        if let Some(drag_state) = self.drag_state {
+            self.update_window_monitor(drag_state.window)?;
+            self.remember_geometry(drag_state.window)?;
+        }
+
+        self.flush()?;
+
+        self.drag_state = None;
+
+        Ok(())
+    }
+
+    // client-side decorations asking us to move or resize, data is root x, root y, direction,
+    // button, source
+    fn handle_net_wm_moveresize(&mut self, window: x::Window, data: [u32; 5]) -> xcb::Result<()> {
+        if !self.windows.contains_key(&window) {
+            return Ok(());
+        }
+
+        let (root_x, root_y) = (data[0] as i16, data[1] as i16);
+
+        let kind = match data[2] {
+            // move
+            8 => DragKind::Move,
+            // any of the edges or corners
+            direction @ 0..=7 => DragKind::Resize(Edges::from_direction(direction).unwrap()),
+            // cancel
+            11 => return self.end_drag(),
+            // keyboard move and resize
+            direction => {
+                debug!("unsupported _NET_WM_MOVERESIZE direction {} on {:?}", direction, window);
+                return Ok(());
+            },
+        };
+
+        self.start_drag(window, kind, root_x, root_y)?;
+        self.flush()?;
+
+        Ok(())
+    }
+
+    // _NET_MOVERESIZE_WINDOW, from scripts like wmctrl -e: the gravity in the low byte of the
+    // first word and flags for which of x, y, width and height are set above it, then those four.
+    // windows we're placing ourselves are left alone, like they are for centre_window
+    fn handle_net_moveresize_window(&mut self, window: x::Window, data: [u32; 5]) -> xcb::Result<()> {
+        let (own_gravity, min_size) = match self.windows.get(&window) {
+            Some(w) if w.fullscreen || w.zoomed || w.maximized_horz || w.maximized_vert => return Ok(()),
+            Some(w) if self.config.tiling && !w.floating => return Ok(()),
+            Some(w) => (w.gravity, w.min_size.unwrap_or((1, 1))),
+            None => return Ok(()),
+        };
+
+        // 0 is the window's own
+        let gravity = match data[0] & 0xff {
+            0 => own_gravity,
+            n => gravity_from(n),
+        };
+        let flags = data[0] >> 8;
+
+        let geometry = self.wait_for_reply(self.conn.send_request(&x::GetGeometry {
+            drawable: x::Drawable::Window(window),
+        }))?;
+        let border = geometry.border_width() as u32;
+        let current = Geometry {
+            x: geometry.x() as i32,
+            y: geometry.y() as i32,
+            width: geometry.width() as u32,
+            height: geometry.height() as u32,
+        };
+
+        let width = if flags & (1 << 2) != 0 { data[3].max(min_size.0) } else { current.width };
+        let height = if flags & (1 << 3) != 0 { data[4].max(min_size.1) } else { current.height };
+
+        // a new size keeps the gravity's reference point still. a new position is for the
+        // outside of the border, or the inside for static
+        let mut new = current.resized(border, width, height, border, gravity);
+        let inside = if gravity == x::Gravity::Static { border as i32 } else { 0 };
+        if flags & (1 << 0) != 0 {
+            new.x = data[1] as i32 - inside;
+        }
+        if flags & (1 << 1) != 0 {
+            new.y = data[2] as i32 - inside;
+        }
+
+        // kept on whichever monitor it lands on, the same as a drag
+        let (outer_width, outer_height) = (width as i32 + 2*border as i32, height as i32 + 2*border as i32);
+        let monitor_idx = self.monitor_at(new.x + outer_width/2, new.y + outer_height/2);
+        let (x, y) = self.work_area(monitor_idx).clamp(new.x, new.y, outer_width, outer_height);
+
+        debug!("{:?} asked to be at {},{} {}x{}, now {},{}", window, new.x, new.y, width, height, x, y);
+
+        self.conn.send_request_checked(&x::ConfigureWindow {
+            window,
+            value_list: &[
+                x::ConfigWindow::X(x),
+                x::ConfigWindow::Y(y),
+                x::ConfigWindow::Width(width),
+                x::ConfigWindow::Height(height),
+            ],
+        });
+        self.send_configure_notify(window, Geometry { x, y, width, height }, border);
+        self.set_window_monitor(window, monitor_idx);
+        self.flush()?;
+
+        Ok(())
+    }
+
+    fn on_button_release(&mut self, ev: &x::ButtonReleaseEvent) -> xcb::Result<()> {
+        self.end_drag()?;
+
+        debug!("button release on {:?}, drag cleared", ev.child());
+
+        Ok(())
+    }
+
+    fn on_motion(&mut self, _ev: &x::MotionNotifyEvent) -> xcb::Result<()> {
+        let drag_state = match self.drag_state {
+            Some(d) => d,
+            None => return Ok(()),
+        };
+        self.watch_drag();
+
+        let pointer = self.wait_for_reply(self.conn.send_request(&x::QueryPointer {
+            window: self.screen.root(),
+        }))?;
+        let geometry = self.wait_for_reply(self.conn.send_request(&x::GetGeometry {
+            drawable: x::Drawable::Window(drag_state.window),
+        }))?;
+
+        let grid = match pointer.mask().bits() & self.config.snap_bypass_modifier.bits() {
+            0 => self.config.snap_grid,
+            _ => None,
+        };
+
+        match drag_state.kind {
+            DragKind::Move => {
+                // its own border, not the config's; zoomed and fullscreen windows have none
+                let outer_width = geometry.width() as i32 + 2*geometry.border_width() as i32;
+                let outer_height = geometry.height() as i32 + 2*geometry.border_width() as i32;
+
+                let (new_x, new_y) = match self.config.edge_resistance {
+                    0 => drag::clamp_move(
+                        (pointer.root_x() as i32, pointer.root_y() as i32),
+                        (drag_state.off_x as i32, drag_state.off_y as i32),
+                        (outer_width, outer_height),
+                        &self.screen_work_area(),
+                        grid,
+                    ),
+
+                    // free to leave the screen, but held at the edges for a bit on the way
+                    resistance => {
+                        let x = drag::snap_to_grid(pointer.root_x() as i32 - drag_state.off_x as i32, grid);
+                        let y = drag::snap_to_grid(pointer.root_y() as i32 - drag_state.off_y as i32, grid);
+
+                        let (screen_width, screen_height) = self.screen_size();
+                        let mut x_edges = vec![0, screen_width];
+                        let mut y_edges = vec![0, screen_height];
+                        for m in &self.monitors {
+                            x_edges.extend([m.x, m.x + m.width]);
+                            y_edges.extend([m.y, m.y + m.height]);
+                        }
+
+                        let (x, overshoot_x) = drag::resist(x, geometry.x() as i32, outer_width, &x_edges, resistance);
+                        let (y, overshoot_y) = drag::resist(y, geometry.y() as i32, outer_height, &y_edges, resistance);
+
+                        let overshoot = (overshoot_x, overshoot_y);
+                        if overshoot != drag_state.overshoot {
+                            debug!("{:?} held at edge, {:?} past", drag_state.window, overshoot);
+                        }
+                        if let Some(d) = self.drag_state.as_mut() {
+                            d.overshoot = overshoot;
+                        }
+
+                        (x, y)
+                    },
+                };
+
+                debug!("moving {:?} to {},{}", drag_state.window, new_x, new_y);
+
+                self.conn.send_request_checked(&x::ConfigureWindow {
+                    window: drag_state.window,
+                    value_list: &[
+                        x::ConfigWindow::X(new_x),
+                        x::ConfigWindow::Y(new_y),
+                    ],
+                });
+                let moved = Geometry { x: new_x, y: new_y, width: geometry.width() as u32, height: geometry.height() as u32 };
+                self.send_configure_notify(drag_state.window, moved, geometry.border_width() as u32);
+                self.flush()?;
+            },
+
+            DragKind::Resize(edges) => {
+                // as small as the window says it can go, or as the config says if it doesn't
+                let min_size = self.windows.get(&drag_state.window)
+                    .and_then(|w| w.min_size)
+                    .map(|(w, h)| (w.max(1), h.max(1)))
+                    .unwrap_or((self.config.min_window_size, self.config.min_window_size));
+
+                let (new_x, new_y, new_width, new_height) = drag::resize_edges(
+                    (pointer.root_x() as i32, pointer.root_y() as i32),
+                    (geometry.x() as i32, geometry.y() as i32, geometry.width() as u32, geometry.height() as u32),
+                    edges,
+                    geometry.border_width() as i32,
+                    min_size,
+                    grid,
+                );
+
+                debug!("resizing {:?} to {},{} {}x{}", drag_state.window, new_x, new_y, new_width, new_height);
+
+                self.conn.send_request_checked(&x::ConfigureWindow {
+                    window: drag_state.window,
+                    value_list: &[
+                        x::ConfigWindow::X(new_x),
+                        x::ConfigWindow::Y(new_y),
+                        x::ConfigWindow::Width(new_width),
+                        x::ConfigWindow::Height(new_height),
+                    ],
+                });
+                let resized = Geometry { x: new_x, y: new_y, width: new_width, height: new_height };
+                self.send_configure_notify(drag_state.window, resized, geometry.border_width() as u32);
+                self.flush()?;
+            },
+        }
+
+        Ok(())
+    }
+
+    fn on_enter_notify(&mut self, ev: &x::EnterNotifyEvent) -> xcb::Result<()> {
+        if self.fullscreen_active {
+            debug!("pointer entered {:?}, but fullscreen is active", ev.event());
+            return Ok(());
+        }
+
+        // keyboard focus wins until the pointer actually moves
+        if self.pointer_at_focus == Some((ev.root_x(), ev.root_y())) {
+            debug!("pointer entered {:?} without moving, leaving focus alone", ev.event());
+            return Ok(());
+        }
+        self.pointer_at_focus = None;
+
+        // on the way somewhere else
+        let frozen = self.config.focus_freeze_modifier
+            .is_some_and(|m| ev.state().bits() & m.bits() == m.bits());
+        if frozen {
+            debug!("pointer entered {:?} with focus frozen", ev.event());
+            return Ok(());
+        }
+
+        debug!("pointer entered {:?}, focusing", ev.event());
+
+        // focus follows mouse :)
+        self.focus_window(ev.event());
+
+        // and maybe raise, once it's been there a moment
+        if self.config.auto_raise {
+            match self.config.auto_raise_delay {
+                0 => self.bring_window_to_front(ev.event()),
+                delay => self.set_timer(Duration::from_millis(delay as u64), Timer::AutoRaise(ev.event())),
+            }
+        }
+
+        self.flush()?;
+
+        Ok(())
+    }
+
+    fn on_focus_in(&mut self, ev: &x::FocusInEvent) -> xcb::Result<()> {
+        debug!("{:?} received focus", ev.event());
+
+        self.focused = Some(ev.event());
+        if self.windows.contains_key(&ev.event()) {
+            self.focus_history.retain(|&w| w != ev.event());
+            self.focus_history.insert(0, ev.event());
+        }
+        self.update_fullscreen_active();
+        self.set_demands_attention(ev.event(), false);
+        self.apply_border(ev.event());
+        self.show_all_opacity();
+
+        self.flush()?;
+
+        Ok(())
+    }
+
+    fn on_visibility_notify(&mut self, ev: &x::VisibilityNotifyEvent) -> xcb::Result<()> {
+        let obscured = ev.state() == x::Visibility::FullyObscured;
+
+        let was_obscured = match self.windows.get_mut(&ev.window()) {
+            Some(w) => std::mem::replace(&mut w.obscured, obscured),
+            None => return Ok(()),
+        };
+
+        // coming out from under something, so show it with whatever border it should have now
+        if was_obscured && !obscured {
+            debug!("{:?} no longer obscured", ev.window());
+            self.apply_border(ev.window());
+            self.flush()?;
+        }
+
+        Ok(())
+    }
+
+    fn on_focus_out(&mut self, ev: &x::FocusOutEvent) -> xcb::Result<()> {
+        // it still has focus underneath a keyboard grab, like the one for a key binding that's
+        // about to act on it, or when focus only moved into one of its own subwindows
+        if ev.mode() == x::NotifyMode::Grab || ev.detail() == x::NotifyDetail::Inferior {
+            return Ok(());
+        }
+
+        debug!("{:?} lost focus", ev.event());
+
+        // FocusIn for wherever it went comes after this, so until then nothing has it
+        if self.focused == Some(ev.event()) {
+            self.focused = None;
+        }
+
+        self.apply_border(ev.event());
+
+        // its whole group is dimmed, in case focus went somewhere we'll get no FocusIn from
+        self.show_all_opacity();
+
+        self.flush()?;
+
+        Ok(())
+    }
+
+    fn on_client_message(&mut self, ev: &x::ClientMessageEvent) -> xcb::Result<()> {
+        // client wants its state changed
+        if ev.r#type() == self.atoms.net_wm_state {
+            if let x::ClientMessageData::Data32(data) = ev.data() {
+                debug!("state change request for {:?}: {:?}", ev.window(), data);
+
+                self.handle_net_wm_state(ev.window(), data)?;
+                self.flush()?;
+            }
+        }
+
+        // a pager or taskbar wants it closed, like close_window would
+        else if ev.r#type() == self.atoms.net_close_window {
+            debug!("close request for {:?}", ev.window());
+
+            if self.windows.get(&ev.window()).is_some_and(|w| w.mapped) {
+                self.close_window(ev.window())?;
+            }
+        }
+
+        // client wants to be minimized
+        else if ev.r#type() == self.atoms.wm_change_state {
+            if let x::ClientMessageData::Data32(data) = ev.data() {
+                if data[0] == ICONIC_STATE {
+                    self.minimize(ev.window())?;
+                }
+            }
+        }
+
+        // client wants to be moved or resized by the pointer
+        else if ev.r#type() == self.atoms.net_wm_moveresize {
+            if let x::ClientMessageData::Data32(data) = ev.data() {
+                debug!("move/resize request for {:?}: {:?}", ev.window(), data);
+
+                self.handle_net_wm_moveresize(ev.window(), data)?;
+            }
+        }
+
+        // something wants the window somewhere in particular, without the pointer
+        else if ev.r#type() == self.atoms.net_moveresize_window {
+            if let x::ClientMessageData::Data32(data) = ev.data() {
+                debug!("move/resize window request for {:?}: {:?}", ev.window(), data);
+
+                self.handle_net_moveresize_window(ev.window(), data)?;
+            }
+        }
+
+        // client wants to know how big its border will be, before it's mapped. it'll be the
+        // normal one; fullscreen and zoom take it away later if they happen
+        else if ev.r#type() == self.atoms.net_request_frame_extents {
+            debug!("frame extents request for {:?}", ev.window());
+
+            self.set_frame_extents(ev.window(), self.config.border_width);
+            self.flush()?;
+        }
+
+        Ok(())
+    }
+
+    fn on_property_notify(&mut self, ev: &x::PropertyNotifyEvent) -> xcb::Result<()> {
+        if self.windows.contains_key(&ev.window()) {
+            self.handle_property_change(ev.window(), ev.atom())?;
+            self.flush()?;
+        }
+
+        Ok(())
+    }
+
+    // old-style raise or lower request
+    fn on_circulate_request(&mut self, ev: &x::CirculateRequestEvent) -> xcb::Result<()> {
+        debug!("circulate request for {:?}: {:?}", ev.window(), ev.place());
+
+        match ev.place() {
+            x::Place::OnTop => self.bring_window_to_front(ev.window()),
+            x::Place::OnBottom => self.send_window_to_back(ev.window()),
+        }
+        self.flush()?;
+
+        Ok(())
+    }
+
+    // someone else circulated, so note where it went
+    fn on_circulate_notify(&mut self, ev: &x::CirculateNotifyEvent) -> xcb::Result<()> {
+        self.restacked(ev.window(), ev.place());
+
+        Ok(())
+    }
+
+    fn on_configure_notify(&mut self, ev: &x::ConfigureNotifyEvent) -> xcb::Result<()> {
+        // root resized, which happens on resolution change even without randr
+        if ev.window() == self.screen.root() {
+            self.screen_width = ev.width() as i32;
+            self.screen_height = ev.height() as i32;
+
+            debug!("root configured, screen size now {}x{}", self.screen_width, self.screen_height);
+
+            // with randr, the screen change event does the rest
+            if !self.randr {
+                self.refresh_monitors()?;
+                self.bring_windows_into_view()?;
+                self.flush()?;
+            }
+
+            return Ok(());
+        }
+
+        // synthetic ones are sent by clients and don't say anything reliable
+        if ev.response_type() & 0x80 != 0 {
+            return Ok(());
+        }
+
+        // keep the cached geometry current, whoever moved it
+        if let Some(w) = self.windows.get_mut(&ev.window()) {
+            let geometry = Geometry {
+                x: ev.x() as i32,
+                y: ev.y() as i32,
+                width: ev.width() as u32,
+                height: ev.height() as u32,
+            };
+
+            if w.geometry.is_some_and(|g| g.width != geometry.width || g.height != geometry.height) {
+                debug!("{:?} resized to {}x{}", ev.window(), geometry.width, geometry.height);
+            }
+
+            w.geometry = Some(geometry);
+        }
+
+        Ok(())
+    }
+
+    // the server moved a window for its win_gravity attribute, when the root changed size
+    fn on_gravity_notify(&mut self, ev: &x::GravityNotifyEvent) -> xcb::Result<()> {
+        if let Some(w) = self.windows.get_mut(&ev.window()) {
+            debug!("{:?} moved by gravity to {},{}", ev.window(), ev.x(), ev.y());
+            if let Some(g) = w.geometry.as_mut() {
+                g.x = ev.x() as i32;
+                g.y = ev.y() as i32;
+            }
+        }
+
+        Ok(())
+    }
+
+    // we decide where windows go, so requests are turned down. ICCCM says to tell the client
+    // where it still is, or it'll carry on thinking it got what it asked for
+    fn on_configure_request(&mut self, ev: &x::ConfigureRequestEvent) -> xcb::Result<()> {
+        // not ours, like one an ignore rule let go, so it gets exactly what it asked for
+        if !self.windows.contains_key(&ev.window()) {
+            let mask = ev.value_mask();
+            let mut values = vec![];
+            if mask.contains(x::ConfigWindowMask::X) {
+                values.push(x::ConfigWindow::X(ev.x() as i32));
+            }
+            if mask.contains(x::ConfigWindowMask::Y) {
+                values.push(x::ConfigWindow::Y(ev.y() as i32));
+            }
+            if mask.contains(x::ConfigWindowMask::WIDTH) {
+                values.push(x::ConfigWindow::Width(ev.width() as u32));
+            }
+            if mask.contains(x::ConfigWindowMask::HEIGHT) {
+                values.push(x::ConfigWindow::Height(ev.height() as u32));
+            }
+            if mask.contains(x::ConfigWindowMask::BORDER_WIDTH) {
+                values.push(x::ConfigWindow::BorderWidth(ev.border_width() as u32));
+            }
+            if mask.contains(x::ConfigWindowMask::SIBLING) {
+                values.push(x::ConfigWindow::Sibling(ev.sibling()));
+            }
+            if mask.contains(x::ConfigWindowMask::STACK_MODE) {
+                values.push(x::ConfigWindow::StackMode(ev.stack_mode()));
+            }
+
+            debug!("passing on configure request for {:?}: {:?}", ev.window(), values);
+            self.conn.send_request_checked(&x::ConfigureWindow {
+                window: ev.window(),
+                value_list: &values,
+            });
+            self.flush()?;
+            return Ok(());
+        }
+
+        let cookie = self.conn.send_request(&x::GetGeometry {
+            drawable: x::Drawable::Window(ev.window()),
+        });
+        match self.wait_for_reply(cookie) {
+            Ok(g) => {
+                let geometry = Geometry {
+                    x: g.x() as i32,
+                    y: g.y() as i32,
+                    width: g.width() as u32,
+                    height: g.height() as u32,
+                };
+                self.send_configure_notify(ev.window(), geometry, g.border_width() as u32);
+                self.flush()?;
+            },
+            Err(xcb::Error::Protocol(e)) => debug!("couldn't get geometry for {:?}: {:?}", ev.window(), e),
+            Err(e) => return Err(e),
+        }
+
+        Ok(())
+    }
+
+    // keyboard layout changed, keycodes for our bindings may have moved
+    fn on_mapping_notify(&mut self, ev: &x::MappingNotifyEvent) -> xcb::Result<()> {
+        if ev.request() == x::Mapping::Keyboard {
+            self.grab_keys()?;
+            self.flush()?;
+        }
+
+        Ok(())
+    }
+
+    // resolution or monitor layout changed
+    fn on_screen_change(&mut self, ev: &randr::ScreenChangeNotifyEvent) -> xcb::Result<()> {
+        debug!("screen changed: {}x{}", ev.width(), ev.height());
+
+        self.refresh_screen_size()?;
+        self.refresh_monitors()?;
+        self.bring_windows_into_view()?;
+        self.arrange();
+        if self.config.background.is_some() {
+            self.paint_background();
+        }
+        self.flush()?;
+
+        Ok(())
+    }
+
+    // new is false for windows that were already showing when we started
+    fn map_window(&mut self, window: x::Window, new: bool) -> xcb::Result<()> {
+        // usually tracked since its CreateNotify, but that can be missed, so it's never too late
+        if let hash_map::Entry::Vacant(e) = self.windows.entry(window) {
+            debug!("{:?} mapped before we knew about it, tracking it now", window);
+            e.insert(Window::new(window));
+            self.restacked(window, x::Place::OnTop);
+        }
+
+        let class = self.get_class(window)?;
+        let title = self.get_title(window)?;
+        let pid = self.get_pid(window)?;
+        let client_machine = self.get_client_machine(window)?;
+        let opacity = self.get_opacity(window)?;
+        let restored = self.restoring.remove(&window).unwrap_or_default();
+        let iconic = restored.iconic || (new && self.wants_iconic(window)?);
+        let aspect = self.get_aspect(window)?;
+        let gravity = self.get_gravity(window)?;
+        let min_size = self.get_min_size(window)?;
+        let fixed_size = self.get_fixed_size(window)?;
+        let input = self.wants_input(window)?;
+        let take_focus = self.supports_protocol(window, self.atoms.wm_take_focus)?;
+        let transient_for = self.get_transient_for(window)?;
+
+        // a transient with no type of its own is a dialog, says EWMH
+        let window_type = match (self.get_window_type(window)?, transient_for) {
+            (WindowType::Normal, Some(_)) => WindowType::Dialog,
+            (window_type, _) => window_type,
+        };
+
+        // the first window of the scratchpad class becomes the scratchpad
+        let is_scratchpad = self.scratchpad.is_none() &&
+            class.is_some() && class == self.config.scratchpad_class;
+        if is_scratchpad {
+            debug!("{:?} is the scratchpad", window);
+            self.scratchpad = Some(window);
+            self.scratchpad_shown = true;
+        }
+
+        // dialogs go over their parent, if it's one of ours
+        let parent = transient_for.and_then(|p| self.windows.get(&p)).filter(|p| p.mapped);
+        let parent_placement = parent.map(|p| (p.x_window, p.monitor, p.workspace, p.tags));
+
+        // put it back where the last one of its class was, if we've seen one before
+        // one that's already up stays where it is, with the inside kept still as its border
+        // changes, like release_windows does on the way out
+        let adopted = restored.geometry.map(|(g, old_border_width)| {
+            let border_width = if window_type == WindowType::Splash { 0 } else { self.config.border_width };
+            (g.resized(old_border_width, g.width, g.height, border_width, x::Gravity::Static), border_width)
+        });
+
+        let remembered = match window_type {
+            _ if adopted.is_some() => adopted.map(|(g, _)| g),
+            WindowType::Normal => class.as_ref().and_then(|c| self.remembered.get(c)).copied(),
+            _ => None,
+        };
+
+        // rules have the last word, later ones over earlier
+        let matched_rules = self.matching_rules(class.as_deref(), title.as_deref());
+        let (mut rule_monitor, mut rule_workspace, mut rule_floating, mut rule_size) = (None, None, None, None);
+        let mut rule_pin = None;
+        for &i in &matched_rules {
+            let rule = &self.config.rules[i];
+            rule_monitor = rule.monitor.filter(|&m| m < self.monitors.len()).or(rule_monitor);
+            rule_workspace = rule.workspace.filter(|&w| w < self.config.workspace_count).or(rule_workspace);
+            rule_floating = rule.floating.or(rule_floating);
+            rule_size = rule.size.or(rule_size);
+            rule_pin = rule.pin_width.or(rule_pin);
+        }
+
+        // new windows go on whatever's showing where the user is working
+        let monitor_idx = match (rule_monitor, parent_placement, remembered) {
+            (Some(monitor_idx), _, _) => monitor_idx,
+            (_, Some((_, monitor_idx, _, _)), _) => monitor_idx,
+            (_, _, Some(g)) => self.monitor_at(g.x + g.width as i32/2, g.y + g.height as i32/2),
+            _ => self.active_monitor()?,
+        };
+        let monitor = self.work_area(monitor_idx);
+        let workspace = match (restored.workspace.or(rule_workspace), parent_placement) {
+            (Some(workspace), _) => workspace,
+            (_, Some((_, _, workspace, _))) if rule_monitor.is_none() => workspace,
+            _ => self.current_workspaces[monitor_idx],
+        };
+        // what it had before we restarted, or with its parent, or whatever's being viewed
+        let tags = restored.tags.or(parent_placement.map(|(_, _, _, tags)| tags)).unwrap_or(self.selected_tags);
+
+        if let Some(win) = self.windows.get_mut(&window) {
+            win.class = class;
+            win.title = title;
+            win.pid = pid;
+            win.client_machine = client_machine;
+            win.opacity = opacity.unwrap_or(u32::MAX);
+            win.shown_opacity = win.opacity;
+            win.aspect = aspect;
+            win.gravity = gravity;
+            win.min_size = min_size;
+            win.fixed_size = fixed_size;
+            win.input = input;
+            win.take_focus = take_focus;
+            win.iconic = iconic;
+            win.window_type = window_type;
+            win.transient_for = transient_for;
+            win.mapped = true;
+            win.hidden = false;
+            win.monitor = monitor_idx;
+            win.workspace = workspace;
+            win.tags = tags;
+
+            // dialogs and the like always float; anything the user floated stays that way
+            win.floating |= window_type.is_floating();
+            if let Some(floating) = restored.floating.or(rule_floating) {
+                win.floating = floating;
+            }
+            win.pinned_width = rule_pin;
+            win.matched_rules = matched_rules;
+        }
+        self.publish_workspaces();
+        self.update_allowed_actions(window);
+
+        if !self.layout_order.contains(&window) {
+            self.layout_order.push(window);
+        }
+
+        let (x, y, w, h, border_width) = if let Some((g, border_width)) = adopted {
+            // as long as that's still on a monitor
+            let (x, y) = monitor.clamp(g.x, g.y, g.width as i32 + 2*border_width as i32, g.height as i32 + 2*border_width as i32);
+            (x, y, g.width, g.height, border_width)
+        }
+        else {
+            match (window_type, remembered) {
+                // scratchpad drops down from the top of the monitor, unless it's been somewhere before
+                _ if is_scratchpad => match self.scratchpad_geometry {
+                    Some(g) => (g.x, g.y, g.width, g.height, self.config.border_width),
+                    None => {
+                        let w = (monitor.width - 2*self.border_width()).max(1) as u32;
+                        let h = (monitor.height/2 - 2*self.border_width()).max(1) as u32;
+                        (monitor.x, monitor.y, w, h, self.config.border_width)
+                    },
+                },
+
+                // splash screens get the size they asked for, centred, with no border
+                (WindowType::Splash, _) => {
+                    let (w, h) = match self.get_geometry(window)? {
+                        Some(g) => (g.width, g.height),
+                        None => (640, 480),
+                    };
+                    let x = monitor.x + (monitor.width - w as i32)/2;
+                    let y = monitor.y + (monitor.height - h as i32)/2;
+                    (x, y, w, h, 0)
+                },
+
+                // dialogs and friends keep their size and float centred over their parent, or the
+                // monitor if they don't have one
+                (window_type, _) if window_type.is_floating() => {
+                    let (w, h) = match self.get_geometry(window)? {
+                        Some(g) => (g.width, g.height),
+                        None => (640, 480),
+                    };
+                    let parent_geometry = match parent_placement {
+                        Some((parent, _, _, _)) => self.get_geometry(parent)?,
+                        None => None,
+                    };
+                    let (centre_x, centre_y) = match parent_geometry {
+                        Some(p) => (p.x + p.width as i32/2 + self.border_width(), p.y + p.height as i32/2 + self.border_width()),
+                        None => (monitor.x + monitor.width/2, monitor.y + monitor.height/2),
+                    };
+                    let outer_w = w as i32 + 2*self.border_width();
+                    let outer_h = h as i32 + 2*self.border_width();
+                    let (x, y) = monitor.clamp(centre_x - outer_w/2, centre_y - outer_h/2, outer_w, outer_h);
+                    (x, y, w, h, self.config.border_width)
+                },
+
+                (_, Some(g)) => {
+                    let (w, h) = match rule_size {
+                        Some(size) => self.resolve_size(size, &monitor),
+                        None => (g.width, g.height),
+                    };
+
+                    // monitors may have changed since, so make sure it's still on one
+                    let (x, y) = monitor.clamp(g.x, g.y, w as i32 + 2*self.border_width(), h as i32 + 2*self.border_width());
+                    (x, y, w, h, self.config.border_width)
+                },
+
+                // a rule says, or the client does, or it gets the default
+                (_, None) => {
+                    let (w, h) = match (rule_size, self.get_requested_size(window)?) {
+                        (Some(size), _) => self.resolve_size(size, &monitor),
+                        (None, Some(requested)) => requested,
+                        (None, None) => self.resolve_size(self.config.default_size, &monitor),
+                    };
+                    (monitor.x, monitor.y, w, h, self.config.border_width)
+                },
+            }
+        };
+
+        // too big for the monitor, so parts of it would be out of reach
+        let outer_w = w as i32 + 2*border_width as i32;
+        let outer_h = h as i32 + 2*border_width as i32;
+        let oversized = outer_w > monitor.width || outer_h > monitor.height;
+        let (x, y, w, h) = match self.config.oversized_windows {
+            Oversized::Shrink | Oversized::KeepAspect if oversized => {
+                let min_size = min_size.unwrap_or((1, 1));
+                let keep_aspect = self.config.oversized_windows == Oversized::KeepAspect;
+                let g = layout::shrink_to_fit(&monitor, (w, h), min_size, keep_aspect, border_width as i32);
+
+                if g.width as i32 + 2*border_width as i32 > monitor.width || g.height as i32 + 2*border_width as i32 > monitor.height {
+                    warn!("{:?} asked for {}x{} and can't be smaller than {}x{}, which is still too big for its monitor",
+                        window, w, h, min_size.0, min_size.1);
+                }
+                debug!("{:?} asked for {}x{}, shrunk to fit", window, w, h);
+                (g.x, g.y, g.width, g.height)
+            },
+            _ => (x, y, w, h),
+        };
+
+        debug!("mapping {:?} ({:?}) to {},{} {}x{}", window, window_type, x, y, w, h);
+
+        // position and size
+        // XXX maybe we should take defaults or preferences from ConfigureRequest
+        self.conn.send_request_checked(&x::ConfigureWindow {
+            window,
+            value_list: &[
+                x::ConfigWindow::X(x),
+                x::ConfigWindow::Y(y),
+                x::ConfigWindow::Width(w),
+                x::ConfigWindow::Height(h),
+                x::ConfigWindow::BorderWidth(border_width),
+            ],
+        });
+        self.send_configure_notify(window, Geometry { x, y, width: w, height: h }, border_width);
+        self.set_frame_extents(window, border_width);
+        self.apply_border(window);
+        self.show_opacity(window);
+
+        // anything it asked for before it was mapped, like a video player coming back fullscreen.
+        // it's all added, as though it had sent them with _NET_WM_STATE_ADD
+        let state = self.get_net_wm_state(window)?;
+        if !state.is_empty() {
+            self.change_net_wm_state(window, 1, &state)?;
+        }
+
+        // its tile, if it has one, before anyone sees it
+        self.arrange();
+
+        // request enter and focus events, except for splash screens which never take focus
+        let event_mask = match window_type {
+            WindowType::Splash => x::EventMask::empty(),
+            _ => x::EventMask::ENTER_WINDOW | x::EventMask::FOCUS_CHANGE | x::EventMask::PROPERTY_CHANGE |
+                x::EventMask::VISIBILITY_CHANGE,
+        };
+        self.conn.send_request_checked(&x::ChangeWindowAttributes {
+            window,
+            value_list: &[
+                x::Cw::EventMask(event_mask),
+            ],
+        });
+
+        // it's placed and ready for when it's restored, but stays out of sight until then
+        if iconic {
+            debug!("{:?} starts minimized", window);
+            if let Some(win) = self.windows.get_mut(&window) {
+                win.hidden = true;
+            }
+            self.minimized.push(window);
+            self.set_wm_state(window, ICONIC_STATE);
+            return Ok(());
+        }
+
+        // a rule sent it to a workspace that isn't showing, so it's there for when it is
+        if self.windows.get(&window).is_some_and(|w| !self.is_visible(w)) {
+            debug!("{:?} goes on workspace {}, not showing", window, workspace);
+            if let Some(win) = self.windows.get_mut(&window) {
+                win.hidden = true;
+            }
+            self.set_wm_state(window, NORMAL_STATE);
+            return Ok(());
+        }
+
+        // be visible!
+        self.conn.send_request_checked(&x::MapWindow {
+            window,
+        });
+        self.set_wm_state(window, NORMAL_STATE);
+
+        // dialogs start out above everything, like they were just raised
+        if window_type.is_floating() {
+            self.bring_window_to_front(window);
+        }
+
+        // it was summoned, so it's wanted right away
+        if is_scratchpad {
+            self.bring_window_to_front(window);
+            self.focus_by_keyboard(window)?;
+        }
+        else if new && window_type != WindowType::Splash {
+            // whatever we decide here, it shouldn't be undone by the window appearing under the
+            // pointer
+            self.hold_focus_from_pointer()?;
+
+            // a user time of 0 means the window doesn't want focus at all. otherwise, with
+            // prevention on, don't take focus away from what the user is doing unless the window
+            // came from their last input or it's a dialog for the focused window
+            let user_time = self.get_user_time(window)?;
+            let allowed = match user_time {
+                Some(0) => false,
+                _ if !self.config.focus_stealing_prevention || self.focused.is_none() => true,
+                _ if transient_for.is_some() && transient_for == self.focused => true,
+                Some(time) => time_since(time, self.last_user_time) >= 0,
+                None => false,
+            };
+
+            if !allowed {
+                debug!("not letting {:?} steal focus", window);
+
+                if let Some(focused) = self.focused {
+                    self.stack_below(window, focused);
+                }
+                self.set_demands_attention(window, true);
+            }
+            else if self.config.new_windows_in_front {
+                self.bring_window_to_front(window);
+                self.focus_window(window);
+            }
+            else if !window_type.is_floating() {
+                self.send_window_to_back(window);
+            }
+        }
+
+        Ok(())
+    }
+
+    // a synthetic ConfigureNotify saying where the window is on the screen, after we've moved it.
+    // the server's own only arrives if something actually changed, and clients like xterm need
+    // one either way to work out their absolute position
+    fn send_configure_notify(&self, window: x::Window, geometry: Geometry, border_width: u32) {
+        let event = x::ConfigureNotifyEvent::new(window, window, x::WINDOW_NONE,
+            geometry.x as i16, geometry.y as i16, geometry.width as u16, geometry.height as u16,
+            border_width as u16, false);
+        self.conn.send_request_checked(&x::SendEvent {
+            propagate: false,
+            destination: x::SendEventDest::Window(window),
+            event_mask: x::EventMask::STRUCTURE_NOTIFY,
+            event: &event,
+        });
+    }
+
+    // whether bring_window_to_front would leave it where it is: the only things above it are
+    // the ones that get put back above it anyway
+    fn in_front(&self, window: x::Window) -> bool {
+        let Some(w) = self.windows.get(&window) else {
+            return false;
+        };
+        if w.below {
+            return true;
+        }
+        let Some(pos) = self.stacking.iter().position(|&s| s == window) else {
+            return false;
+        };
+
+        let tiled = self.config.tiling && !w.floating;
+        self.stacking[pos+1..].iter()
+            .filter_map(|s| self.windows.get(s))
+            .filter(|s| s.mapped && !s.hidden)
+            .all(|s| {
+                s.above ||
+                    (self.config.raise_transients && s.transient_for == Some(window)) ||
+                    (tiled && s.floating)
+            })
+    }
+
+    fn bring_window_to_front(&mut self, window: x::Window) {
+        // it asked to stay under everything
+        if self.windows.get(&window).is_some_and(|w| w.below) {
+            return;
+        }
+
+        self.conn.send_request_checked(&x::ConfigureWindow {
+            window,
+            value_list: &[
+                x::ConfigWindow::StackMode(x::StackMode::Above),
             ],
         });
         debug!("raised {:?}", window);
+        self.restacked(window, x::Place::OnTop);
+
+        // keep its dialogs above it, in the order they were already in, unless they're to be
+        // left where they are
+        let transients: Vec<_> = self.stacking.iter()
+            .filter_map(|w| self.windows.get(w))
+            .filter(|w| self.config.raise_transients && w.transient_for == Some(window) && w.mapped && !w.hidden)
+            .map(|w| w.x_window)
+            .collect();
+
+        for transient in transients {
+            self.conn.send_request_checked(&x::ConfigureWindow {
+                window: transient,
+                value_list: &[
+                    x::ConfigWindow::StackMode(x::StackMode::Above),
+                ],
+            });
+            debug!("raised {:?} above its parent", transient);
+            self.restacked(transient, x::Place::OnTop);
+        }
+
+        // tiles don't cover floating windows
+        let tiled = self.windows.get(&window).is_some_and(|w| !w.floating);
+        if self.config.tiling && tiled {
+            let floating: Vec<_> = self.stacking.iter()
+                .filter_map(|w| self.windows.get(w))
+                .filter(|w| w.floating && w.mapped && !w.hidden)
+                .filter(|w| !self.config.raise_transients || w.transient_for != Some(window))
+                .map(|w| w.x_window)
+                .collect();
+
+            for float in floating {
+                self.conn.send_request_checked(&x::ConfigureWindow {
+                    window: float,
+                    value_list: &[
+                        x::ConfigWindow::StackMode(x::StackMode::Above),
+                    ],
+                });
+                self.restacked(float, x::Place::OnTop);
+            }
+        }
+
+        // and nothing covers the ones that asked to stay over everything
+        let above: Vec<_> = self.stacking.iter()
+            .filter_map(|w| self.windows.get(w))
+            .filter(|w| w.above && w.mapped && !w.hidden && w.x_window != window)
+            .map(|w| w.x_window)
+            .collect();
+
+        for over in above {
+            self.conn.send_request_checked(&x::ConfigureWindow {
+                window: over,
+                value_list: &[
+                    x::ConfigWindow::StackMode(x::StackMode::Above),
+                ],
+            });
+            self.restacked(over, x::Place::OnTop);
+        }
     }
 
-    fn focus_window(&mut self, window: x::Window) {
-        self.conn.send_request_checked(&x::SetInputFocus {
-            revert_to: x::InputFocus::PointerRoot,
-            focus: window,
-            time: x::CURRENT_TIME,
+    fn send_window_to_back(&mut self, window: x::Window) {
+        self.conn.send_request_checked(&x::ConfigureWindow {
+            window,
+            value_list: &[
+                x::ConfigWindow::StackMode(x::StackMode::Below),
+            ],
+        });
+        debug!("lowered {:?}", window);
+        self.restacked(window, x::Place::OnBottom);
+    }
+
+    fn stack_below(&mut self, window: x::Window, sibling: x::Window) {
+        self.conn.send_request_checked(&x::ConfigureWindow {
+            window,
+            value_list: &[
+                x::ConfigWindow::Sibling(sibling),
+                x::ConfigWindow::StackMode(x::StackMode::Below),
+            ],
         });
-        debug!("focused {:?}", window);
+        debug!("put {:?} below {:?}", window, sibling);
+
+        self.stacking.retain(|&w| w != window);
+        if let Some(i) = self.stacking.iter().position(|&w| w == sibling) {
+            self.stacking.insert(i, window);
+        }
+    }
+
+    // keep our idea of the stacking order up to date
+    fn restacked(&mut self, window: x::Window, place: x::Place) {
+        if !self.windows.contains_key(&window) {
+            return;
+        }
+
+        self.stacking.retain(|&w| w != window);
+        match place {
+            x::Place::OnTop => self.stacking.push(window),
+            x::Place::OnBottom => self.stacking.insert(0, window),
+        }
+    }
+
+    // ICCCM's input models: clients that take input get it set on them, and ones with
+    // WM_TAKE_FOCUS are also told, so they can move it to one of their own windows
+    fn focus_window(&mut self, window: x::Window) {
+        let (input, take_focus) = self.windows.get(&window).map_or((true, false), |w| (w.input, w.take_focus));
+
+        if input {
+            self.conn.send_request_checked(&x::SetInputFocus {
+                revert_to: x::InputFocus::PointerRoot,
+                focus: window,
+                time: self.last_time,
+            });
+        }
+
+        if take_focus {
+            let event = x::ClientMessageEvent::new(window, self.atoms.wm_protocols,
+                x::ClientMessageData::Data32([self.atoms.wm_take_focus.resource_id(), self.last_time, 0, 0, 0]));
+            self.conn.send_request_checked(&x::SendEvent {
+                propagate: false,
+                destination: x::SendEventDest::Window(window),
+                event_mask: x::EventMask::NO_EVENT,
+                event: &event,
+            });
+        }
+
+        debug!("focused {:?}{}", window, if input { "" } else { ", which takes focus itself" });
+    }
+
+    // focus moved by something other than the pointer, so it shouldn't follow the pointer back
+    // until it moves
+    fn focus_by_keyboard(&mut self, window: x::Window) -> xcb::Result<()> {
+        self.focus_window(window);
+        self.hold_focus_from_pointer()
+    }
+
+    fn hold_focus_from_pointer(&mut self) -> xcb::Result<()> {
+        let pointer = self.wait_for_reply(self.conn.send_request(&x::QueryPointer {
+            window: self.screen.root(),
+        }))?;
+
+        self.pointer_at_focus = Some((pointer.root_x(), pointer.root_y()));
+
+        Ok(())
+    }
+
+    // focus the topmost window showing on the active monitor, or nothing if it's empty, rather
+    // than whatever the pointer happens to be left over
+    fn focus_top_window(&mut self) -> xcb::Result<()> {
+        let monitor = self.active_monitor()?;
+        self.focus_top_window_on(monitor)
+    }
+
+    fn focus_top_window_on(&mut self, monitor: usize) -> xcb::Result<()> {
+        let top = self.stacking.iter().rev()
+            .filter_map(|w| self.windows.get(w))
+            .find(|w| w.monitor == monitor && self.is_visible(w))
+            .map(|w| w.x_window);
+
+        match top {
+            Some(window) => self.focus_by_keyboard(window)?,
+            None => {
+                self.conn.send_request_checked(&x::SetInputFocus {
+                    revert_to: x::InputFocus::PointerRoot,
+                    focus: self.screen.root(),
+                    time: self.last_time,
+                });
+                self.focused = None;
+                self.hold_focus_from_pointer()?;
+            },
+        }
+
+        Ok(())
+    }
+}
+
+// a gravity as a number, from WM_NORMAL_HINTS or _NET_MOVERESIZE_WINDOW
+fn gravity_from(value: u32) -> x::Gravity {
+    match value {
+        2 => x::Gravity::North,
+        3 => x::Gravity::NorthEast,
+        4 => x::Gravity::West,
+        5 => x::Gravity::Center,
+        6 => x::Gravity::East,
+        7 => x::Gravity::SouthWest,
+        8 => x::Gravity::South,
+        9 => x::Gravity::SouthEast,
+        10 => x::Gravity::Static,
+        _ => x::Gravity::NorthWest,
+    }
+}
+
+// the server time an event happened, for the ones that say
+fn event_time(event: &xcb::Event) -> Option<x::Timestamp> {
+    match event {
+        xcb::Event::X(x::Event::KeyPress(ev)) => Some(ev.time()),
+        xcb::Event::X(x::Event::KeyRelease(ev)) => Some(ev.time()),
+        xcb::Event::X(x::Event::ButtonPress(ev)) => Some(ev.time()),
+        xcb::Event::X(x::Event::ButtonRelease(ev)) => Some(ev.time()),
+        xcb::Event::X(x::Event::MotionNotify(ev)) => Some(ev.time()),
+        xcb::Event::X(x::Event::EnterNotify(ev)) => Some(ev.time()),
+        xcb::Event::X(x::Event::LeaveNotify(ev)) => Some(ev.time()),
+        xcb::Event::X(x::Event::PropertyNotify(ev)) => Some(ev.time()),
+        _ => None,
+    }
+}
+
+// _NET_WM_STATE_REMOVE (0), _NET_WM_STATE_ADD (1), _NET_WM_STATE_TOGGLE (2)
+fn apply_state_action(action: u32, current: bool) -> bool {
+    match action {
+        0 => false,
+        1 => true,
+        2 => !current,
+        _ => current,
+    }
+}
+
+// run a shell command without waiting for it; a thread reaps it so it doesn't linger as a zombie
+fn spawn(command: &str) {
+    debug!("running {:?}", command);
+
+    match Command::new("sh").arg("-c").arg(command).spawn() {
+        Ok(mut child) => {
+            thread::spawn(move || child.wait());
+        },
+        Err(e) => warn!("couldn't run {:?}: {}", command, e),
+    }
+}
+
+fn hostname() -> Option<String> {
+    let mut buf = [0u8; 256];
+    if unsafe { libc::gethostname(buf.as_mut_ptr() as *mut libc::c_char, buf.len()) } < 0 {
+        return None;
     }
+    let len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+    Some(String::from_utf8_lossy(&buf[..len]).into_owned())
+}
+
+// how much later a is than b, allowing for server time wrapping
+fn time_since(a: u32, b: u32) -> i32 {
+    a.wrapping_sub(b) as i32
 }
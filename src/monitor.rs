@@ -0,0 +1,71 @@
+// how many pixels to use for each one asked for, from a monitor's width in pixels and in
+// millimetres: one per 96dpi, rounded, so 2 from about 144dpi. 1 if it doesn't know its size,
+// and no more than 4 in case it's lying
+pub fn scale_for(pixels: u32, millimetres: u32) -> u32 {
+    if millimetres == 0 {
+        return 1;
+    }
+
+    let dpi = pixels as f64 * 25.4 / millimetres as f64;
+    (dpi / 96.0).round().clamp(1.0, 4.0) as u32
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Monitor {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl Monitor {
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        x >= self.x && x < self.x + self.width &&
+        y >= self.y && y < self.y + self.height
+    }
+
+    // squared distance from the point to the nearest edge, 0 if inside
+    pub fn distance_to(&self, x: i32, y: i32) -> i64 {
+        let dx = (self.x - x).max(0).max(x - (self.x + self.width - 1)) as i64;
+        let dy = (self.y - y).max(0).max(y - (self.y + self.height - 1)) as i64;
+        dx*dx + dy*dy
+    }
+
+    // smaller by this much at each edge, but never less than a pixel across
+    pub fn inset(&self, top: i32, bottom: i32, left: i32, right: i32) -> Monitor {
+        let width = (self.width - left - right).max(1);
+        let height = (self.height - top - bottom).max(1);
+        Monitor {
+            x: self.x + left.min(self.width - width),
+            y: self.y + top.min(self.height - height),
+            width,
+            height,
+        }
+    }
+
+    // move a rect so it fits inside the monitor, preferring the top-left if too big
+    pub fn clamp(&self, x: i32, y: i32, width: i32, height: i32) -> (i32, i32) {
+        let new_x = x.min(self.x + self.width - width).max(self.x);
+        let new_y = y.min(self.y + self.height - height).max(self.y);
+        (new_x, new_y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scale_from_dpi() {
+        // 24" 1080p, 27" 4k, 13" 3k laptop
+        assert_eq!(scale_for(1920, 531), 1);
+        assert_eq!(scale_for(3840, 597), 2);
+        assert_eq!(scale_for(3000, 285), 3);
+    }
+
+    #[test]
+    fn scale_without_size() {
+        assert_eq!(scale_for(3840, 0), 1);
+        assert_eq!(scale_for(3840, 10), 4);
+    }
+}
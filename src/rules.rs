@@ -0,0 +1,221 @@
+use regex::Regex;
+
+use crate::config::{self, Length};
+
+// what to do with windows that match, from "rule = ..." in the config. a rule is words of
+// matches and effects, and values with spaces go in double quotes:
+//
+//   rule = class=Firefox workspace=2
+//   rule = class=Firefox title="^Picture-in-Picture$" float
+//
+// class= is the class half of WM_CLASS, exactly. title= is a regex searched for anywhere in
+// _NET_WM_NAME, or WM_NAME if there isn't one. a rule with both only matches when both do.
+// effects are float, tile, workspace=N and monitor=N, numbered from 1, size=WxH like
+// default_size, and keep_aspect, which maximizes to the biggest size with the window's aspect
+// ratio rather than stretching it, for video players. ignore leaves the window entirely alone:
+// it's mapped as it is and never managed, for things like wallpaper setters and compositor
+// overlays that aren't override-redirect but should be. pin_width=N, in pixels or percent of
+// the monitor, keeps a tiled window that wide in a column down the left, with the other tiles
+// sharing what's left, for things like chat rosters.
+//
+// rules are checked when a window is mapped, and ones with a title are checked again whenever
+// the title changes, applying when they start matching. if rules disagree the later one in the
+// config wins, so put broad class rules before narrower title ones.
+#[derive(Debug)]
+pub struct Rule {
+    pub class: Option<String>,
+    pub title: Option<Regex>,
+
+    pub floating: Option<bool>,
+    pub workspace: Option<usize>,
+    pub monitor: Option<usize>,
+    pub size: Option<(Length, Length)>,
+    pub keep_aspect: bool,
+    pub ignore: bool,
+    pub pin_width: Option<Length>,
+}
+
+// regexes don't compare, but the text they came from does
+impl PartialEq for Rule {
+    fn eq(&self, other: &Rule) -> bool {
+        self.class == other.class &&
+            self.title.as_ref().map(|r| r.as_str()) == other.title.as_ref().map(|r| r.as_str()) &&
+            self.floating == other.floating &&
+            self.workspace == other.workspace &&
+            self.monitor == other.monitor &&
+            self.size == other.size &&
+            self.keep_aspect == other.keep_aspect &&
+            self.ignore == other.ignore &&
+            self.pin_width == other.pin_width
+    }
+}
+
+impl Rule {
+    pub fn parse(value: &str) -> Result<Rule, String> {
+        let mut rule = Rule {
+            class: None,
+            title: None,
+            floating: None,
+            workspace: None,
+            monitor: None,
+            size: None,
+            keep_aspect: false,
+            ignore: false,
+            pin_width: None,
+        };
+
+        // numbered from 1 in the config
+        let index = |name: &str, value: &str| -> Result<usize, String> {
+            match value.parse::<usize>() {
+                Ok(n) if n > 0 => Ok(n - 1),
+                _ => Err(format!("{} needs a number, got {:?}", name, value)),
+            }
+        };
+
+        for word in split_words(value)? {
+            match word.split_once('=') {
+                Some(("class", class)) => rule.class = Some(class.to_string()),
+                Some(("title", title)) => {
+                    rule.title = Some(Regex::new(title).map_err(|e| format!("bad title regex: {}", e))?);
+                },
+                Some(("workspace", n)) => rule.workspace = Some(index("workspace", n)?),
+                Some(("monitor", n)) => rule.monitor = Some(index("monitor", n)?),
+                Some(("size", size)) => rule.size = Some(config::parse_size(size)?),
+                Some(("pin_width", width)) => {
+                    rule.pin_width = Some(config::parse_length(width).ok_or(format!("pin_width needs a width like 300 or 20%, got {:?}", width))?);
+                },
+                None if word == "float" => rule.floating = Some(true),
+                None if word == "tile" => rule.floating = Some(false),
+                None if word == "keep_aspect" => rule.keep_aspect = true,
+                None if word == "ignore" => rule.ignore = true,
+                _ => return Err(format!("unknown rule part {:?}", word)),
+            }
+        }
+
+        if rule.class.is_none() && rule.title.is_none() {
+            return Err("rule needs a class= or title= to match".to_string());
+        }
+
+        Ok(rule)
+    }
+
+    pub fn matches(&self, class: Option<&str>, title: Option<&str>) -> bool {
+        let class_matches = match &self.class {
+            Some(c) => class == Some(c.as_str()),
+            None => true,
+        };
+        let title_matches = match &self.title {
+            Some(r) => title.is_some_and(|t| r.is_match(t)),
+            None => true,
+        };
+        class_matches && title_matches
+    }
+}
+
+// on whitespace, except inside double quotes, which are removed
+fn split_words(value: &str) -> Result<Vec<String>, String> {
+    let mut words = vec![];
+    let mut word = String::new();
+    let mut quoted = false;
+    let mut started = false;
+
+    for c in value.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                started = true;
+            },
+            c if c.is_whitespace() && !quoted => {
+                if started {
+                    words.push(std::mem::take(&mut word));
+                    started = false;
+                }
+            },
+            c => {
+                word.push(c);
+                started = true;
+            },
+        }
+    }
+
+    if quoted {
+        return Err("unclosed quote".to_string());
+    }
+    if started {
+        words.push(word);
+    }
+
+    Ok(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_class_rule() {
+        let rule = Rule::parse("class=Firefox workspace=2 float").unwrap();
+        assert_eq!(rule.class.as_deref(), Some("Firefox"));
+        assert!(rule.title.is_none());
+        assert_eq!(rule.workspace, Some(1));
+        assert_eq!(rule.floating, Some(true));
+        assert_eq!(rule.monitor, None);
+        assert_eq!(rule.size, None);
+        assert!(!rule.keep_aspect);
+        assert!(!rule.ignore);
+        assert_eq!(rule.pin_width, None);
+    }
+
+    #[test]
+    fn parse_pin_width() {
+        assert_eq!(Rule::parse("class=Pidgin pin_width=300").unwrap().pin_width, Some(Length::Pixels(300)));
+        assert_eq!(Rule::parse("class=Pidgin pin_width=20%").unwrap().pin_width, Some(Length::Percent(20)));
+        assert!(Rule::parse("class=Pidgin pin_width=0").is_err());
+        assert!(Rule::parse("class=Pidgin pin_width=wide").is_err());
+    }
+
+    #[test]
+    fn parse_size() {
+        let rule = Rule::parse("class=mpv size=50%x720").unwrap();
+        assert_eq!(rule.size, Some((Length::Percent(50), Length::Pixels(720))));
+        assert!(Rule::parse("class=mpv size=50%").is_err());
+        assert!(Rule::parse("class=mpv size=0x100").is_err());
+        assert!(Rule::parse("class=mpv size=150%x100").is_err());
+    }
+
+    #[test]
+    fn parse_quoted_title() {
+        let rule = Rule::parse(r#"title="^Picture in .*$" monitor=2 tile"#).unwrap();
+        assert_eq!(rule.title.unwrap().as_str(), "^Picture in .*$");
+        assert_eq!(rule.monitor, Some(1));
+        assert_eq!(rule.floating, Some(false));
+        assert!(Rule::parse("class=mpv keep_aspect").unwrap().keep_aspect);
+        assert!(Rule::parse("class=feh ignore").unwrap().ignore);
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(Rule::parse("float").is_err());
+        assert!(Rule::parse("class=Foo workspace=0").is_err());
+        assert!(Rule::parse("class=Foo sideways").is_err());
+        assert!(Rule::parse(r#"title="(unclosed""#).is_err());
+        assert!(Rule::parse(r#"title="unclosed"#).is_err());
+    }
+
+    #[test]
+    fn class_and_title_both_have_to_match() {
+        let rule = Rule::parse("class=Firefox title=Profile.2").unwrap();
+        assert!(rule.matches(Some("Firefox"), Some("Mozilla Firefox - Profile 2")));
+        assert!(!rule.matches(Some("Firefox"), Some("Mozilla Firefox")));
+        assert!(!rule.matches(Some("Chromium"), Some("Profile 2")));
+        assert!(!rule.matches(Some("Firefox"), None));
+    }
+
+    #[test]
+    fn title_only_matches_any_class() {
+        let rule = Rule::parse("title=^vim").unwrap();
+        assert!(rule.matches(None, Some("vim config.rs")));
+        assert!(rule.matches(Some("XTerm"), Some("vim config.rs")));
+        assert!(!rule.matches(Some("XTerm"), Some("bash")));
+    }
+}
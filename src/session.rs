@@ -0,0 +1,226 @@
+use log::{debug, warn};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::window::Geometry;
+
+fn state_dir() -> Option<PathBuf> {
+    let state_dir = match std::env::var_os("XDG_STATE_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".local/state"),
+    };
+    Some(state_dir.join("panko"))
+}
+
+// window geometries remembered by WM_CLASS, one per line:
+//   class<TAB>x<TAB>y<TAB>width<TAB>height
+fn geometry_path() -> Option<PathBuf> {
+    Some(state_dir()?.join("geometry"))
+}
+
+pub fn load_geometries() -> HashMap<String, Geometry> {
+    let mut geometries = HashMap::default();
+
+    let path = match geometry_path() {
+        Some(p) => p,
+        None => return geometries,
+    };
+
+    let data = match fs::read_to_string(&path) {
+        Ok(d) => d,
+        Err(e) => {
+            debug!("couldn't read {:?}: {}", path, e);
+            return geometries;
+        },
+    };
+
+    for line in data.lines() {
+        match parse_geometry_line(line) {
+            Some((class, geometry)) => {
+                geometries.insert(class, geometry);
+            },
+            None => warn!("ignoring bad line in {:?}: {:?}", path, line),
+        }
+    }
+
+    debug!("loaded {} remembered geometries from {:?}", geometries.len(), path);
+
+    geometries
+}
+
+fn parse_geometry_line(line: &str) -> Option<(String, Geometry)> {
+    let fields: Vec<_> = line.split('\t').collect();
+    match fields[..] {
+        [class, x, y, width, height] => Some((class.to_string(), Geometry {
+            x: x.parse().ok()?,
+            y: y.parse().ok()?,
+            width: width.parse().ok()?,
+            height: height.parse().ok()?,
+        })),
+        _ => None,
+    }
+}
+
+pub fn save_geometries(geometries: &HashMap<String, Geometry>) {
+    let path = match geometry_path() {
+        Some(p) => p,
+        None => return,
+    };
+
+    match write_geometries(&path, geometries) {
+        Ok(()) => debug!("saved {} remembered geometries to {:?}", geometries.len(), path),
+        Err(e) => warn!("couldn't save geometries to {:?}: {}", path, e),
+    }
+}
+
+fn write_geometries(path: &Path, geometries: &HashMap<String, Geometry>) -> io::Result<()> {
+    write_lines(path, geometries.iter()
+        .map(|(class, g)| format!("{}\t{}\t{}\t{}\t{}", class, g.x, g.y, g.width, g.height)))
+}
+
+// write aside and rename, so a crash never leaves a half-written file
+fn write_lines(path: &Path, lines: impl Iterator<Item = String>) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let tmp_path = path.with_extension("tmp");
+    let mut file = fs::File::create(&tmp_path)?;
+    for line in lines {
+        writeln!(file, "{}", line)?;
+    }
+
+    fs::rename(&tmp_path, path)
+}
+
+// what we were showing when we last stopped, so starting again on the same display can pick up
+// where it left off. the workspace showing on each monitor, the tags being viewed, the master
+// width of each workspace that's had it changed, the workspaces that are tabbed, then the
+// windows in tiling order:
+//   workspaces<TAB>0<TAB>3
+//   tags<TAB>5
+//   master<TAB>workspace<TAB>percent
+//   tabbed<TAB>workspace
+//   window<TAB>0x1a00003<TAB>workspace<TAB>floating|tiled<TAB>normal|iconic<TAB>tags
+// window ids only mean anything on the same X server, so the file is removed once it's read.
+// window lines from before the last two fields were added are still read
+#[derive(Debug, Default)]
+pub struct Layout {
+    pub workspaces: Vec<usize>,
+    pub tags: Option<u32>,
+    pub master_widths: HashMap<usize, u32>,
+    pub tabbed: Vec<usize>,
+    pub windows: Vec<SavedWindow>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct SavedWindow {
+    pub id: u32,
+    pub workspace: usize,
+    pub floating: bool,
+    pub iconic: bool,
+    pub tags: Option<u32>,
+}
+
+fn layout_path() -> Option<PathBuf> {
+    Some(state_dir()?.join("layout"))
+}
+
+pub fn take_layout() -> Layout {
+    let mut layout = Layout::default();
+
+    let path = match layout_path() {
+        Some(p) => p,
+        None => return layout,
+    };
+
+    let data = match fs::read_to_string(&path) {
+        Ok(d) => d,
+        Err(e) => {
+            debug!("couldn't read {:?}: {}", path, e);
+            return layout;
+        },
+    };
+    let _ = fs::remove_file(&path);
+
+    for line in data.lines() {
+        let fields: Vec<_> = line.split('\t').collect();
+        let parsed = match fields[..] {
+            ["workspaces", ref workspaces @ ..] => workspaces.iter()
+                .map(|w| w.parse().ok())
+                .collect::<Option<Vec<_>>>()
+                .map(|w| layout.workspaces = w),
+            ["tags", tags] => tags.parse().ok()
+                .filter(|&t| t != 0)
+                .map(|t| layout.tags = Some(t)),
+            ["master", workspace, width] => workspace.parse().ok()
+                .zip(width.parse().ok())
+                .map(|(workspace, width)| {
+                    layout.master_widths.insert(workspace, width);
+                }),
+            ["tabbed", workspace] => workspace.parse().ok()
+                .map(|w| layout.tabbed.push(w)),
+            ["window", id, workspace, floating] => parse_saved_window(id, workspace, floating, "normal", None)
+                .map(|w| layout.windows.push(w)),
+            ["window", id, workspace, floating, state, tags] => parse_saved_window(id, workspace, floating, state, Some(tags))
+                .map(|w| layout.windows.push(w)),
+            _ => None,
+        };
+        if parsed.is_none() {
+            warn!("ignoring bad line in {:?}: {:?}", path, line);
+        }
+    }
+
+    debug!("loaded layout with {} windows from {:?}", layout.windows.len(), path);
+
+    layout
+}
+
+fn parse_saved_window(id: &str, workspace: &str, floating: &str, state: &str, tags: Option<&str>) -> Option<SavedWindow> {
+    Some(SavedWindow {
+        id: u32::from_str_radix(id.strip_prefix("0x")?, 16).ok()?,
+        workspace: workspace.parse().ok()?,
+        floating: match floating {
+            "floating" => true,
+            "tiled" => false,
+            _ => return None,
+        },
+        iconic: match state {
+            "iconic" => true,
+            "normal" => false,
+            _ => return None,
+        },
+        tags: match tags {
+            Some(tags) => Some(tags.parse().ok().filter(|&t| t != 0)?),
+            None => None,
+        },
+    })
+}
+
+pub fn save_layout(layout: &Layout) {
+    let path = match layout_path() {
+        Some(p) => p,
+        None => return,
+    };
+
+    let workspaces = std::iter::once("workspaces".to_string())
+        .chain(layout.workspaces.iter().map(|w| w.to_string()))
+        .collect::<Vec<_>>()
+        .join("\t");
+    let tags = layout.tags.map(|t| format!("tags\t{}", t));
+    let master_widths = layout.master_widths.iter().map(|(workspace, width)| format!("master\t{}\t{}", workspace, width));
+    let tabbed = layout.tabbed.iter().map(|workspace| format!("tabbed\t{}", workspace));
+    let windows = layout.windows.iter().map(|w| {
+        format!("window\t{:#x}\t{}\t{}\t{}\t{}", w.id, w.workspace,
+            if w.floating { "floating" } else { "tiled" },
+            if w.iconic { "iconic" } else { "normal" },
+            w.tags.unwrap_or(1))
+    });
+
+    match write_lines(&path, std::iter::once(workspaces).chain(tags).chain(master_widths).chain(tabbed).chain(windows)) {
+        Ok(()) => debug!("saved layout with {} windows to {:?}", layout.windows.len(), path),
+        Err(e) => warn!("couldn't save layout to {:?}: {}", path, e),
+    }
+}
@@ -0,0 +1,54 @@
+use std::io;
+use std::os::unix::io::RawFd;
+use std::sync::atomic::{AtomicI32, AtomicU64, Ordering};
+
+// signals are turned into a byte on a pipe so the main loop can poll for them alongside the X
+// connection. the handler only does async-signal-safe things: set a bit and write a byte.
+
+static PENDING: AtomicU64 = AtomicU64::new(0);
+static WRITE_FD: AtomicI32 = AtomicI32::new(-1);
+
+extern "C" fn handler(signal: libc::c_int) {
+    PENDING.fetch_or(1 << signal, Ordering::SeqCst);
+
+    let fd = WRITE_FD.load(Ordering::SeqCst);
+    if fd >= 0 {
+        let byte = 0u8;
+        unsafe { libc::write(fd, &byte as *const u8 as *const libc::c_void, 1) };
+    }
+}
+
+// catch the given signals, returning the fd that becomes readable when one arrives
+pub fn install(signals: &[libc::c_int]) -> io::Result<RawFd> {
+    let mut fds = [0 as libc::c_int; 2];
+    if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_NONBLOCK | libc::O_CLOEXEC) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    WRITE_FD.store(fds[1], Ordering::SeqCst);
+
+    for &signal in signals {
+        unsafe {
+            let mut action: libc::sigaction = std::mem::zeroed();
+            action.sa_sigaction = handler as extern "C" fn(libc::c_int) as libc::sighandler_t;
+            action.sa_flags = libc::SA_RESTART;
+            libc::sigemptyset(&mut action.sa_mask);
+
+            if libc::sigaction(signal, &action, std::ptr::null_mut()) < 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+    }
+
+    Ok(fds[0])
+}
+
+// empty the pipe once woken, the pending bits say what actually arrived
+pub fn drain(fd: RawFd) {
+    let mut buf = [0u8; 64];
+    while unsafe { libc::read(fd, buf.as_mut_ptr() as *mut libc::c_void, buf.len()) } > 0 {}
+}
+
+// whether the signal has arrived since we last asked
+pub fn take(signal: libc::c_int) -> bool {
+    PENDING.fetch_and(!(1 << signal), Ordering::SeqCst) & (1 << signal) != 0
+}
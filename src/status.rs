@@ -0,0 +1,87 @@
+use log::debug;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::PathBuf;
+
+// a line of state for an external bar, written whenever it changes:
+//
+//   <workspaces><TAB><title>
+//
+// workspaces are those of the monitor with focus, numbered from 1 and separated by spaces,
+// marked as
+//
+//   [2]  showing
+//   3!   has a window that wants attention
+//   1+   has windows
+//   5    empty
+//
+// and title is the focused window's title, empty if nothing has focus. eg
+//
+//   1+ [2] 3! 4 5 6 7 8 9	vim config
+pub struct Status {
+    target: Target,
+    file: Option<File>,
+    last: Option<String>,
+}
+
+enum Target {
+    Stdout,
+    Path(PathBuf),
+}
+
+impl Status {
+    // "stdout", or the path of a file or named pipe
+    pub fn new(output: &str) -> Status {
+        let target = match output {
+            "stdout" | "-" => Target::Stdout,
+            path => Target::Path(PathBuf::from(path)),
+        };
+
+        Status {
+            target,
+            file: None,
+            last: None,
+        }
+    }
+
+    pub fn update(&mut self, line: String) {
+        if self.last.as_ref() == Some(&line) {
+            return;
+        }
+
+        if let Err(e) = self.write(&line) {
+            debug!("couldn't write status: {}", e);
+
+            // reopen next time, the reader may have gone away
+            self.file = None;
+        }
+
+        self.last = Some(line);
+    }
+
+    fn write(&mut self, line: &str) -> io::Result<()> {
+        match self.target {
+            Target::Stdout => {
+                let mut stdout = io::stdout().lock();
+                writeln!(stdout, "{}", line)?;
+                stdout.flush()
+            },
+
+            Target::Path(ref path) => {
+                if self.file.is_none() {
+                    // non-blocking, so a bar that stops reading can't hang us; a pipe with no
+                    // reader fails to open and we try again on the next update
+                    self.file = Some(OpenOptions::new()
+                        .append(true)
+                        .create(true)
+                        .custom_flags(libc::O_NONBLOCK)
+                        .open(path)?);
+                }
+
+                let file = self.file.as_mut().unwrap();
+                file.write_all(format!("{}\n", line).as_bytes())
+            },
+        }
+    }
+}
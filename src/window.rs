@@ -1,5 +1,235 @@
 use xcb::x;
 
+use crate::config::Length;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Geometry {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Geometry {
+    // the window at a new size and border width, moved so that the point its win_gravity names
+    // stays where it was, as ICCCM asks. the position is the outside of the border, as X has it
+    pub fn resized(&self, border: u32, width: u32, height: u32, new_border: u32, gravity: x::Gravity) -> Geometry {
+        // how far the far edge of the outside comes in
+        let dx = (self.width + 2*border) as i32 - (width + 2*new_border) as i32;
+        let dy = (self.height + 2*border) as i32 - (height + 2*new_border) as i32;
+
+        // static keeps the inside where it was, so only a change of border moves it
+        let inside = border as i32 - new_border as i32;
+
+        let (x, y) = match gravity {
+            x::Gravity::BitForget | x::Gravity::NorthWest => (0, 0),
+            x::Gravity::North => (dx/2, 0),
+            x::Gravity::NorthEast => (dx, 0),
+            x::Gravity::West => (0, dy/2),
+            x::Gravity::Center => (dx/2, dy/2),
+            x::Gravity::East => (dx, dy/2),
+            x::Gravity::SouthWest => (0, dy),
+            x::Gravity::South => (dx/2, dy),
+            x::Gravity::SouthEast => (dx, dy),
+            x::Gravity::Static => (inside, inside),
+        };
+
+        Geometry {
+            x: self.x + x,
+            y: self.y + y,
+            width,
+            height,
+        }
+    }
+}
+
+// what _NET_WM_WINDOW_TYPE says, for the types we treat differently
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WindowType {
+    Normal,
+    Splash,
+    Dialog,
+    Utility,
+    Toolbar,
+    Menu,
+}
+
+impl WindowType {
+    // small helper windows that float over their parent rather than being laid out
+    pub fn is_floating(&self) -> bool {
+        matches!(self, WindowType::Dialog | WindowType::Utility | WindowType::Toolbar | WindowType::Menu)
+    }
+}
+
 pub struct Window {
     pub x_window: x::Window,
+
+    pub class: Option<String>,
+    pub title: Option<String>,
+
+    // _NET_WM_PID, and WM_CLIENT_MACHINE to say where that pid means anything
+    pub pid: Option<i32>,
+    pub client_machine: Option<String>,
+    // times we've been asked to close it, each one less polite than the last
+    pub close_attempts: u32,
+    pub window_type: WindowType,
+    // WM_TRANSIENT_FOR, the window this one is a dialog for
+    pub transient_for: Option<x::Window>,
+
+    // client asked to be shown and we're managing it
+    pub mapped: bool,
+    // we unmapped it because its workspace isn't showing, or it's minimized
+    pub hidden: bool,
+    // minimized, by the user or because it asked to start that way
+    pub iconic: bool,
+    // completely covered by other windows, going by the last VisibilityNotify. border changes
+    // can wait until it shows
+    pub obscured: bool,
+    // unmaps we caused, so we don't mistake them for the client withdrawing
+    pub ignore_unmaps: u32,
+
+    // last position and size the server told us about
+    pub geometry: Option<Geometry>,
+
+    pub monitor: usize,
+    pub workspace: usize,
+    // bit per tag; it shows if any of them are being viewed, as well as being on the workspace
+    pub tags: u32,
+    // what _NET_WM_DESKTOP says, so it's only set when the workspace changes
+    pub published_workspace: Option<usize>,
+
+    // left out of tiling, keeping whatever geometry it's given
+    pub floating: bool,
+    // tiled in a column this wide at the left of the monitor, from a pin_width rule
+    pub pinned_width: Option<Length>,
+
+    // config rules it matched when we last looked, so title rules only apply as they start
+    // matching
+    pub matched_rules: Vec<usize>,
+
+    pub maximized_horz: bool,
+    pub maximized_vert: bool,
+    // where it was before being maximized or zoomed, so we can put it back
+    pub saved_geometry: Option<Geometry>,
+
+    // filling the monitor without a border at the user's say, unlike fullscreen which the
+    // client asks for
+    pub zoomed: bool,
+    // width to height, if WM_NORMAL_HINTS asks for one; keep_aspect rules use it when maximizing
+    pub aspect: Option<(u32, u32)>,
+    // from WM_NORMAL_HINTS, which point to keep still when we change its size or border
+    pub gravity: x::Gravity,
+    // and how small it can go, which resizing stops at
+    pub min_size: Option<(u32, u32)>,
+    // its minimum and maximum sizes are the same, so there's no resizing it
+    pub fixed_size: bool,
+
+    pub fullscreen: bool,
+    // separate from saved_geometry, so it can be fullscreen and maximized underneath
+    pub fullscreen_geometry: Option<Geometry>,
+
+    // wants the user to look at it, until it gets focus
+    pub demands_attention: bool,
+
+    // takes keyboard focus when we give it, from the input field of WM_HINTS
+    pub input: bool,
+    // has WM_TAKE_FOCUS in WM_PROTOCOLS, so it's told when it gets focus and can put it where
+    // it wants, even if it doesn't take input itself
+    pub take_focus: bool,
+
+    // on whatever workspace its monitor is showing
+    pub sticky: bool,
+    // kept over or under everything else, like a clock or a desktop widget
+    pub above: bool,
+    pub below: bool,
+    // _NET_WM_STATE atoms the client set that we don't act on, like SKIP_TASKBAR. they stay on
+    // the property for pagers and taskbars when we rewrite it. raw, as they came
+    pub other_states: Vec<u32>,
+    // the only window showing on its monitor, so smart_borders has taken its border away
+    pub lone: bool,
+
+    // _NET_WM_WINDOW_OPACITY, for a compositor to use; 0xffffffff is opaque
+    pub opacity: u32,
+    // what's on the property now, which is dimmed from opacity while it's unfocused
+    pub shown_opacity: u32,
+}
+
+impl Window {
+    pub fn new(x_window: x::Window) -> Window {
+        Window {
+            x_window,
+            class: None,
+            title: None,
+            pid: None,
+            client_machine: None,
+            close_attempts: 0,
+            window_type: WindowType::Normal,
+            transient_for: None,
+            mapped: false,
+            hidden: false,
+            iconic: false,
+            obscured: false,
+            ignore_unmaps: 0,
+            geometry: None,
+            monitor: 0,
+            workspace: 0,
+            tags: 1,
+            published_workspace: None,
+            floating: false,
+            pinned_width: None,
+            matched_rules: vec![],
+            maximized_horz: false,
+            maximized_vert: false,
+            saved_geometry: None,
+            zoomed: false,
+            aspect: None,
+            gravity: x::Gravity::NorthWest,
+            min_size: None,
+            fixed_size: false,
+            fullscreen: false,
+            fullscreen_geometry: None,
+            demands_attention: false,
+            input: true,
+            take_focus: false,
+            sticky: false,
+            above: false,
+            below: false,
+            other_states: vec![],
+            lone: false,
+            opacity: u32::MAX,
+            shown_opacity: u32::MAX,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WINDOW: Geometry = Geometry { x: 100, y: 100, width: 200, height: 100 };
+
+    fn rect(g: Geometry) -> (i32, i32, u32, u32) {
+        (g.x, g.y, g.width, g.height)
+    }
+
+    #[test]
+    fn northwest_keeps_position() {
+        assert_eq!(rect(WINDOW.resized(2, 300, 50, 2, x::Gravity::NorthWest)), (100, 100, 300, 50));
+    }
+
+    #[test]
+    fn southeast_keeps_far_corner() {
+        assert_eq!(rect(WINDOW.resized(2, 300, 50, 2, x::Gravity::SouthEast)), (0, 150, 300, 50));
+        assert_eq!(rect(WINDOW.resized(0, 200, 100, 4, x::Gravity::SouthEast)), (92, 92, 200, 100));
+    }
+
+    #[test]
+    fn center_keeps_middle() {
+        assert_eq!(rect(WINDOW.resized(0, 100, 200, 0, x::Gravity::Center)), (150, 50, 100, 200));
+    }
+
+    #[test]
+    fn static_keeps_inside() {
+        assert_eq!(rect(WINDOW.resized(1, 300, 300, 5, x::Gravity::Static)), (96, 96, 300, 300));
+    }
 }
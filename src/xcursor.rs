@@ -0,0 +1,186 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// cursor images from Xcursor themes, found and read the way libXcursor does it so our cursors
+// match everyone else's. a theme is a directory in the search path with the cursors in a
+// cursors/ directory, one file per name, and an index.theme that can name themes to fall back on
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub xhot: u32,
+    pub yhot: u32,
+    // premultiplied ARGB, a row at a time
+    pub pixels: Vec<u32>,
+}
+
+const MAGIC: &[u8] = b"Xcur";
+const IMAGE_CHUNK: u32 = 0xfffd0002;
+const MAX_SIZE: u32 = 0x7fff;
+
+// the image with the nominal size closest to the one asked for, or the first frame of it if
+// it's animated
+pub fn parse(data: &[u8], size: u32) -> Option<Image> {
+    let u32_at = |offset: usize| {
+        data.get(offset..offset.checked_add(4)?).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    };
+
+    if data.get(0..4)? != MAGIC {
+        return None;
+    }
+
+    // header: magic, header length, version, table of contents length. each entry is the chunk
+    // type, its subtype (the nominal size for images) and where it is in the file
+    let toc = u32_at(4)? as usize;
+    let entries = u32_at(12)? as usize;
+    let (_, _, chunk) = (0..entries)
+        .map_while(|i| {
+            let entry = toc + i*12;
+            Some((u32_at(entry)?, u32_at(entry + 4)?, u32_at(entry + 8)? as usize))
+        })
+        .filter(|&(kind, _, _)| kind == IMAGE_CHUNK)
+        .min_by_key(|&(_, nominal, _)| nominal.abs_diff(size))?;
+
+    // chunk header: header length, type, subtype, version, then width, height, hotspot and
+    // delay, and the pixels after the header
+    let (width, height) = (u32_at(chunk + 16)?, u32_at(chunk + 20)?);
+    let (xhot, yhot) = (u32_at(chunk + 24)?, u32_at(chunk + 28)?);
+    if width == 0 || height == 0 || width > MAX_SIZE || height > MAX_SIZE || xhot >= width || yhot >= height {
+        return None;
+    }
+
+    let start = chunk + u32_at(chunk)? as usize;
+    let pixels = data.get(start..start + width as usize*height as usize*4)?
+        .chunks_exact(4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect();
+
+    Some(Image {
+        width,
+        height,
+        xhot,
+        yhot,
+        pixels,
+    })
+}
+
+// XCURSOR_PATH, or the same default libXcursor uses
+fn search_path() -> Vec<PathBuf> {
+    let path = env::var("XCURSOR_PATH")
+        .unwrap_or_else(|_| "~/.local/share/icons:~/.icons:/usr/share/icons:/usr/share/pixmaps".to_string());
+    let home = env::var_os("HOME").map(PathBuf::from);
+
+    path.split(':')
+        .filter(|dir| !dir.is_empty())
+        .filter_map(|dir| match dir.strip_prefix("~/") {
+            Some(rest) => home.as_ref().map(|home| home.join(rest)),
+            None => Some(PathBuf::from(dir)),
+        })
+        .collect()
+}
+
+// the themes named by Inherits= in a theme's index.theme
+fn inherits(index: &Path) -> Vec<String> {
+    let Ok(text) = fs::read_to_string(index) else {
+        return vec![];
+    };
+
+    text.lines()
+        .find_map(|line| line.strip_prefix("Inherits")?.trim_start().strip_prefix('='))
+        .map(|names| {
+            names.split([',', ';'])
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .map(String::from)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// a cursor from the theme, or failing that the themes it inherits from, and theirs, each looked
+// at once. None if none of them have it
+pub fn load(theme: &str, name: &str, size: u32) -> Option<Image> {
+    let dirs = search_path();
+    let mut themes = vec![theme.to_string()];
+
+    let mut n = 0;
+    while n < themes.len() {
+        for dir in &dirs {
+            if let Some(image) = fs::read(dir.join(&themes[n]).join("cursors").join(name)).ok().and_then(|data| parse(&data, size)) {
+                return Some(image);
+            }
+        }
+
+        for dir in &dirs {
+            for parent in inherits(&dir.join(&themes[n]).join("index.theme")) {
+                if !themes.contains(&parent) {
+                    themes.push(parent);
+                }
+            }
+        }
+        n += 1;
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a cursor file with a square image of each size, every pixel set to its size
+    fn cursor_file(sizes: &[u32]) -> Vec<u8> {
+        let mut toc = vec![];
+        let mut chunks = vec![];
+        let mut position = 16 + 12*sizes.len() as u32;
+
+        for &size in sizes {
+            toc.extend([IMAGE_CHUNK, size, position]);
+            let chunk = [36, IMAGE_CHUNK, size, 1, size, size, size/2, size/2, 0];
+            chunks.extend(chunk);
+            chunks.extend(std::iter::repeat_n(size, (size*size) as usize));
+            position += 4*(chunk.len() as u32 + size*size);
+        }
+
+        let mut data = MAGIC.to_vec();
+        for word in [16, 0x10000, sizes.len() as u32].into_iter().chain(toc).chain(chunks) {
+            data.extend(word.to_le_bytes());
+        }
+        data
+    }
+
+    #[test]
+    fn picks_closest_size() {
+        let data = cursor_file(&[24, 32, 48]);
+
+        let image = parse(&data, 30).unwrap();
+        assert_eq!((image.width, image.height, image.xhot, image.yhot), (32, 32, 16, 16));
+        assert_eq!(image.pixels, vec![32; 32*32]);
+
+        assert_eq!(parse(&data, 16).unwrap().width, 24);
+        assert_eq!(parse(&data, 96).unwrap().width, 48);
+    }
+
+    #[test]
+    fn rejects_bad_files() {
+        let data = cursor_file(&[24]);
+        assert!(parse(&data[..data.len() - 1], 24).is_none());
+        assert!(parse(&data[4..], 24).is_none());
+        assert!(parse(b"Xcur", 24).is_none());
+        assert!(parse(&cursor_file(&[]), 24).is_none());
+    }
+
+    #[test]
+    fn reads_inherits() {
+        let dir = std::env::temp_dir().join(format!("panko-xcursor-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let index = dir.join("index.theme");
+
+        fs::write(&index, "[Icon Theme]\nName=Thing\nInherits = Adwaita, core;\n").unwrap();
+        assert_eq!(inherits(&index), vec!["Adwaita", "core"]);
+        assert!(inherits(&dir.join("missing")).is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
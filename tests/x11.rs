@@ -0,0 +1,713 @@
+// end-to-end tests against a real X server. each test starts its own Xvfb with panko running on it,
+// then plays client over a second connection and checks what panko did to its windows. tests pass
+// without doing anything if Xvfb isn't installed, so they're safe to run anywhere; CI should
+// install it to get any value out of them.
+
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::os::unix::io::FromRawFd;
+use std::os::unix::process::CommandExt;
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use xcb::{x, Xid};
+
+const SCREEN_WIDTH: i16 = 1280;
+const SCREEN_HEIGHT: i16 = 1024;
+
+// how long to wait for panko to react before giving up
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+struct Session {
+    xvfb: Child,
+    panko: Option<Child>,
+    display: String,
+    dir: PathBuf,
+    conn: xcb::Connection,
+    root: x::Window,
+}
+
+#[derive(Debug, PartialEq)]
+struct Geometry {
+    x: i16,
+    y: i16,
+    width: u16,
+    height: u16,
+    border_width: u16,
+}
+
+impl Session {
+    // Xvfb and panko with the given config file, or None if there's no Xvfb to run
+    fn start(config: &str) -> Option<Session> {
+        let mut session = Session::without_panko(config)?;
+        session.start_panko();
+        Some(session)
+    }
+
+    // just Xvfb, so the test can set things up before panko arrives
+    fn without_panko(config: &str) -> Option<Session> {
+        static SESSIONS: AtomicUsize = AtomicUsize::new(0);
+
+        // somewhere for config and state, so the user's own don't get involved
+        let dir = std::env::temp_dir().join(format!("panko-test-{}-{}",
+            std::process::id(), SESSIONS.fetch_add(1, Ordering::Relaxed)));
+        fs::create_dir_all(dir.join("config/panko")).unwrap();
+        fs::create_dir_all(dir.join("state")).unwrap();
+        fs::write(dir.join("config/panko/config"), config).unwrap();
+
+        let (xvfb, display) = start_xvfb()?;
+
+        let (conn, screen_num) = xcb::Connection::connect(Some(&display)).expect("couldn't connect to Xvfb");
+        let root = conn.get_setup().roots().nth(screen_num as usize).unwrap().root();
+
+        Some(Session {
+            xvfb,
+            panko: None,
+            display,
+            dir,
+            conn,
+            root,
+        })
+    }
+
+    fn start_panko(&mut self) {
+        self.panko = Some(Command::new(env!("CARGO_BIN_EXE_panko"))
+            .env("DISPLAY", &self.display)
+            .env("XDG_CONFIG_HOME", self.dir.join("config"))
+            .env("XDG_STATE_HOME", self.dir.join("state"))
+            .stdout(Stdio::null())
+            .spawn()
+            .expect("couldn't start panko"));
+
+        // panko sets _NET_SUPPORTED once it's taken over the root window
+        let net_supported = self.atom("_NET_SUPPORTED");
+        self.wait_for("panko to start", |s| {
+            let reply = s.conn.wait_for_reply(s.conn.send_request(&x::GetProperty {
+                delete: false,
+                window: s.root,
+                property: net_supported,
+                r#type: x::ATOM_ATOM,
+                long_offset: 0,
+                long_length: 1,
+            })).unwrap();
+            (reply.length() > 0).then_some(())
+        });
+    }
+
+    fn atom(&self, name: &str) -> x::Atom {
+        self.conn.wait_for_reply(self.conn.send_request(&x::InternAtom {
+            only_if_exists: false,
+            name: name.as_bytes(),
+        })).unwrap().atom()
+    }
+
+    // poll until the check returns something, failing the test if it takes too long
+    fn wait_for<T>(&mut self, what: &str, check: impl Fn(&Session) -> Option<T>) -> T {
+        let start = Instant::now();
+
+        loop {
+            if let Some(status) = self.panko.as_mut().and_then(|p| p.try_wait().unwrap()) {
+                panic!("panko exited while waiting for {}: {}", what, status);
+            }
+
+            if let Some(result) = check(self) {
+                return result;
+            }
+
+            if start.elapsed() > TIMEOUT {
+                panic!("timed out waiting for {}", what);
+            }
+
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    fn create_window(&self, width: u16, height: u16) -> x::Window {
+        self.create_window_with(width, height, x::WindowClass::InputOutput, &[])
+    }
+
+    fn create_window_with(&self, width: u16, height: u16, class: x::WindowClass, value_list: &[x::Cw]) -> x::Window {
+        let window = self.conn.generate_id();
+        self.conn.send_and_check_request(&x::CreateWindow {
+            depth: x::COPY_FROM_PARENT as u8,
+            wid: window,
+            parent: self.root,
+            x: 0,
+            y: 0,
+            width,
+            height,
+            border_width: 0,
+            class,
+            visual: x::COPY_FROM_PARENT,
+            value_list,
+        }).unwrap();
+        window
+    }
+
+    fn set_atom_property(&self, window: x::Window, property: &str, r#type: x::Atom, value: u32) {
+        self.conn.send_and_check_request(&x::ChangeProperty {
+            mode: x::PropMode::Replace,
+            window,
+            property: self.atom(property),
+            r#type,
+            data: &[value],
+        }).unwrap();
+    }
+
+    // map it and wait for panko to let it through
+    fn map(&mut self, window: x::Window) {
+        self.conn.send_and_check_request(&x::MapWindow {
+            window,
+        }).unwrap();
+
+        self.wait_for("window to be mapped", |s| {
+            let attrs = s.conn.wait_for_reply(s.conn.send_request(&x::GetWindowAttributes {
+                window,
+            })).unwrap();
+            (attrs.map_state() == x::MapState::Viewable).then_some(())
+        });
+    }
+
+    fn geometry(&self, window: x::Window) -> Geometry {
+        let reply = self.conn.wait_for_reply(self.conn.send_request(&x::GetGeometry {
+            drawable: x::Drawable::Window(window),
+        })).unwrap();
+
+        Geometry {
+            x: reply.x(),
+            y: reply.y(),
+            width: reply.width(),
+            height: reply.height(),
+            border_width: reply.border_width(),
+        }
+    }
+
+    fn focus(&self) -> x::Window {
+        self.conn.wait_for_reply(self.conn.send_request(&x::GetInputFocus {})).unwrap().focus()
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        if let Some(panko) = self.panko.as_mut() {
+            let _ = panko.kill();
+            let _ = panko.wait();
+        }
+        let _ = self.xvfb.kill();
+        let _ = self.xvfb.wait();
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+// start Xvfb on whatever display it finds free, returning it and the display name
+fn start_xvfb() -> Option<(Child, String)> {
+    // Xvfb writes the display number it chose to this pipe when it's ready for clients
+    let mut fds = [0; 2];
+    assert_eq!(unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) }, 0);
+    let (read_fd, write_fd) = (fds[0], fds[1]);
+
+    let mut command = Command::new("Xvfb");
+    command
+        .arg("-displayfd").arg(write_fd.to_string())
+        .arg("-screen").arg("0").arg(format!("{}x{}x24", SCREEN_WIDTH, SCREEN_HEIGHT))
+        .arg("-nolisten").arg("tcp")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+
+    // only Xvfb gets the write end, so other tests' children can't hold it open
+    unsafe {
+        command.pre_exec(move || {
+            match libc::fcntl(write_fd, libc::F_SETFD, 0) {
+                -1 => Err(std::io::Error::last_os_error()),
+                _ => Ok(()),
+            }
+        });
+    }
+
+    let spawned = command.spawn();
+
+    unsafe { libc::close(write_fd) };
+    let mut pipe = BufReader::new(unsafe { fs::File::from_raw_fd(read_fd) });
+
+    let xvfb = match spawned {
+        Ok(child) => child,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            eprintln!("Xvfb not found, skipping");
+            return None;
+        },
+        Err(e) => panic!("couldn't start Xvfb: {}", e),
+    };
+
+    let mut display = String::new();
+    pipe.read_line(&mut display).expect("couldn't read display from Xvfb");
+    assert!(!display.trim().is_empty(), "Xvfb exited without choosing a display");
+
+    Some((xvfb, format!(":{}", display.trim())))
+}
+
+#[test]
+fn missing_screen_is_an_error_not_a_panic() {
+    let s = match Session::without_panko("") {
+        Some(s) => s,
+        None => return,
+    };
+
+    // Xvfb only has screen 0
+    let output = Command::new(env!("CARGO_BIN_EXE_panko"))
+        .env("DISPLAY", format!("{}.5", s.display))
+        .env("XDG_CONFIG_HOME", s.dir.join("config"))
+        .env("XDG_STATE_HOME", s.dir.join("state"))
+        .env_remove("RUST_LOG")
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .output()
+        .expect("couldn't run panko");
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!stderr.contains("panicked"), "panko panicked: {}", stderr);
+    assert_eq!(output.status.code(), Some(1), "panko didn't fail cleanly: {}", stderr);
+}
+
+#[test]
+fn new_window_gets_border_and_focus() {
+    let mut s = match Session::start("") {
+        Some(s) => s,
+        None => return,
+    };
+
+    let window = s.create_window(300, 200);
+    s.map(window);
+
+    let g = s.geometry(window);
+    assert_eq!(g.border_width, 2);
+    assert!(g.x >= 0 && g.y >= 0);
+    assert!(g.x + g.width as i16 + 4 <= SCREEN_WIDTH);
+    assert!(g.y + g.height as i16 + 4 <= SCREEN_HEIGHT);
+
+    s.wait_for("new window to get focus", |s| (s.focus() == window).then_some(()));
+}
+
+#[test]
+fn window_mapped_straight_after_creation_is_managed() {
+    let mut s = match Session::start("") {
+        Some(s) => s,
+        None => return,
+    };
+
+    // both requests go in one flush, so panko gets the CreateNotify and MapRequest back to back
+    let window = s.conn.generate_id();
+    s.conn.send_request(&x::CreateWindow {
+        depth: x::COPY_FROM_PARENT as u8,
+        wid: window,
+        parent: s.root,
+        x: 0,
+        y: 0,
+        width: 300,
+        height: 200,
+        border_width: 0,
+        class: x::WindowClass::InputOutput,
+        visual: x::COPY_FROM_PARENT,
+        value_list: &[],
+    });
+    s.conn.send_request(&x::MapWindow {
+        window,
+    });
+    s.conn.flush().unwrap();
+
+    s.wait_for("window to be mapped", |s| {
+        let attrs = s.conn.wait_for_reply(s.conn.send_request(&x::GetWindowAttributes {
+            window,
+        })).unwrap();
+        (attrs.map_state() == x::MapState::Viewable).then_some(())
+    });
+    assert_eq!(s.geometry(window).border_width, 2);
+    s.wait_for("new window to get focus", |s| (s.focus() == window).then_some(()));
+}
+
+#[test]
+fn override_redirect_is_checked_at_map_time() {
+    let mut s = match Session::start("") {
+        Some(s) => s,
+        None => return,
+    };
+
+    let window = s.create_window_with(300, 200, x::WindowClass::InputOutput, &[x::Cw::OverrideRedirect(true)]);
+    s.conn.send_and_check_request(&x::ChangeWindowAttributes {
+        window,
+        value_list: &[
+            x::Cw::OverrideRedirect(false),
+        ],
+    }).unwrap();
+    s.map(window);
+
+    assert_eq!(s.geometry(window).border_width, 2);
+}
+
+#[test]
+fn border_width_comes_from_config() {
+    let mut s = match Session::start("border_width = 5\n") {
+        Some(s) => s,
+        None => return,
+    };
+
+    let window = s.create_window(300, 200);
+    s.map(window);
+
+    assert_eq!(s.geometry(window).border_width, 5);
+}
+
+#[test]
+fn smart_borders_only_with_company() {
+    let mut s = match Session::start("smart_borders = true\n") {
+        Some(s) => s,
+        None => return,
+    };
+
+    let first = s.create_window(300, 200);
+    s.map(first);
+    s.wait_for("lone window to lose its border", |s| (s.geometry(first).border_width == 0).then_some(()));
+
+    let second = s.create_window(300, 200);
+    s.map(second);
+    s.wait_for("first window to get its border back", |s| (s.geometry(first).border_width == 2).then_some(()));
+    assert_eq!(s.geometry(second).border_width, 2);
+}
+
+#[test]
+fn new_window_stays_back_when_asked() {
+    let mut s = match Session::start("new_windows_in_front = false\n") {
+        Some(s) => s,
+        None => return,
+    };
+
+    let first = s.create_window(300, 200);
+    s.map(first);
+    let second = s.create_window(300, 200);
+    s.map(second);
+
+    // bottom to top
+    let tree = s.conn.wait_for_reply(s.conn.send_request(&x::QueryTree {
+        window: s.root,
+    })).unwrap();
+    let order: Vec<_> = tree.children().iter().filter(|&&w| w == first || w == second).collect();
+    assert_eq!(order, [&second, &first]);
+
+    assert_ne!(s.focus(), second);
+}
+
+#[test]
+fn splash_is_centred_without_border() {
+    let mut s = match Session::start("") {
+        Some(s) => s,
+        None => return,
+    };
+
+    let window = s.create_window(200, 100);
+    let splash = s.atom("_NET_WM_WINDOW_TYPE_SPLASH");
+    s.set_atom_property(window, "_NET_WM_WINDOW_TYPE", x::ATOM_ATOM, splash.resource_id());
+    s.map(window);
+
+    assert_eq!(s.geometry(window), Geometry {
+        x: (SCREEN_WIDTH - 200)/2,
+        y: (SCREEN_HEIGHT - 100)/2,
+        width: 200,
+        height: 100,
+        border_width: 0,
+    });
+}
+
+#[test]
+fn dialog_is_centred_over_its_parent() {
+    let mut s = match Session::start("") {
+        Some(s) => s,
+        None => return,
+    };
+
+    let parent = s.create_window(300, 200);
+    s.map(parent);
+    let p = s.geometry(parent);
+
+    let dialog = s.create_window(100, 50);
+    s.set_atom_property(dialog, "WM_TRANSIENT_FOR", x::ATOM_WINDOW, parent.resource_id());
+    s.map(dialog);
+    let d = s.geometry(dialog);
+
+    assert_eq!((d.width, d.height), (100, 50));
+
+    let parent_centre = (p.x + (p.width/2 + p.border_width) as i16, p.y + (p.height/2 + p.border_width) as i16);
+    let dialog_centre = (d.x + (d.width/2 + d.border_width) as i16, d.y + (d.height/2 + d.border_width) as i16);
+    assert_eq!(parent_centre, dialog_centre);
+}
+
+#[test]
+fn moveresize_window_places_it_on_screen() {
+    let mut s = match Session::start("") {
+        Some(s) => s,
+        None => return,
+    };
+
+    let window = s.create_window(300, 200);
+    s.map(window);
+
+    // like wmctrl -e 0,100,50,400,300, then again half off the right of the screen
+    let send = |s: &Session, x: i32, y: i32| {
+        let event = x::ClientMessageEvent::new(window, s.atom("_NET_MOVERESIZE_WINDOW"),
+            x::ClientMessageData::Data32([0xf << 8, x as u32, y as u32, 400, 300]));
+        s.conn.send_and_check_request(&x::SendEvent {
+            propagate: false,
+            destination: x::SendEventDest::Window(s.root),
+            event_mask: x::EventMask::SUBSTRUCTURE_REDIRECT | x::EventMask::SUBSTRUCTURE_NOTIFY,
+            event: &event,
+        }).unwrap();
+    };
+
+    send(&s, 100, 50);
+    s.wait_for("window to move", |s| (s.geometry(window).x == 100).then_some(()));
+    assert_eq!(s.geometry(window), Geometry { x: 100, y: 50, width: 400, height: 300, border_width: 2 });
+
+    send(&s, SCREEN_WIDTH as i32 - 200, 50);
+    s.wait_for("window to move", |s| (s.geometry(window).x != 100).then_some(()));
+    assert_eq!(s.geometry(window).x, SCREEN_WIDTH - 404);
+}
+
+#[test]
+fn supporting_window_names_itself_and_isnt_managed() {
+    let mut s = match Session::start("") {
+        Some(s) => s,
+        None => return,
+    };
+
+    let check = s.atom("_NET_SUPPORTING_WM_CHECK");
+    let supporting = |s: &Session, window: x::Window| {
+        let reply = s.conn.wait_for_reply(s.conn.send_request(&x::GetProperty {
+            delete: false,
+            window,
+            property: check,
+            r#type: x::ATOM_WINDOW,
+            long_offset: 0,
+            long_length: 1,
+        })).unwrap();
+        reply.value::<x::Window>().first().copied()
+    };
+
+    let window = supporting(&s, s.root).expect("no _NET_SUPPORTING_WM_CHECK on the root");
+    assert_eq!(supporting(&s, window), Some(window));
+
+    // once a client window has been managed, panko is past the CreateNotify for its own, which
+    // should have left it alone: unmapped, off screen and with no border
+    let client = s.create_window(300, 200);
+    s.map(client);
+    let attrs = s.conn.wait_for_reply(s.conn.send_request(&x::GetWindowAttributes {
+        window,
+    })).unwrap();
+    assert_eq!(attrs.map_state(), x::MapState::Unmapped);
+    assert_eq!(s.geometry(window), Geometry { x: -1, y: -1, width: 1, height: 1, border_width: 0 });
+}
+
+#[test]
+fn adopts_existing_windows_around_odd_ones() {
+    let mut s = match Session::without_panko("") {
+        Some(s) => s,
+        None => return,
+    };
+
+    // before panko arrives, so these are all just sitting there
+    let first = s.create_window(300, 200);
+    s.map(first);
+    let input_only = s.create_window_with(300, 200, x::WindowClass::InputOnly, &[]);
+    s.map(input_only);
+    let override_redirect = s.create_window_with(300, 200, x::WindowClass::InputOutput, &[x::Cw::OverrideRedirect(true)]);
+    s.map(override_redirect);
+    let unmapped = s.create_window(300, 200);
+    let gone = s.create_window(300, 200);
+    s.map(gone);
+    s.conn.send_and_check_request(&x::DestroyWindow {
+        window: gone,
+    }).unwrap();
+    let second = s.create_window(300, 200);
+    s.map(second);
+
+    s.start_panko();
+
+    for window in [first, second] {
+        s.wait_for("existing window to be adopted", |s| (s.geometry(window).border_width == 2).then_some(()));
+    }
+    assert_eq!(s.geometry(override_redirect).border_width, 0);
+
+    // known about, even though it wasn't showing
+    s.map(unmapped);
+    assert_eq!(s.geometry(unmapped).border_width, 2);
+}
+
+#[test]
+fn sigterm_lets_go_of_windows() {
+    let mut s = match Session::start("") {
+        Some(s) => s,
+        None => return,
+    };
+
+    let window = s.create_window(300, 200);
+    s.map(window);
+    let managed = s.geometry(window);
+    assert_eq!(managed.border_width, 2);
+
+    let mut panko = s.panko.take().unwrap();
+    unsafe { libc::kill(panko.id() as libc::pid_t, libc::SIGTERM) };
+    let start = Instant::now();
+    let status = loop {
+        if let Some(status) = panko.try_wait().unwrap() {
+            break status;
+        }
+        assert!(start.elapsed() < TIMEOUT, "panko didn't quit on SIGTERM");
+        thread::sleep(Duration::from_millis(10));
+    };
+    assert!(status.success(), "panko didn't quit cleanly: {}", status);
+
+    // no border, and the contents haven't moved
+    let released = s.geometry(window);
+    assert_eq!(released.border_width, 0);
+    assert_eq!((released.x, released.y), (managed.x + 2, managed.y + 2));
+
+    let wm_state = s.atom("WM_STATE");
+    let reply = s.conn.wait_for_reply(s.conn.send_request(&x::GetProperty {
+        delete: false,
+        window,
+        property: wm_state,
+        r#type: wm_state,
+        long_offset: 0,
+        long_length: 2,
+    })).unwrap();
+    assert_eq!(reply.value::<u32>().first(), Some(&0));
+}
+
+#[test]
+fn fixed_size_window_cant_be_resized() {
+    let mut s = match Session::start("") {
+        Some(s) => s,
+        None => return,
+    };
+
+    let allowed = s.atom("_NET_WM_ALLOWED_ACTIONS");
+    let actions = |s: &Session, window: x::Window| {
+        let reply = s.conn.wait_for_reply(s.conn.send_request(&x::GetProperty {
+            delete: false,
+            window,
+            property: allowed,
+            r#type: x::ATOM_ATOM,
+            long_offset: 0,
+            long_length: 32,
+        })).unwrap();
+        reply.value::<x::Atom>().to_vec()
+    };
+    let (resize, close) = (s.atom("_NET_WM_ACTION_RESIZE"), s.atom("_NET_WM_ACTION_CLOSE"));
+
+    let normal = s.create_window(300, 200);
+    s.map(normal);
+    let normal_actions = s.wait_for("allowed actions", |s| Some(actions(s, normal)).filter(|a| !a.is_empty()));
+    assert!(normal_actions.contains(&resize) && normal_actions.contains(&close));
+
+    // PMinSize and PMaxSize, both 300x200
+    let fixed = s.create_window(300, 200);
+    s.conn.send_and_check_request(&x::ChangeProperty {
+        mode: x::PropMode::Replace,
+        window: fixed,
+        property: x::ATOM_WM_NORMAL_HINTS,
+        r#type: x::ATOM_WM_SIZE_HINTS,
+        data: &[1u32 << 4 | 1 << 5, 0, 0, 0, 0, 300, 200, 300, 200, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    }).unwrap();
+    s.map(fixed);
+    let fixed_actions = s.wait_for("allowed actions", |s| Some(actions(s, fixed)).filter(|a| !a.is_empty()));
+    assert!(!fixed_actions.contains(&resize) && fixed_actions.contains(&close));
+}
+
+#[test]
+fn ignored_window_configures_itself() {
+    let mut s = match Session::start("rule = class=Ignored ignore\n") {
+        Some(s) => s,
+        None => return,
+    };
+
+    let window = s.create_window(300, 200);
+    s.conn.send_and_check_request(&x::ChangeProperty {
+        mode: x::PropMode::Replace,
+        window,
+        property: x::ATOM_WM_CLASS,
+        r#type: x::ATOM_STRING,
+        data: b"ignored\0Ignored\0",
+    }).unwrap();
+    s.map(window);
+
+    s.conn.send_and_check_request(&x::ConfigureWindow {
+        window,
+        value_list: &[
+            x::ConfigWindow::X(40),
+            x::ConfigWindow::Y(50),
+            x::ConfigWindow::Width(400),
+        ],
+    }).unwrap();
+    s.wait_for("window to move and resize", |s| {
+        let g = s.geometry(window);
+        (g == Geometry { x: 40, y: 50, width: 400, height: 200, border_width: 0 }).then_some(())
+    });
+}
+
+#[test]
+fn unknown_states_are_kept() {
+    let mut s = match Session::start("") {
+        Some(s) => s,
+        None => return,
+    };
+
+    let (above, skip_taskbar) = (s.atom("_NET_WM_STATE_ABOVE"), s.atom("_NET_WM_STATE_SKIP_TASKBAR"));
+    let window = s.create_window(300, 200);
+    s.conn.send_and_check_request(&x::ChangeProperty {
+        mode: x::PropMode::Replace,
+        window,
+        property: s.atom("_NET_WM_STATE"),
+        r#type: x::ATOM_ATOM,
+        data: &[above, skip_taskbar],
+    }).unwrap();
+    s.map(window);
+
+    // panko writes the states it takes on before it maps the window
+    let reply = s.conn.wait_for_reply(s.conn.send_request(&x::GetProperty {
+        delete: false,
+        window,
+        property: s.atom("_NET_WM_STATE"),
+        r#type: x::ATOM_ATOM,
+        long_offset: 0,
+        long_length: 32,
+    })).unwrap();
+    let mut atoms = reply.value::<x::Atom>().to_vec();
+    atoms.sort();
+    let mut expected = vec![above, skip_taskbar];
+    expected.sort();
+    assert_eq!(atoms, expected);
+}
+
+#[test]
+fn adopted_window_stays_where_it_was() {
+    let mut s = match Session::without_panko("") {
+        Some(s) => s,
+        None => return,
+    };
+
+    let window = s.create_window(300, 200);
+    s.conn.send_and_check_request(&x::ConfigureWindow {
+        window,
+        value_list: &[
+            x::ConfigWindow::X(100),
+            x::ConfigWindow::Y(150),
+        ],
+    }).unwrap();
+    s.map(window);
+
+    s.start_panko();
+
+    // the border goes around it, and what's inside doesn't move
+    s.wait_for("existing window to be adopted", |s| (s.geometry(window).border_width == 2).then_some(()));
+    assert_eq!(s.geometry(window), Geometry { x: 98, y: 148, width: 300, height: 200, border_width: 2 });
+}